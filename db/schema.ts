@@ -6,9 +6,11 @@ import {
   timestamp,
   serial,
   unique,
+  uniqueIndex,
   jsonb,
   bigserial,
 } from 'drizzle-orm/pg-core';
+import { sql } from 'drizzle-orm';
 
 // ─── 1. panels ───────────────────────────────────────────────────────────────
 export const panels = pgTable('panels', {
@@ -219,7 +221,11 @@ export const cliPasskeyCredentials = pgTable('cli_passkey_credentials', {
   status:        text('status').notNull().default('active'), // active | revoked
   created_at:    timestamp('created_at', { withTimezone: true, mode: 'date' }).notNull().defaultNow(),
   last_used_at:  timestamp('last_used_at', { withTimezone: true, mode: 'date' }),
-}, (t) => [unique().on(t.user_id, t.device_name)]);
+  revoked_at:    timestamp('revoked_at', { withTimezone: true, mode: 'date' }),
+}, (t) => [
+  // One active key per device; revoked keys are kept for audit (see migration 006).
+  uniqueIndex('uq_cli_passkey_active_device').on(t.user_id, t.device_name).where(sql`status = 'active'`),
+]);
 
 // ─── 16. Founder signing keys ─────────────────────────────────────────────────
 
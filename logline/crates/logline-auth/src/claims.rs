@@ -0,0 +1,237 @@
+//! Typed view over Supabase access-token claims.
+//!
+//! Mirrors what Supabase Auth puts in every access token plus the extra claims
+//! injected by the `app.custom_access_token` hook (tenant, app memberships,
+//! capabilities).
+
+use crate::{Result, VerifiedJwt};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Capability granted to the bootstrap founder account.
+pub const FOUNDER_CAPABILITY: &str = "founder";
+
+/// App role that grants administrative access to an app.
+pub const APP_ADMIN_ROLE: &str = "app_admin";
+
+/// One entry of the `amr` (authentication methods references) claim.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "AmrEntry")]
+pub struct AuthMethodRef {
+    /// Method name (`password`, `otp`, `oauth`, `mfa/totp`, ...).
+    pub method: String,
+    /// When the method was used (seconds since epoch), if present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+}
+
+/// Supabase emits objects; RFC 8176 issuers emit plain strings. Accept both.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AmrEntry {
+    Object {
+        method: String,
+        #[serde(default)]
+        timestamp: Option<i64>,
+    },
+    Name(String),
+}
+
+impl From<AmrEntry> for AuthMethodRef {
+    fn from(entry: AmrEntry) -> Self {
+        match entry {
+            AmrEntry::Object { method, timestamp } => Self { method, timestamp },
+            AmrEntry::Name(method) => Self {
+                method,
+                timestamp: None,
+            },
+        }
+    }
+}
+
+/// An app membership as emitted in the `app_memberships` claim.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppMembershipClaim {
+    /// Tenant the app belongs to.
+    pub tenant_id: String,
+    /// App id.
+    pub app_id: String,
+    /// Role within the app (`member` or `app_admin`).
+    pub role: String,
+}
+
+/// Typed Supabase access-token claims.
+///
+/// Unknown claims are ignored; every non-standard field is optional so tokens
+/// minted before the hook was deployed still parse.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupabaseClaims {
+    /// Subject (user id).
+    pub sub: String,
+
+    /// Postgres role (`authenticated`, `anon`, `service_role`).
+    #[serde(default)]
+    pub role: Option<String>,
+
+    /// User email.
+    #[serde(default)]
+    pub email: Option<String>,
+
+    /// Supabase Auth session id.
+    #[serde(default)]
+    pub session_id: Option<String>,
+
+    /// Authenticator assurance level (`aal1`, `aal2`).
+    #[serde(default)]
+    pub aal: Option<String>,
+
+    /// Primary tenant injected by the access-token hook.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+
+    /// Legacy name for `tenant_id`, still emitted by the hook.
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+
+    /// Primary app injected by the access-token hook.
+    #[serde(default)]
+    pub app_id: Option<String>,
+
+    /// App memberships in the primary tenant.
+    #[serde(default)]
+    pub app_memberships: Vec<AppMembershipClaim>,
+
+    /// Global capabilities (e.g. `founder`).
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+
+    /// Authentication methods used to obtain this session.
+    #[serde(default)]
+    pub amr: Vec<AuthMethodRef>,
+
+    /// Issued-at (seconds since epoch).
+    #[serde(default)]
+    pub iat: Option<i64>,
+
+    /// Expiry (seconds since epoch).
+    #[serde(default)]
+    pub exp: Option<i64>,
+}
+
+impl SupabaseClaims {
+    /// Parse typed claims from a raw claims object.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::Json`] if `sub` is missing or a known claim has
+    /// an unexpected type.
+    pub fn from_value(claims: &Value) -> Result<Self> {
+        Ok(Self::deserialize(claims)?)
+    }
+
+    /// Primary tenant, preferring `tenant_id` over the legacy `workspace_id`.
+    #[must_use]
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant_id.as_deref().or(self.workspace_id.as_deref())
+    }
+
+    /// True if the token carries capability `cap`.
+    #[must_use]
+    pub fn has_capability(&self, cap: &str) -> bool {
+        self.capabilities.iter().any(|c| c == cap)
+    }
+
+    /// True if the token carries the `founder` capability.
+    #[must_use]
+    pub fn is_founder(&self) -> bool {
+        self.has_capability(FOUNDER_CAPABILITY)
+    }
+
+    /// Role in `tenant`/`app`, if the user is a member.
+    #[must_use]
+    pub fn app_role(&self, tenant: &str, app: &str) -> Option<&str> {
+        self.app_memberships
+            .iter()
+            .find(|m| m.tenant_id == tenant && m.app_id == app)
+            .map(|m| m.role.as_str())
+    }
+
+    /// True if the user is a member of `tenant`/`app` (any role).
+    #[must_use]
+    pub fn is_app_member(&self, tenant: &str, app: &str) -> bool {
+        self.app_role(tenant, app).is_some()
+    }
+
+    /// True if the user is `app_admin` of `tenant`/`app`.
+    #[must_use]
+    pub fn is_app_admin(&self, tenant: &str, app: &str) -> bool {
+        self.app_role(tenant, app) == Some(APP_ADMIN_ROLE)
+    }
+
+    /// True if `method` appears in the `amr` claim.
+    #[must_use]
+    pub fn has_auth_method(&self, method: &str) -> bool {
+        self.amr.iter().any(|m| m.method == method)
+    }
+}
+
+impl VerifiedJwt {
+    /// Typed Supabase view over the verified claims.
+    ///
+    /// # Errors
+    ///
+    /// See [`SupabaseClaims::from_value`].
+    pub fn supabase_claims(&self) -> Result<SupabaseClaims> {
+        SupabaseClaims::from_value(&self.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        serde_json::json!({
+            "sub": "u-1",
+            "role": "authenticated",
+            "email": "op@example.com",
+            "session_id": "sess-1",
+            "aal": "aal1",
+            "workspace_id": "acme",
+            "tenant_id": "acme",
+            "app_id": "ublx",
+            "app_memberships": [
+                {"tenant_id": "acme", "app_id": "ublx", "role": "app_admin"},
+                {"tenant_id": "acme", "app_id": "llm-gateway", "role": "member"}
+            ],
+            "capabilities": ["founder"],
+            "amr": [{"method": "password", "timestamp": 1_700_000_000}, "otp"],
+            "exp": 1_700_003_600
+        })
+    }
+
+    #[test]
+    fn parses_hook_claims() {
+        let c = SupabaseClaims::from_value(&sample()).unwrap();
+        assert_eq!(c.tenant(), Some("acme"));
+        assert!(c.is_founder());
+        assert!(c.is_app_admin("acme", "ublx"));
+        assert!(!c.is_app_admin("acme", "llm-gateway"));
+        assert!(c.is_app_member("acme", "llm-gateway"));
+        assert!(c.has_auth_method("password"));
+        assert!(c.has_auth_method("otp"));
+    }
+
+    #[test]
+    fn tolerates_pre_hook_tokens() {
+        let c = SupabaseClaims::from_value(&serde_json::json!({
+            "sub": "u-2",
+            "workspace_id": "acme"
+        }))
+        .unwrap();
+        assert_eq!(c.tenant(), Some("acme"));
+        assert!(!c.has_capability("founder"));
+        assert!(c.app_memberships.is_empty());
+    }
+}
@@ -69,7 +69,7 @@ impl Default for CookieOptions {
     }
 }
 
-fn cookie_name(opts: &CookieOptions) -> Result<String> {
+pub(crate) fn cookie_name(opts: &CookieOptions) -> Result<String> {
     if opts.use_host_prefix && opts.domain.is_none() {
         // Enforce __Host- cookie requirements.
         if opts.path != "/" {
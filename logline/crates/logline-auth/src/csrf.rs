@@ -0,0 +1,203 @@
+//! CSRF token helpers.
+//!
+//! `SameSite=Lax` cookies already block most cross-site POSTs; deployments
+//! that need `SameSite=None` (embedded apps, cross-site APIs) must check a
+//! CSRF token on every state-changing request. Two patterns are supported:
+//!
+//! - **Double submit**: the token is set in a readable cookie and echoed by
+//!   the client in a header or form field; see [`CsrfTokens::verify_double_submit`].
+//! - **Synchronizer**: the token is stored server-side (e.g. inside the
+//!   sealed session payload) and compared with [`verify_synchronizer_token`].
+//!
+//! Tokens are `<nonce>.<mac>`, where the MAC covers the nonce and the session
+//! cookie value, so a token minted for one session is useless in another.
+
+use crate::session::{mac, random_bytes};
+use crate::{CookieOptions, Error, Result, build_set_cookie};
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use subtle::ConstantTimeEq;
+
+/// Default name of the CSRF cookie (before any `__Host-` prefix).
+pub const CSRF_COOKIE_NAME: &str = "logline_csrf";
+
+/// Default header clients echo the token in.
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+
+const MIN_SECRET_LEN: usize = 32;
+
+/// Issues and verifies session-bound CSRF tokens.
+#[derive(Clone)]
+pub struct CsrfTokens {
+    mac_key: [u8; 32],
+}
+
+impl CsrfTokens {
+    /// Create a token issuer from a secret of at least 32 bytes.
+    ///
+    /// Use a secret distinct from the session cookie key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Csrf`] if the secret is too short.
+    pub fn new(secret: &[u8]) -> Result<Self> {
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(Error::Csrf(format!(
+                "CSRF secret must be at least {MIN_SECRET_LEN} bytes"
+            )));
+        }
+        Ok(Self {
+            mac_key: mac(secret, b"logline-csrf"),
+        })
+    }
+
+    /// Generate a token bound to `session` (the raw session cookie value).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Csrf`] if the system random source fails.
+    pub fn generate(&self, session: &str) -> Result<String> {
+        let nonce = random_bytes::<16>().map_err(|e| Error::Csrf(format!("no randomness: {e}")))?;
+        let nonce = URL_SAFE_NO_PAD.encode(nonce);
+        let tag = URL_SAFE_NO_PAD.encode(self.tag(&nonce, session));
+        Ok(format!("{nonce}.{tag}"))
+    }
+
+    /// Verify that `token` was issued by this key for `session`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Csrf`] if the token is malformed or bound to another
+    /// session.
+    pub fn verify(&self, session: &str, token: &str) -> Result<()> {
+        let (nonce, tag) = token
+            .trim()
+            .split_once('.')
+            .ok_or_else(|| Error::Csrf("malformed token".to_string()))?;
+        let tag = URL_SAFE_NO_PAD
+            .decode(tag)
+            .map_err(|_| Error::Csrf("malformed token".to_string()))?;
+        if bool::from(self.tag(nonce, session).ct_eq(tag.as_slice())) {
+            Ok(())
+        } else {
+            Err(Error::Csrf("token does not match session".to_string()))
+        }
+    }
+
+    /// Double-submit check: the cookie and the submitted token must both be
+    /// present, equal, and bound to `session`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Csrf`] if either token is missing, they differ, or
+    /// they were not issued for `session`.
+    pub fn verify_double_submit(
+        &self,
+        session: &str,
+        cookie_token: Option<&str>,
+        submitted_token: Option<&str>,
+    ) -> Result<()> {
+        let (Some(cookie_token), Some(submitted_token)) = (cookie_token, submitted_token) else {
+            return Err(Error::Csrf("missing token".to_string()));
+        };
+        verify_synchronizer_token(cookie_token, submitted_token)?;
+        self.verify(session, submitted_token)
+    }
+
+    fn tag(&self, nonce: &str, session: &str) -> [u8; 32] {
+        // Length-prefix the nonce so (nonce, session) pairs cannot collide.
+        let msg = format!("{}:{nonce}:{session}", nonce.len());
+        mac(&self.mac_key, msg.as_bytes())
+    }
+}
+
+impl std::fmt::Debug for CsrfTokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CsrfTokens").finish_non_exhaustive()
+    }
+}
+
+/// Synchronizer check: compare the server-side `expected` token with the
+/// `submitted` one in constant time.
+///
+/// # Errors
+///
+/// Returns [`Error::Csrf`] if the tokens differ or `expected` is empty.
+pub fn verify_synchronizer_token(expected: &str, submitted: &str) -> Result<()> {
+    let expected = expected.trim();
+    if !expected.is_empty() && bool::from(expected.as_bytes().ct_eq(submitted.trim().as_bytes())) {
+        Ok(())
+    } else {
+        Err(Error::Csrf("token mismatch".to_string()))
+    }
+}
+
+/// Cookie options for the CSRF cookie, derived from the session cookie's.
+///
+/// Keeps path/domain/secure/SameSite/`__Host-` semantics but renames the
+/// cookie and drops `HttpOnly` so client code can read and echo the token.
+#[must_use]
+pub fn csrf_cookie_options(session_opts: &CookieOptions) -> CookieOptions {
+    CookieOptions {
+        name: CSRF_COOKIE_NAME.to_string(),
+        http_only: false,
+        ..session_opts.clone()
+    }
+}
+
+/// Build a `Set-Cookie` header value for a CSRF token.
+///
+/// # Errors
+///
+/// See [`build_set_cookie`].
+pub fn build_csrf_cookie(token: &str, session_opts: &CookieOptions) -> Result<String> {
+    build_set_cookie(token, &csrf_cookie_options(session_opts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_bound_to_session() {
+        let csrf = CsrfTokens::new(&[7; 32]).unwrap();
+        let token = csrf.generate("session-a").unwrap();
+        csrf.verify("session-a", &token).unwrap();
+        assert!(matches!(
+            csrf.verify("session-b", &token),
+            Err(Error::Csrf(_))
+        ));
+        assert!(csrf.verify("session-a", "garbage").is_err());
+
+        let other = CsrfTokens::new(&[8; 32]).unwrap();
+        assert!(other.verify("session-a", &token).is_err());
+    }
+
+    #[test]
+    fn double_submit_requires_matching_pair() {
+        let csrf = CsrfTokens::new(&[7; 32]).unwrap();
+        let token = csrf.generate("s").unwrap();
+        csrf.verify_double_submit("s", Some(&token), Some(&token))
+            .unwrap();
+        assert!(csrf.verify_double_submit("s", Some(&token), None).is_err());
+
+        let second = csrf.generate("s").unwrap();
+        assert!(
+            csrf.verify_double_submit("s", Some(&token), Some(&second))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn synchronizer_and_cookie() {
+        assert!(verify_synchronizer_token("abc", "abc").is_ok());
+        assert!(verify_synchronizer_token("abc", "abd").is_err());
+        assert!(verify_synchronizer_token("", "").is_err());
+
+        let cookie = build_csrf_cookie("t.v", &CookieOptions::default()).unwrap();
+        assert!(cookie.starts_with("__Host-logline_csrf=t.v;"));
+        assert!(!cookie.contains("HttpOnly"));
+        assert!(cookie.contains("Secure"));
+    }
+}
@@ -28,6 +28,30 @@ pub enum Error {
     #[error("token validation failed: {0}")]
     Validation(String),
 
+    /// Token is valid but has been revoked.
+    #[error("token revoked: {0}")]
+    Revoked(crate::RevokedBy),
+
+    /// The revocation list could not be consulted.
+    #[error("revocation check failed: {0}")]
+    Revocation(String),
+
+    /// Session cookie is malformed, tampered with, or protected by an unknown key.
+    #[error("invalid session cookie: {0}")]
+    InvalidCookie(String),
+
+    /// Session cookie is authentic but past its embedded expiry.
+    #[error("session cookie expired")]
+    CookieExpired,
+
+    /// CSRF token missing, malformed, or not valid for the session.
+    #[error("CSRF check failed: {0}")]
+    Csrf(String),
+
+    /// Passkey assertion rejected.
+    #[error("passkey verification failed: {0}")]
+    Passkey(String),
+
     /// An error occurred while performing HTTP requests.
     #[cfg(feature = "fetch-reqwest")]
     #[error(transparent)]
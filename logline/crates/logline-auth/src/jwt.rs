@@ -1,11 +1,13 @@
 //! JWT verification using JWKS.
 
-use crate::{Error, Result};
+use crate::{Error, Result, RevocationCheck, RevocationKey};
 
 use base64::Engine;
 use jsonwebtoken::{Algorithm, DecodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "cache")]
@@ -139,6 +141,24 @@ impl VerifiedJwt {
     pub fn exp(&self) -> Option<i64> {
         self.claim("exp").and_then(|v| v.as_i64())
     }
+
+    /// Convenience accessor for `iat`.
+    #[must_use]
+    pub fn iat(&self) -> Option<i64> {
+        self.claim("iat").and_then(Value::as_i64)
+    }
+
+    /// Convenience accessor for `jti`.
+    #[must_use]
+    pub fn jti(&self) -> Option<&str> {
+        self.claim("jti").and_then(Value::as_str)
+    }
+
+    /// Convenience accessor for the Supabase `session_id` claim.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        self.claim("session_id").and_then(Value::as_str)
+    }
 }
 
 #[cfg(feature = "cache")]
@@ -152,12 +172,28 @@ struct CachedJwks {
 static JWKS_CACHE: Lazy<DashMap<String, CachedJwks>> = Lazy::new(DashMap::new);
 
 /// Verifies JWTs against a JWKS.
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct JwtVerifier {
-    _priv: (),
+    revocation: Option<Arc<dyn RevocationCheck>>,
+}
+
+impl fmt::Debug for JwtVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtVerifier")
+            .field("revocation", &self.revocation.is_some())
+            .finish()
+    }
 }
 
 impl JwtVerifier {
+    /// Consult `check` after signature and claim validation; revoked tokens
+    /// fail with [`Error::Revoked`].
+    #[must_use]
+    pub fn with_revocation(mut self, check: Arc<dyn RevocationCheck>) -> Self {
+        self.revocation = Some(check);
+        self
+    }
+
     /// Verify a token using the configured JWKS URL.
     ///
     /// Requires the `fetch-reqwest` feature.
@@ -177,19 +213,44 @@ impl JwtVerifier {
         source: JwksSource,
         opts: VerifyOptions,
     ) -> Result<VerifiedJwt> {
-        let header = jsonwebtoken::decode_header(token)
-            .map_err(|e| Error::InvalidJwt(format!("failed to decode header: {e}")))?;
+        let header = check_header(token, &opts)?;
+        let jwks = self.load_jwks(&source, &opts).await?;
+        self.verify_decoded(token, &header, &jwks, &opts)
+    }
 
-        if !opts.allowed_algs.contains(&header.alg) {
-            return Err(Error::UnsupportedAlg(header.alg));
-        }
+    /// Verify a token against a key set already in hand, without any I/O.
+    ///
+    /// Useful for blocking callers (e.g. a CLI) that fetch or cache the JWKS themselves.
+    ///
+    /// # Errors
+    ///
+    /// Same failures as [`verify_with_source`](Self::verify_with_source), minus JWKS loading.
+    pub fn verify_with_jwks(
+        &self,
+        token: &str,
+        jwks: &JwksSet,
+        opts: &VerifyOptions,
+    ) -> Result<VerifiedJwt> {
+        let header = check_header(token, opts)?;
+        self.verify_decoded(token, &header, jwks, opts)
+    }
 
-        if opts.require_kid && header.kid.as_deref().unwrap_or("").is_empty() {
-            return Err(Error::InvalidJwt("missing kid".to_string()));
+    fn verify_decoded(
+        &self,
+        token: &str,
+        header: &Header,
+        jwks: &JwksSet,
+        opts: &VerifyOptions,
+    ) -> Result<VerifiedJwt> {
+        let verified = verify_against_jwks(token, header, jwks, opts)?;
+
+        if let Some(check) = &self.revocation {
+            if let Some(reason) = check.check(&RevocationKey::from_verified(&verified))? {
+                return Err(Error::Revoked(reason));
+            }
         }
 
-        let jwks = self.load_jwks(&source, &opts).await?;
-        verify_against_jwks(token, &header, &jwks, &opts)
+        Ok(verified)
     }
 
     async fn load_jwks(&self, source: &JwksSource, opts: &VerifyOptions) -> Result<JwksSet> {
@@ -277,6 +338,21 @@ impl JwtVerifier {
     }
 }
 
+fn check_header(token: &str, opts: &VerifyOptions) -> Result<Header> {
+    let header = jsonwebtoken::decode_header(token)
+        .map_err(|e| Error::InvalidJwt(format!("failed to decode header: {e}")))?;
+
+    if !opts.allowed_algs.contains(&header.alg) {
+        return Err(Error::UnsupportedAlg(header.alg));
+    }
+
+    if opts.require_kid && header.kid.as_deref().unwrap_or("").is_empty() {
+        return Err(Error::InvalidJwt("missing kid".to_string()));
+    }
+
+    Ok(header)
+}
+
 fn verify_against_jwks(
     token: &str,
     header: &Header,
@@ -357,8 +433,7 @@ fn decoding_key_from_jwk(jwk: &Jwk) -> Result<DecodingKey> {
             Ok(DecodingKey::from_ec_components(x, y)?)
         }
         "OKP" => {
-            // jsonwebtoken supports EdDSA in recent versions. It expects a DER-encoded key.
-            // Many providers publish Ed25519 as OKP + x (public key bytes base64url).
+            // Ed25519 is published as OKP + x (raw public key bytes, base64url).
             let crv = jwk.crv.as_deref().unwrap_or("");
             if crv != "Ed25519" {
                 return Err(Error::Jwks(format!("unsupported OKP curve: {crv}")));
@@ -370,7 +445,14 @@ fn decoding_key_from_jwk(jwk: &Jwk) -> Result<DecodingKey> {
             let pubkey = base64::engine::general_purpose::URL_SAFE_NO_PAD
                 .decode(x)
                 .map_err(|e| Error::Jwks(format!("invalid okp x: {e}")))?;
-            Ok(DecodingKey::from_ed_der(&ed25519_spki_der(&pubkey)))
+            if pubkey.len() != 32 {
+                return Err(Error::Jwks(format!(
+                    "invalid okp x: expected 32 bytes, got {}",
+                    pubkey.len()
+                )));
+            }
+            // The rust_crypto backend reads the raw 32-byte key, not an SPKI document.
+            Ok(DecodingKey::from_ed_components(x)?)
         }
         other => Err(Error::Jwks(format!("unsupported kty: {other}"))),
     }
@@ -409,29 +491,6 @@ fn validate_issuer_audience(verified: &VerifiedJwt, opts: &VerifyOptions) -> Res
     Ok(())
 }
 
-fn ed25519_spki_der(pubkey32: &[u8]) -> Vec<u8> {
-    // SubjectPublicKeyInfo for Ed25519:
-    // SEQUENCE {
-    //   SEQUENCE { OID 1.3.101.112 }
-    //   BIT STRING (pubkey)
-    // }
-    // This is a tiny DER builder just for this structure.
-
-    // OID 1.3.101.112 DER: 06 03 2B 65 70
-    let mut alg_id: Vec<u8> = vec![0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x70];
-
-    // BIT STRING: 03 21 00 <32 bytes>
-    let mut bit_string: Vec<u8> = vec![0x03, 0x21, 0x00];
-    bit_string.extend_from_slice(&pubkey32[..32]);
-
-    // Outer SEQUENCE length = alg_id + bit_string
-    let len = alg_id.len() + bit_string.len();
-    let mut out: Vec<u8> = vec![0x30, len as u8];
-    out.append(&mut alg_id);
-    out.append(&mut bit_string);
-    out
-}
-
 fn now_epoch_ms() -> u128 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -507,6 +566,110 @@ fn parse_cache_control_max_age(cc: &str) -> Option<u64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::MemoryRevocationList;
+    use jsonwebtoken::EncodingKey;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    // RFC 8032 test vector 1.
+    const ED25519_SEED: [u8; 32] = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c,
+        0xc4, 0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae,
+        0x7f, 0x60,
+    ];
+    const ED25519_PUBLIC_B64URL: &str = "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo";
+
+    /// Drive a future that never actually waits (no network involved).
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    fn sign(claims: &Value) -> String {
+        let mut pkcs8 = vec![
+            0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22,
+            0x04, 0x20,
+        ];
+        pkcs8.extend_from_slice(&ED25519_SEED);
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("k1".to_string());
+        jsonwebtoken::encode(&header, claims, &EncodingKey::from_ed_der(&pkcs8)).unwrap()
+    }
+
+    fn jwks() -> JwksSource {
+        JwksSource::Json(
+            serde_json::json!({"keys": [{
+                "kty": "OKP", "crv": "Ed25519", "kid": "k1", "x": ED25519_PUBLIC_B64URL
+            }]})
+            .to_string(),
+        )
+    }
+
+    fn now_secs() -> i64 {
+        i64::try_from(now_epoch_ms() / 1000).unwrap()
+    }
+
+    #[test]
+    fn revoked_token_is_distinct_from_valid() {
+        let now = now_secs();
+        let token = sign(&serde_json::json!({
+            "sub": "u1", "jti": "j1", "session_id": "s1", "iat": now, "exp": now + 600
+        }));
+
+        let list = Arc::new(MemoryRevocationList::new());
+        let verifier = JwtVerifier::default().with_revocation(list.clone());
+
+        let ok = block_on(verifier.verify_with_source(&token, jwks(), VerifyOptions::default()));
+        assert_eq!(ok.unwrap().session_id(), Some("s1"));
+
+        list.revoke_session("s1");
+        let err = block_on(verifier.verify_with_source(&token, jwks(), VerifyOptions::default()));
+        assert!(matches!(
+            err,
+            Err(Error::Revoked(crate::RevokedBy::Session))
+        ));
+    }
+
+    #[test]
+    fn expired_token_is_not_reported_as_revoked() {
+        let now = now_secs();
+        let token = sign(&serde_json::json!({"sub": "u1", "iat": now - 7200, "exp": now - 3600}));
+
+        let list = Arc::new(MemoryRevocationList::new());
+        list.revoke_subject_before("u1", now);
+        let verifier = JwtVerifier::default().with_revocation(list);
+
+        let err = block_on(verifier.verify_with_source(&token, jwks(), VerifyOptions::default()));
+        assert!(matches!(err, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn sync_verification_matches_async() {
+        let now = now_secs();
+        let token = sign(&serde_json::json!({"sub": "u1", "iat": now, "exp": now + 600}));
+        let JwksSource::Json(json) = jwks() else {
+            unreachable!()
+        };
+        let set: JwksSet = serde_json::from_str(&json).unwrap();
+
+        let verified = JwtVerifier::default()
+            .verify_with_jwks(&token, &set, &VerifyOptions::default())
+            .unwrap();
+        assert_eq!(verified.sub(), Some("u1"));
+
+        let other = JwksSet { keys: Vec::new() };
+        assert!(
+            JwtVerifier::default()
+                .verify_with_jwks(&token, &other, &VerifyOptions::default())
+                .is_err()
+        );
+    }
 
     #[test]
     fn cache_control_parser() {
@@ -1,11 +1,16 @@
 //! logline-auth
 //!
 //! Authentication helpers for Logline runtime services and clients.
-//! It focuses on three recurring problems:
+//! It focuses on a few recurring problems:
 //!
 //! - **Verifying JWTs using a JWKS** (kid selection, algorithm allow-list, iss/aud/leeway checks)
-//! - **Deriving a tenant** from request host or token claims
+//! - **Reading Supabase claims** as a typed view (capabilities, app memberships, `amr`)
+//! - **Rejecting revoked tokens** via a pluggable denylist (`jti`, session, subject cutoff)
+//! - **Deriving a tenant** from host, custom domain, path, header or token claims
 //! - **Building secure cookies** (`__Host-` semantics, SameSite, etc.)
+//! - **Sealing session cookies** (HMAC-signed or encrypted values with key rotation)
+//! - **Verifying CLI passkey assertions** (Ed25519 challenge-response)
+//! - **CSRF tokens** bound to the session cookie (double-submit and synchronizer)
 //!
 //! The core API is `JwtVerifier`, which can verify a token against a JWKS URL (with optional
 //! in-memory caching) or against a JWKS you provide directly.
@@ -33,12 +38,36 @@
 
 #![forbid(unsafe_code)]
 
+mod claims;
 mod cookie;
+mod csrf;
 mod error;
 mod jwt;
+mod passkey;
+mod revocation;
+mod session;
 mod tenant;
 
+pub use claims::{
+    APP_ADMIN_ROLE, AppMembershipClaim, AuthMethodRef, FOUNDER_CAPABILITY, SupabaseClaims,
+};
 pub use cookie::{CookieOptions, SameSite, build_clear_cookie, build_set_cookie};
+pub use csrf::{
+    CSRF_COOKIE_NAME, CSRF_HEADER_NAME, CsrfTokens, build_csrf_cookie, csrf_cookie_options,
+    verify_synchronizer_token,
+};
 pub use error::{Error, Result};
-pub use jwt::{JwksSource, JwtVerifier, VerifiedJwt, VerifyOptions};
-pub use tenant::{TenantConfig, TenantDecision, TenantSource, derive_tenant};
+pub use jwt::{Jwk, JwksSet, JwksSource, JwtVerifier, VerifiedJwt, VerifyOptions};
+pub use passkey::{
+    PASSKEY_ALGORITHM, PasskeyAssertion, PasskeyChallenge, PasskeyCredential,
+    passkey_signing_payload, verify_passkey_assertion,
+};
+pub use revocation::{
+    FileRevocationList, MemoryRevocationList, RevocationCheck, RevocationEntries, RevocationKey,
+    RevokedBy,
+};
+pub use session::{SessionCodec, SessionKey, SessionProtection, find_cookie, parse_cookie_header};
+pub use tenant::{
+    HostLabel, TenantConfig, TenantDecision, TenantMismatch, TenantRequest, TenantSource,
+    derive_tenant, resolve_tenant,
+};
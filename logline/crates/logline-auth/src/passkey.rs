@@ -0,0 +1,219 @@
+//! CLI passkey challenge-response.
+//!
+//! The CLI holds an Ed25519 key whose public half is registered in
+//! `cli_passkey_credentials`. To log in it asks for a one-time challenge
+//! (a `cli_auth_challenges` row), signs [`passkey_signing_payload`] with the
+//! private key and sends the signature back. The server runs
+//! [`verify_passkey_assertion`] before minting a session, so a `passkey`
+//! session proves possession of the registered key.
+
+use crate::{Error, Result};
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// The only supported passkey algorithm.
+pub const PASSKEY_ALGORITHM: &str = "ed25519";
+
+const PAYLOAD_DOMAIN: &str = "logline-cli-passkey/v1";
+
+/// A registered credential (row of `cli_passkey_credentials`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyCredential {
+    /// Owner.
+    pub user_id: String,
+    /// Device the key lives on.
+    pub device_name: String,
+    /// Hex-encoded 32-byte Ed25519 public key.
+    pub public_key: String,
+    /// Signature algorithm (`ed25519`).
+    pub algorithm: String,
+    /// `active` or `revoked`.
+    pub status: String,
+}
+
+/// A pending challenge (row of `cli_auth_challenges`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyChallenge {
+    /// Challenge id.
+    pub challenge_id: String,
+    /// One-time nonce issued by the server.
+    pub nonce: String,
+    /// `pending`, `approved`, `denied` or `expired`.
+    pub status: String,
+    /// Device the challenge was opened for, if recorded.
+    #[serde(default)]
+    pub device_name: Option<String>,
+    /// Expiry (seconds since epoch).
+    pub expires_at: i64,
+}
+
+/// What the CLI submits to answer a challenge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyAssertion {
+    /// Challenge being answered.
+    pub challenge_id: String,
+    /// Claimed user.
+    pub user_id: String,
+    /// Claimed device (selects the credential).
+    pub device_name: String,
+    /// Hex-encoded Ed25519 signature over [`passkey_signing_payload`].
+    pub signature: String,
+}
+
+/// Canonical bytes the CLI signs.
+///
+/// Binds the signature to one challenge, nonce, user and device, with a
+/// domain prefix so the key cannot be tricked into signing anything else.
+#[must_use]
+pub fn passkey_signing_payload(
+    challenge_id: &str,
+    nonce: &str,
+    user_id: &str,
+    device_name: &str,
+) -> Vec<u8> {
+    format!("{PAYLOAD_DOMAIN}\n{challenge_id}\n{nonce}\n{user_id}\n{device_name}").into_bytes()
+}
+
+/// Check a passkey assertion against the stored challenge and credential.
+///
+/// The caller must mark the challenge consumed after success so the nonce
+/// cannot be replayed.
+///
+/// # Errors
+///
+/// Returns [`Error::Passkey`] if the challenge is not pending, expired or for
+/// another device, the credential is revoked or belongs to someone else, or
+/// the signature does not verify.
+pub fn verify_passkey_assertion(
+    assertion: &PasskeyAssertion,
+    challenge: &PasskeyChallenge,
+    credential: &PasskeyCredential,
+    now: i64,
+) -> Result<()> {
+    let fail = |why: &str| Err(Error::Passkey(why.to_string()));
+
+    if assertion.challenge_id != challenge.challenge_id {
+        return fail("assertion is for another challenge");
+    }
+    if challenge.status != "pending" {
+        return fail("challenge is not pending");
+    }
+    if now >= challenge.expires_at {
+        return fail("challenge expired");
+    }
+    if challenge
+        .device_name
+        .as_deref()
+        .is_some_and(|d| d != assertion.device_name)
+    {
+        return fail("challenge was opened for another device");
+    }
+    if credential.user_id != assertion.user_id || credential.device_name != assertion.device_name {
+        return fail("credential does not match user and device");
+    }
+    if credential.status != "active" {
+        return fail("credential is revoked");
+    }
+    if !credential.algorithm.eq_ignore_ascii_case(PASSKEY_ALGORITHM) {
+        return fail("unsupported credential algorithm");
+    }
+
+    let public_key: [u8; 32] = hex::decode(credential.public_key.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::Passkey("malformed public key".to_string()))?;
+    let key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|_| Error::Passkey("malformed public key".to_string()))?;
+    let signature: [u8; 64] = hex::decode(assertion.signature.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::Passkey("malformed signature".to_string()))?;
+
+    let payload = passkey_signing_payload(
+        &challenge.challenge_id,
+        &challenge.nonce,
+        &assertion.user_id,
+        &assertion.device_name,
+    );
+    key.verify(&payload, &Signature::from_bytes(&signature))
+        .map_err(|_| Error::Passkey("signature does not verify".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn fixture() -> (SigningKey, PasskeyChallenge, PasskeyCredential) {
+        let signing = SigningKey::from_bytes(&[9; 32]);
+        let challenge = PasskeyChallenge {
+            challenge_id: "ch-1".to_string(),
+            nonce: "n-123".to_string(),
+            status: "pending".to_string(),
+            device_name: Some("laptop".to_string()),
+            expires_at: 1_000,
+        };
+        let credential = PasskeyCredential {
+            user_id: "u-1".to_string(),
+            device_name: "laptop".to_string(),
+            public_key: hex::encode(signing.verifying_key().as_bytes()),
+            algorithm: PASSKEY_ALGORITHM.to_string(),
+            status: "active".to_string(),
+        };
+        (signing, challenge, credential)
+    }
+
+    fn assert_for(signing: &SigningKey, challenge: &PasskeyChallenge) -> PasskeyAssertion {
+        let payload =
+            passkey_signing_payload(&challenge.challenge_id, &challenge.nonce, "u-1", "laptop");
+        PasskeyAssertion {
+            challenge_id: challenge.challenge_id.clone(),
+            user_id: "u-1".to_string(),
+            device_name: "laptop".to_string(),
+            signature: hex::encode(signing.sign(&payload).to_bytes()),
+        }
+    }
+
+    #[test]
+    fn valid_assertion_verifies() {
+        let (signing, challenge, credential) = fixture();
+        let assertion = assert_for(&signing, &challenge);
+        verify_passkey_assertion(&assertion, &challenge, &credential, 10).unwrap();
+    }
+
+    #[test]
+    fn rejects_wrong_key_nonce_and_state() {
+        let (signing, challenge, credential) = fixture();
+        let assertion = assert_for(&signing, &challenge);
+
+        let other = SigningKey::from_bytes(&[10; 32]);
+        assert!(
+            verify_passkey_assertion(&assert_for(&other, &challenge), &challenge, &credential, 10)
+                .is_err()
+        );
+
+        let reissued = PasskeyChallenge {
+            nonce: "n-456".to_string(),
+            ..challenge.clone()
+        };
+        assert!(verify_passkey_assertion(&assertion, &reissued, &credential, 10).is_err());
+
+        assert!(verify_passkey_assertion(&assertion, &challenge, &credential, 1_000).is_err());
+
+        let consumed = PasskeyChallenge {
+            status: "approved".to_string(),
+            ..challenge.clone()
+        };
+        assert!(verify_passkey_assertion(&assertion, &consumed, &credential, 10).is_err());
+
+        let revoked = PasskeyCredential {
+            status: "revoked".to_string(),
+            ..credential
+        };
+        assert!(matches!(
+            verify_passkey_assertion(&assertion, &challenge, &revoked, 10),
+            Err(Error::Passkey(_))
+        ));
+    }
+}
@@ -0,0 +1,360 @@
+//! Token revocation (denylist) checks.
+//!
+//! A verified JWT stays valid until `exp`. A [`RevocationCheck`] lets the
+//! verifier reject tokens that were revoked earlier: by `jti`, by Supabase
+//! `session_id`, or every token of a subject issued before a cutoff (logout
+//! everywhere, revoked passkey).
+
+use crate::{Error, Result, VerifiedJwt};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::SystemTime;
+
+/// Identifiers of a verified token that a revocation check can match on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RevocationKey<'a> {
+    /// `jti` claim.
+    pub jti: Option<&'a str>,
+    /// Supabase `session_id` claim.
+    pub session_id: Option<&'a str>,
+    /// `sub` claim.
+    pub sub: Option<&'a str>,
+    /// `iat` claim (seconds since epoch).
+    pub issued_at: Option<i64>,
+}
+
+impl<'a> RevocationKey<'a> {
+    /// Extract the key fields from a verified token.
+    #[must_use]
+    pub fn from_verified(jwt: &'a VerifiedJwt) -> Self {
+        Self {
+            jti: jwt.jti(),
+            session_id: jwt.session_id(),
+            sub: jwt.sub(),
+            issued_at: jwt.iat(),
+        }
+    }
+}
+
+/// Why a token was considered revoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevokedBy {
+    /// The token's `jti` is on the denylist.
+    Jti,
+    /// The token's session was revoked.
+    Session,
+    /// All tokens of the subject issued before a cutoff were revoked.
+    Subject,
+}
+
+impl fmt::Display for RevokedBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RevokedBy::Jti => "jti revoked",
+            RevokedBy::Session => "session revoked",
+            RevokedBy::Subject => "subject tokens issued before cutoff revoked",
+        })
+    }
+}
+
+/// Pluggable revocation check, called by `JwtVerifier` after signature and
+/// claim validation.
+///
+/// Implementations should fail closed: return `Err` when the denylist cannot
+/// be consulted rather than `Ok(None)`.
+pub trait RevocationCheck: Send + Sync {
+    /// Return `Some(reason)` if the token identified by `key` is revoked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the revocation source cannot be read.
+    fn check(&self, key: &RevocationKey<'_>) -> Result<Option<RevokedBy>>;
+}
+
+/// Serializable denylist contents, shared by the in-memory and file-backed
+/// implementations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationEntries {
+    /// Revoked `jti` values.
+    #[serde(default)]
+    pub jti: BTreeSet<String>,
+
+    /// Revoked Supabase session ids.
+    #[serde(default)]
+    pub sessions: BTreeSet<String>,
+
+    /// Subject -> cutoff (seconds since epoch). Tokens of the subject with
+    /// `iat` before the cutoff (or without `iat`) are revoked.
+    #[serde(default)]
+    pub subjects: BTreeMap<String, i64>,
+}
+
+impl RevocationEntries {
+    /// Match `key` against these entries.
+    #[must_use]
+    pub fn matches(&self, key: &RevocationKey<'_>) -> Option<RevokedBy> {
+        if key.jti.is_some_and(|j| self.jti.contains(j)) {
+            return Some(RevokedBy::Jti);
+        }
+        if key.session_id.is_some_and(|s| self.sessions.contains(s)) {
+            return Some(RevokedBy::Session);
+        }
+        if let Some(cutoff) = key.sub.and_then(|s| self.subjects.get(s)) {
+            // No `iat` means we cannot prove the token is newer than the cutoff.
+            if key.issued_at.is_none_or(|iat| iat < *cutoff) {
+                return Some(RevokedBy::Subject);
+            }
+        }
+        None
+    }
+
+    fn revoke_subject_before(&mut self, sub: &str, cutoff: i64) {
+        let entry = self.subjects.entry(sub.to_string()).or_insert(cutoff);
+        *entry = (*entry).max(cutoff);
+    }
+}
+
+/// In-memory denylist. Suitable for a single process or tests.
+#[derive(Debug, Default)]
+pub struct MemoryRevocationList {
+    entries: RwLock<RevocationEntries>,
+}
+
+impl MemoryRevocationList {
+    /// Create an empty list.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revoke a single token by `jti`.
+    pub fn revoke_jti(&self, jti: &str) {
+        self.write().jti.insert(jti.to_string());
+    }
+
+    /// Revoke every token of a Supabase session.
+    pub fn revoke_session(&self, session_id: &str) {
+        self.write().sessions.insert(session_id.to_string());
+    }
+
+    /// Revoke every token of `sub` issued before `cutoff` (seconds since epoch).
+    pub fn revoke_subject_before(&self, sub: &str, cutoff: i64) {
+        self.write().revoke_subject_before(sub, cutoff);
+    }
+
+    /// Snapshot the current entries.
+    #[must_use]
+    pub fn entries(&self) -> RevocationEntries {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, RevocationEntries> {
+        self.entries.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl RevocationCheck for MemoryRevocationList {
+    fn check(&self, key: &RevocationKey<'_>) -> Result<Option<RevokedBy>> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .matches(key))
+    }
+}
+
+/// File-backed denylist (JSON, see [`RevocationEntries`]).
+///
+/// The file is re-read whenever its modification time changes, so several
+/// processes can share one list. A missing file means "nothing revoked"; an
+/// unreadable or malformed file is an error.
+#[derive(Debug)]
+pub struct FileRevocationList {
+    path: PathBuf,
+    cache: Mutex<Option<(SystemTime, RevocationEntries)>>,
+}
+
+impl FileRevocationList {
+    /// Use the denylist at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Path of the backing file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the current entries from disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Revocation`] if the file exists but cannot be read or parsed.
+    pub fn entries(&self) -> Result<RevocationEntries> {
+        let mtime = match fs::metadata(&self.path) {
+            Ok(meta) => meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(RevocationEntries::default());
+            }
+            Err(e) => return Err(self.io_error(&e)),
+        };
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((cached_at, entries)) = cache.as_ref() {
+            if *cached_at == mtime {
+                return Ok(entries.clone());
+            }
+        }
+
+        let text = fs::read_to_string(&self.path).map_err(|e| self.io_error(&e))?;
+        let entries: RevocationEntries = serde_json::from_str(&text).map_err(|e| {
+            Error::Revocation(format!("invalid denylist {}: {e}", self.path.display()))
+        })?;
+        *cache = Some((mtime, entries.clone()));
+        Ok(entries)
+    }
+
+    /// Revoke a single token by `jti`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Revocation`] if the file cannot be read or written.
+    pub fn revoke_jti(&self, jti: &str) -> Result<()> {
+        self.update(|e| {
+            e.jti.insert(jti.to_string());
+        })
+    }
+
+    /// Revoke every token of a Supabase session.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Revocation`] if the file cannot be read or written.
+    pub fn revoke_session(&self, session_id: &str) -> Result<()> {
+        self.update(|e| {
+            e.sessions.insert(session_id.to_string());
+        })
+    }
+
+    /// Revoke every token of `sub` issued before `cutoff` (seconds since epoch).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Revocation`] if the file cannot be read or written.
+    pub fn revoke_subject_before(&self, sub: &str, cutoff: i64) -> Result<()> {
+        self.update(|e| e.revoke_subject_before(sub, cutoff))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut RevocationEntries)) -> Result<()> {
+        let mut entries = self.entries()?;
+        f(&mut entries);
+
+        // Write to a sibling temp file and rename so readers never see a partial file.
+        let tmp = self.path.with_extension("tmp");
+        let json = serde_json::to_string_pretty(&entries)?;
+        fs::write(&tmp, json).map_err(|e| self.io_error(&e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| self.io_error(&e))?;
+
+        *self.cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        Ok(())
+    }
+
+    fn io_error(&self, e: &std::io::Error) -> Error {
+        Error::Revocation(format!("{}: {e}", self.path.display()))
+    }
+}
+
+impl RevocationCheck for FileRevocationList {
+    fn check(&self, key: &RevocationKey<'_>) -> Result<Option<RevokedBy>> {
+        Ok(self.entries()?.matches(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key<'a>(jti: &'a str, session: &'a str, sub: &'a str, iat: i64) -> RevocationKey<'a> {
+        RevocationKey {
+            jti: Some(jti),
+            session_id: Some(session),
+            sub: Some(sub),
+            issued_at: Some(iat),
+        }
+    }
+
+    #[test]
+    fn memory_list_matches_each_kind() {
+        let list = MemoryRevocationList::new();
+        assert_eq!(list.check(&key("j1", "s1", "u1", 100)).unwrap(), None);
+
+        list.revoke_jti("j1");
+        assert_eq!(
+            list.check(&key("j1", "s1", "u1", 100)).unwrap(),
+            Some(RevokedBy::Jti)
+        );
+
+        list.revoke_session("s2");
+        assert_eq!(
+            list.check(&key("j2", "s2", "u1", 100)).unwrap(),
+            Some(RevokedBy::Session)
+        );
+
+        list.revoke_subject_before("u1", 200);
+        assert_eq!(
+            list.check(&key("j3", "s3", "u1", 150)).unwrap(),
+            Some(RevokedBy::Subject)
+        );
+        assert_eq!(list.check(&key("j3", "s3", "u1", 250)).unwrap(), None);
+    }
+
+    #[test]
+    fn subject_cutoff_without_iat_is_revoked() {
+        let list = MemoryRevocationList::new();
+        list.revoke_subject_before("u1", 200);
+        let k = RevocationKey {
+            sub: Some("u1"),
+            ..Default::default()
+        };
+        assert_eq!(list.check(&k).unwrap(), Some(RevokedBy::Subject));
+    }
+
+    #[test]
+    fn file_list_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "logline-auth-denylist-{}-{:?}.json",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let list = FileRevocationList::new(&path);
+        assert_eq!(list.check(&key("j1", "s1", "u1", 1)).unwrap(), None);
+
+        list.revoke_session("s1").unwrap();
+        let reopened = FileRevocationList::new(&path);
+        assert_eq!(
+            reopened.check(&key("j1", "s1", "u1", 1)).unwrap(),
+            Some(RevokedBy::Session)
+        );
+
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            FileRevocationList::new(&path).check(&key("j1", "s1", "u1", 1)),
+            Err(Error::Revocation(_))
+        ));
+        let _ = fs::remove_file(&path);
+    }
+}
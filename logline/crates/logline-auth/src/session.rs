@@ -0,0 +1,429 @@
+//! Authenticated (and optionally encrypted) session cookie values.
+//!
+//! [`SessionCodec`] turns an opaque payload into a cookie-safe string that
+//! embeds its expiry and the id of the key that protected it:
+//!
+//! - signed:    `v1.s.<kid>.<exp>.<payload>.<hmac>` (HMAC-SHA256)
+//! - encrypted: `v1.e.<kid>.<exp>.<nonce>.<ciphertext>` (ChaCha20-Poly1305)
+//!
+//! Segments are base64url without padding. Several keys can be active at
+//! once: the first one protects new cookies, the rest are only accepted on
+//! read, so keys can be rotated without logging everybody out.
+
+use crate::cookie::cookie_name;
+use crate::{CookieOptions, Error, Result, build_set_cookie};
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const VERSION: &str = "v1";
+const MIN_SECRET_LEN: usize = 32;
+
+/// A named session key.
+#[derive(Clone)]
+pub struct SessionKey {
+    id: String,
+    mac_key: [u8; 32],
+    enc_key: [u8; 32],
+}
+
+impl SessionKey {
+    /// Create a key from a secret of at least 32 bytes.
+    ///
+    /// `id` is embedded in every cookie and must be non-empty `[A-Za-z0-9_-]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCookie`] if the id or secret is unusable.
+    pub fn new(id: &str, secret: &[u8]) -> Result<Self> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(Error::InvalidCookie(format!("invalid key id: {id:?}")));
+        }
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(Error::InvalidCookie(format!(
+                "session secret must be at least {MIN_SECRET_LEN} bytes"
+            )));
+        }
+        Ok(Self {
+            id: id.to_string(),
+            mac_key: derive_subkey(secret, b"logline-session-mac"),
+            enc_key: derive_subkey(secret, b"logline-session-enc"),
+        })
+    }
+
+    /// Key id.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How cookie values are protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionProtection {
+    /// Integrity only (payload readable by the client).
+    #[default]
+    Signed,
+    /// Integrity and confidentiality.
+    Encrypted,
+}
+
+impl SessionProtection {
+    fn tag(self) -> &'static str {
+        match self {
+            SessionProtection::Signed => "s",
+            SessionProtection::Encrypted => "e",
+        }
+    }
+}
+
+/// Encodes and verifies session cookie values.
+#[derive(Debug, Clone)]
+pub struct SessionCodec {
+    keys: Vec<SessionKey>,
+    protection: SessionProtection,
+}
+
+impl SessionCodec {
+    /// Codec that protects new cookies with `active`.
+    #[must_use]
+    pub fn new(active: SessionKey) -> Self {
+        Self {
+            keys: vec![active],
+            protection: SessionProtection::default(),
+        }
+    }
+
+    /// Also accept cookies protected by `key` (a rotated-out key).
+    #[must_use]
+    pub fn with_previous_key(mut self, key: SessionKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Choose signed or encrypted cookies for new values. Both are accepted on read.
+    #[must_use]
+    pub fn with_protection(mut self, protection: SessionProtection) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    /// Protect `payload` for `ttl_seconds`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCookie`] if encryption fails.
+    pub fn seal(&self, payload: &[u8], ttl_seconds: u64) -> Result<String> {
+        self.seal_at(payload, now_secs().saturating_add(ttl_seconds))
+    }
+
+    /// Verify a cookie value and return its payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CookieExpired`] past the embedded expiry and
+    /// [`Error::InvalidCookie`] for any tampering, unknown key or bad format.
+    pub fn open(&self, value: &str) -> Result<Vec<u8>> {
+        self.open_at(value, now_secs())
+    }
+
+    /// Serialize `payload` as JSON and [`seal`](Self::seal) it.
+    ///
+    /// # Errors
+    ///
+    /// See [`seal`](Self::seal); also fails if `payload` cannot be serialized.
+    pub fn seal_json<T: Serialize>(&self, payload: &T, ttl_seconds: u64) -> Result<String> {
+        self.seal(&serde_json::to_vec(payload)?, ttl_seconds)
+    }
+
+    /// [`open`](Self::open) a cookie value and parse its payload as JSON.
+    ///
+    /// # Errors
+    ///
+    /// See [`open`](Self::open); also fails if the payload is not valid JSON for `T`.
+    pub fn open_json<T: DeserializeOwned>(&self, value: &str) -> Result<T> {
+        Ok(serde_json::from_slice(&self.open(value)?)?)
+    }
+
+    /// Build a `Set-Cookie` header carrying `payload`.
+    ///
+    /// The expiry embedded in the value follows `opts.max_age_seconds`
+    /// (one day when unset, so a session cookie cannot live forever).
+    ///
+    /// # Errors
+    ///
+    /// See [`seal`](Self::seal) and [`build_set_cookie`].
+    pub fn set_cookie(&self, payload: &[u8], opts: &CookieOptions) -> Result<String> {
+        let ttl = opts.max_age_seconds.unwrap_or(86_400);
+        build_set_cookie(&self.seal(payload, ttl)?, opts)
+    }
+
+    /// Find the session cookie named by `opts` in a `Cookie` header and open it.
+    ///
+    /// Returns `Ok(None)` when the cookie is absent.
+    ///
+    /// # Errors
+    ///
+    /// See [`open`](Self::open).
+    pub fn read_cookie(
+        &self,
+        cookie_header: &str,
+        opts: &CookieOptions,
+    ) -> Result<Option<Vec<u8>>> {
+        let name = cookie_name(opts)?;
+        match find_cookie(cookie_header, &name) {
+            Some(value) => self.open(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn seal_at(&self, payload: &[u8], exp: u64) -> Result<String> {
+        let key = &self.keys[0];
+        let tag = self.protection.tag();
+        let header = format!("{VERSION}.{tag}.{}.{exp}", key.id);
+
+        match self.protection {
+            SessionProtection::Signed => {
+                let body = URL_SAFE_NO_PAD.encode(payload);
+                let signed = format!("{header}.{body}");
+                let mac = URL_SAFE_NO_PAD.encode(mac(&key.mac_key, signed.as_bytes()));
+                Ok(format!("{signed}.{mac}"))
+            }
+            SessionProtection::Encrypted => {
+                let nonce = random_bytes::<12>()
+                    .map_err(|e| Error::InvalidCookie(format!("no randomness: {e}")))?;
+                let cipher = ChaCha20Poly1305::new(&Key::from(key.enc_key));
+                let ciphertext = cipher
+                    .encrypt(
+                        &Nonce::from(nonce),
+                        Payload {
+                            msg: payload,
+                            aad: header.as_bytes(),
+                        },
+                    )
+                    .map_err(|_| Error::InvalidCookie("encryption failed".to_string()))?;
+                Ok(format!(
+                    "{header}.{}.{}",
+                    URL_SAFE_NO_PAD.encode(nonce),
+                    URL_SAFE_NO_PAD.encode(ciphertext)
+                ))
+            }
+        }
+    }
+
+    fn open_at(&self, value: &str, now: u64) -> Result<Vec<u8>> {
+        let invalid = |why: &str| Error::InvalidCookie(why.to_string());
+
+        let parts: Vec<&str> = value.trim().split('.').collect();
+        let [version, tag, kid, exp, a, b] = parts.as_slice() else {
+            return Err(invalid("malformed value"));
+        };
+        if *version != VERSION {
+            return Err(invalid("unsupported version"));
+        }
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.id == *kid)
+            .ok_or_else(|| invalid("unknown key id"))?;
+        let header = format!("{version}.{tag}.{kid}.{exp}");
+
+        // Authenticate before trusting any field (including the expiry).
+        let payload = match *tag {
+            "s" => {
+                let expected = URL_SAFE_NO_PAD
+                    .decode(b)
+                    .map_err(|_| invalid("malformed signature"))?;
+                let mut m = <HmacSha256 as Mac>::new_from_slice(&key.mac_key)
+                    .map_err(|_| invalid("bad key"))?;
+                m.update(format!("{header}.{a}").as_bytes());
+                m.verify_slice(&expected)
+                    .map_err(|_| invalid("signature mismatch"))?;
+                URL_SAFE_NO_PAD
+                    .decode(a)
+                    .map_err(|_| invalid("malformed payload"))?
+            }
+            "e" => {
+                let nonce: [u8; 12] = URL_SAFE_NO_PAD
+                    .decode(a)
+                    .ok()
+                    .and_then(|n| n.try_into().ok())
+                    .ok_or_else(|| invalid("malformed nonce"))?;
+                let ciphertext = URL_SAFE_NO_PAD
+                    .decode(b)
+                    .map_err(|_| invalid("malformed ciphertext"))?;
+                let cipher = ChaCha20Poly1305::new(&Key::from(key.enc_key));
+                cipher
+                    .decrypt(
+                        &Nonce::from(nonce),
+                        Payload {
+                            msg: &ciphertext,
+                            aad: header.as_bytes(),
+                        },
+                    )
+                    .map_err(|_| invalid("decryption failed"))?
+            }
+            _ => return Err(invalid("unknown protection")),
+        };
+
+        let exp: u64 = exp.parse().map_err(|_| invalid("malformed expiry"))?;
+        if now >= exp {
+            return Err(Error::CookieExpired);
+        }
+        Ok(payload)
+    }
+}
+
+/// Split a `Cookie` request header into `(name, value)` pairs, in order.
+///
+/// Values keep their raw form (no percent-decoding); surrounding double
+/// quotes are removed. Pairs without `=` are skipped.
+#[must_use]
+pub fn parse_cookie_header(header: &str) -> Vec<(&str, &str)> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// Value of the first cookie called `name` in a `Cookie` header.
+#[must_use]
+pub fn find_cookie<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    parse_cookie_header(header)
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v)
+}
+
+fn derive_subkey(secret: &[u8], label: &[u8]) -> [u8; 32] {
+    mac(secret, label)
+}
+
+pub(crate) fn mac(key: &[u8], msg: &[u8]) -> [u8; 32] {
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut m = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    m.update(msg);
+    m.finalize().into_bytes().into()
+}
+
+pub(crate) fn random_bytes<const N: usize>() -> std::result::Result<[u8; N], getrandom::Error> {
+    let mut buf = [0u8; N];
+    getrandom::getrandom(&mut buf)?;
+    Ok(buf)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str, fill: u8) -> SessionKey {
+        SessionKey::new(id, &[fill; 32]).unwrap()
+    }
+
+    #[test]
+    fn signed_round_trip_and_tamper() {
+        let codec = SessionCodec::new(key("k1", 1));
+        let value = codec.seal_at(b"user=42", 1_000).unwrap();
+        assert!(value.starts_with("v1.s.k1.1000."));
+        assert_eq!(codec.open_at(&value, 999).unwrap(), b"user=42");
+
+        let forged = value.replacen("1000", "9999", 1);
+        assert!(matches!(
+            codec.open_at(&forged, 999),
+            Err(Error::InvalidCookie(_))
+        ));
+        assert!(matches!(
+            codec.open_at(&value, 1_000),
+            Err(Error::CookieExpired)
+        ));
+    }
+
+    #[test]
+    fn encrypted_round_trip_hides_payload() {
+        let codec = SessionCodec::new(key("k1", 1)).with_protection(SessionProtection::Encrypted);
+        let value = codec.seal_at(b"secret-payload", 1_000).unwrap();
+        assert!(!value.contains(&URL_SAFE_NO_PAD.encode(b"secret-payload")));
+        assert_eq!(codec.open_at(&value, 10).unwrap(), b"secret-payload");
+    }
+
+    #[test]
+    fn rotation_accepts_previous_key_only_when_configured() {
+        let old = SessionCodec::new(key("old", 1));
+        let value = old.seal_at(b"x", 1_000).unwrap();
+
+        let rotated = SessionCodec::new(key("new", 2)).with_previous_key(key("old", 1));
+        assert_eq!(rotated.open_at(&value, 10).unwrap(), b"x");
+        assert!(
+            rotated
+                .seal_at(b"x", 1_000)
+                .unwrap()
+                .starts_with("v1.s.new.")
+        );
+
+        let dropped = SessionCodec::new(key("new", 2));
+        assert!(dropped.open_at(&value, 10).is_err());
+    }
+
+    #[test]
+    fn cookie_header_parsing_and_host_prefix() {
+        let codec = SessionCodec::new(key("k1", 1));
+        let opts = CookieOptions::default();
+        let sealed = codec.seal(b"hello", 60).unwrap();
+        let header = format!("theme=dark; __Host-logline_session={sealed}; other=\"q\"");
+
+        assert_eq!(find_cookie(&header, "other"), Some("q"));
+        assert_eq!(parse_cookie_header(&header).len(), 3);
+        assert_eq!(
+            codec.read_cookie(&header, &opts).unwrap().as_deref(),
+            Some(&b"hello"[..])
+        );
+        assert_eq!(codec.read_cookie("theme=dark", &opts).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_short_secret() {
+        assert!(SessionKey::new("k1", &[0; 16]).is_err());
+        assert!(SessionKey::new("bad.id", &[0; 32]).is_err());
+    }
+}
@@ -1,18 +1,38 @@
 //! Tenant derivation helpers.
 
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 /// Where a tenant decision came from.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TenantSource {
     /// Derived from the request host (subdomain).
     Host,
+    /// Derived from an explicit custom-domain mapping.
+    CustomDomain,
+    /// Derived from a request path prefix (e.g. `/t/acme/...`).
+    Path,
+    /// Derived from an explicit request header.
+    Header,
     /// Derived from a token claim.
     Claim,
     /// No tenant could be derived.
     None,
 }
 
+/// Which subdomain label names the tenant when the host has several labels
+/// in front of `host_root`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HostLabel {
+    /// Label adjacent to the root: `foo.acme.example.com` -> `acme`.
+    #[default]
+    Nearest,
+    /// Leftmost label: `foo.acme.example.com` -> `foo`.
+    Leftmost,
+    /// Only single-label subdomains: `foo.acme.example.com` -> no tenant.
+    Single,
+}
+
 /// Tenant derivation configuration.
 #[derive(Debug, Clone)]
 pub struct TenantConfig {
@@ -20,12 +40,32 @@ pub struct TenantConfig {
     /// when `host_root` is `example.com`.
     pub host_root: Option<String>,
 
+    /// Which label to use for nested subdomains of `host_root`.
+    pub host_label: HostLabel,
+
+    /// Exact host -> tenant mapping for custom domains
+    /// (e.g. `app.acme.com` -> `acme`). Checked before `host_root`.
+    pub domain_map: BTreeMap<String, String>,
+
+    /// If set, a path like `/t/acme/settings` will derive tenant `acme`
+    /// when `path_prefix` is `/t/`.
+    pub path_prefix: Option<String>,
+
+    /// Name of a header carrying the tenant id. When set, the caller passes
+    /// that header's value as [`TenantRequest::header_value`].
+    pub header_name: Option<String>,
+
     /// If set, a claim like `{ "tenant_id": "acme" }` will be considered.
     pub claim_key: Option<String>,
 
-    /// Prefer host-derived tenants over claim-derived tenants.
+    /// Prefer request-derived tenants (domain, host, path, header) over
+    /// claim-derived tenants.
     pub prefer_host: bool,
 
+    /// Reject the request when derived tenants disagree instead of picking
+    /// one by precedence.
+    pub strict: bool,
+
     /// Optional allow-list of tenant ids.
     pub allow_list: Option<Vec<String>>,
 }
@@ -34,13 +74,39 @@ impl Default for TenantConfig {
     fn default() -> Self {
         Self {
             host_root: None,
+            host_label: HostLabel::default(),
+            domain_map: BTreeMap::new(),
+            path_prefix: None,
+            header_name: None,
             claim_key: Some("tenant_id".to_string()),
             prefer_host: true,
+            strict: false,
             allow_list: None,
         }
     }
 }
 
+/// Request attributes used for tenant resolution.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TenantRequest<'a> {
+    /// `Host` header (port allowed).
+    pub host: Option<&'a str>,
+    /// Request path (query string allowed).
+    pub path: Option<&'a str>,
+    /// Value of the header named by [`TenantConfig::header_name`].
+    /// Ignored unless that option is set.
+    pub header_value: Option<&'a str>,
+}
+
+/// Two sources that derived different tenants (strict mode).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantMismatch {
+    /// First source and the tenant it derived.
+    pub first: (TenantSource, String),
+    /// Conflicting source and the tenant it derived.
+    pub second: (TenantSource, String),
+}
+
 /// Result of deriving a tenant.
 #[derive(Debug, Clone)]
 pub struct TenantDecision {
@@ -48,6 +114,8 @@ pub struct TenantDecision {
     pub tenant_id: Option<String>,
     /// Source used.
     pub source: TenantSource,
+    /// Set in strict mode when sources disagreed; `tenant_id` is then `None`.
+    pub mismatch: Option<TenantMismatch>,
 }
 
 impl TenantDecision {
@@ -55,55 +123,130 @@ impl TenantDecision {
     pub fn is_some(&self) -> bool {
         self.tenant_id.is_some()
     }
+
+    /// True if strict mode rejected the request because sources disagreed.
+    #[must_use]
+    pub fn is_mismatch(&self) -> bool {
+        self.mismatch.is_some()
+    }
+
+    fn none() -> Self {
+        Self {
+            tenant_id: None,
+            source: TenantSource::None,
+            mismatch: None,
+        }
+    }
 }
 
 /// Derive a tenant id from host and/or claims.
 ///
-/// - If `cfg.prefer_host` is true, host is tried first.
-/// - If `cfg.allow_list` is set, derived tenants must be in the list.
+/// Shorthand for [`resolve_tenant`] with only the host set.
 pub fn derive_tenant(host: Option<&str>, claims: &Value, cfg: &TenantConfig) -> TenantDecision {
-    let host_tenant = host.and_then(|h| derive_from_host(h, cfg.host_root.as_deref()));
-    let claim_tenant = derive_from_claims(claims, cfg.claim_key.as_deref());
-
-    let (tenant_id, source) = if cfg.prefer_host {
-        if host_tenant.is_some() {
-            (host_tenant, TenantSource::Host)
-        } else if claim_tenant.is_some() {
-            (claim_tenant, TenantSource::Claim)
-        } else {
-            (None, TenantSource::None)
+    let req = TenantRequest {
+        host,
+        ..TenantRequest::default()
+    };
+    resolve_tenant(&req, claims, cfg)
+}
+
+/// Resolve a tenant id from the request and/or claims.
+///
+/// - Request sources are tried in order: custom domain, host, path, header.
+/// - If `cfg.prefer_host` is true, request sources win over the claim.
+/// - If `cfg.strict` is true, every derived tenant must agree; otherwise the
+///   decision carries a [`TenantMismatch`] and no tenant.
+/// - If `cfg.allow_list` is set, derived tenants must be in the list.
+#[must_use]
+pub fn resolve_tenant(
+    req: &TenantRequest<'_>,
+    claims: &Value,
+    cfg: &TenantConfig,
+) -> TenantDecision {
+    let mut request_candidates: Vec<(TenantSource, String)> = Vec::new();
+    if let Some(host) = req.host {
+        if let Some(t) = derive_from_domain_map(host, &cfg.domain_map) {
+            request_candidates.push((TenantSource::CustomDomain, t));
         }
-    } else {
-        if claim_tenant.is_some() {
-            (claim_tenant, TenantSource::Claim)
-        } else if host_tenant.is_some() {
-            (host_tenant, TenantSource::Host)
-        } else {
-            (None, TenantSource::None)
+        if let Some(t) = derive_from_host(host, cfg.host_root.as_deref(), cfg.host_label) {
+            request_candidates.push((TenantSource::Host, t));
         }
-    };
+    }
+    if let Some(t) = req
+        .path
+        .and_then(|p| derive_from_path(p, cfg.path_prefix.as_deref()))
+    {
+        request_candidates.push((TenantSource::Path, t));
+    }
+    if cfg.header_name.is_some() {
+        if let Some(t) = req.header_value.and_then(sanitize_tenant) {
+            request_candidates.push((TenantSource::Header, t));
+        }
+    }
 
-    let tenant_id = match (tenant_id, &cfg.allow_list) {
-        (Some(t), Some(allow)) if !allow.iter().any(|a| a == &t) => None,
-        (t, _) => t,
-    };
+    let claim_candidate =
+        derive_from_claims(claims, cfg.claim_key.as_deref()).map(|t| (TenantSource::Claim, t));
 
-    let source = if tenant_id.is_some() {
-        source
-    } else {
-        TenantSource::None
+    let mut candidates = request_candidates;
+    if cfg.prefer_host {
+        candidates.extend(claim_candidate);
+    } else if let Some(claim) = claim_candidate {
+        candidates.insert(0, claim);
+    }
+
+    if cfg.strict {
+        if let Some(first) = candidates.first() {
+            if let Some(second) = candidates.iter().find(|(_, t)| *t != first.1) {
+                return TenantDecision {
+                    tenant_id: None,
+                    source: TenantSource::None,
+                    mismatch: Some(TenantMismatch {
+                        first: first.clone(),
+                        second: second.clone(),
+                    }),
+                };
+            }
+        }
+    }
+
+    let Some((source, tenant_id)) = candidates.into_iter().next() else {
+        return TenantDecision::none();
     };
 
-    TenantDecision { tenant_id, source }
-}
+    if let Some(allow) = &cfg.allow_list {
+        if !allow.iter().any(|a| a == &tenant_id) {
+            return TenantDecision::none();
+        }
+    }
 
-fn derive_from_host(host: &str, host_root: Option<&str>) -> Option<String> {
-    let mut host = host.trim().to_lowercase();
+    TenantDecision {
+        tenant_id: Some(tenant_id),
+        source,
+        mismatch: None,
+    }
+}
 
+fn normalize_host(host: &str) -> String {
+    let host = host.trim().to_lowercase();
     // Strip port, if any.
-    if let Some((h, _port)) = host.split_once(':') {
-        host = h.to_string();
+    match host.split_once(':') {
+        Some((h, _port)) => h.trim_end_matches('.').to_string(),
+        None => host.trim_end_matches('.').to_string(),
     }
+}
+
+fn derive_from_domain_map(host: &str, map: &BTreeMap<String, String>) -> Option<String> {
+    if map.is_empty() {
+        return None;
+    }
+    let host = normalize_host(host);
+    map.iter()
+        .find(|(domain, _)| normalize_host(domain) == host)
+        .and_then(|(_, tenant)| sanitize_tenant(tenant))
+}
+
+fn derive_from_host(host: &str, host_root: Option<&str>, label: HostLabel) -> Option<String> {
+    let host = normalize_host(host);
 
     let root = host_root?.trim().to_lowercase();
     let root = root.trim_start_matches('.');
@@ -112,28 +255,34 @@ fn derive_from_host(host: &str, host_root: Option<&str>) -> Option<String> {
         return None;
     }
 
-    if !host.ends_with(root) {
-        return None;
-    }
-
-    // Remove root suffix. Example: acme.example.com -> acme.
-    let prefix = host.trim_end_matches(root).trim_end_matches('.');
+    // Require a label boundary: `evilexample.com` is not under `example.com`.
+    let prefix = host.strip_suffix(root)?.strip_suffix('.')?;
     if prefix.is_empty() {
         return None;
     }
 
-    // Use the last label of the remaining prefix (supports nested subdomains).
-    let tenant = prefix.split('.').last()?.to_string();
+    let mut labels = prefix.split('.');
+    let tenant = match label {
+        HostLabel::Nearest => labels.next_back()?,
+        HostLabel::Leftmost => labels.next()?,
+        HostLabel::Single => {
+            let only = labels.next()?;
+            if labels.next().is_some() {
+                return None;
+            }
+            only
+        }
+    };
 
-    // Basic sanity: [a-z0-9-]
-    if tenant
-        .chars()
-        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
-    {
-        Some(tenant)
-    } else {
-        None
-    }
+    sanitize_tenant(tenant)
+}
+
+fn derive_from_path(path: &str, prefix: Option<&str>) -> Option<String> {
+    let prefix = prefix?.trim_end_matches('/');
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    let rest = path.strip_prefix(prefix)?.strip_prefix('/')?;
+    let segment = rest.split('/').next()?;
+    sanitize_tenant(segment)
 }
 
 fn derive_from_claims(claims: &Value, claim_key: Option<&str>) -> Option<String> {
@@ -144,6 +293,20 @@ fn derive_from_claims(claims: &Value, claim_key: Option<&str>) -> Option<String>
         .map(|s| s.to_string())
 }
 
+/// Basic sanity: non-empty `[a-z0-9-]`.
+fn sanitize_tenant(raw: &str) -> Option<String> {
+    let tenant = raw.trim();
+    if !tenant.is_empty()
+        && tenant
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        Some(tenant.to_string())
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,14 +314,41 @@ mod tests {
     #[test]
     fn tenant_from_host() {
         assert_eq!(
-            derive_from_host("acme.example.com", Some("example.com")),
+            derive_from_host("acme.example.com", Some("example.com"), HostLabel::Nearest),
             Some("acme".to_string())
         );
         assert_eq!(
-            derive_from_host("foo.acme.example.com", Some("example.com")),
+            derive_from_host(
+                "foo.acme.example.com",
+                Some("example.com"),
+                HostLabel::Nearest
+            ),
             Some("acme".to_string())
         );
-        assert_eq!(derive_from_host("example.com", Some("example.com")), None);
+        assert_eq!(
+            derive_from_host(
+                "foo.acme.example.com",
+                Some("example.com"),
+                HostLabel::Leftmost
+            ),
+            Some("foo".to_string())
+        );
+        assert_eq!(
+            derive_from_host(
+                "foo.acme.example.com",
+                Some("example.com"),
+                HostLabel::Single
+            ),
+            None
+        );
+        assert_eq!(
+            derive_from_host("example.com", Some("example.com"), HostLabel::Nearest),
+            None
+        );
+        assert_eq!(
+            derive_from_host("evilexample.com", Some("example.com"), HostLabel::Nearest),
+            None
+        );
     }
 
     #[test]
@@ -169,4 +359,70 @@ mod tests {
         assert_eq!(d.tenant_id.as_deref(), Some("acme"));
         assert_eq!(d.source, TenantSource::Claim);
     }
+
+    #[test]
+    fn tenant_from_path_header_and_domain() {
+        let cfg = TenantConfig {
+            path_prefix: Some("/t/".to_string()),
+            header_name: Some("x-logline-tenant".to_string()),
+            domain_map: BTreeMap::from([("app.acme.com".to_string(), "acme".to_string())]),
+            ..TenantConfig::default()
+        };
+        let none = Value::Null;
+
+        let by_path = TenantRequest {
+            path: Some("/t/globex/settings?x=1"),
+            ..TenantRequest::default()
+        };
+        let d = resolve_tenant(&by_path, &none, &cfg);
+        assert_eq!(d.tenant_id.as_deref(), Some("globex"));
+        assert_eq!(d.source, TenantSource::Path);
+
+        let by_header = TenantRequest {
+            header_value: Some("initech"),
+            ..TenantRequest::default()
+        };
+        let d = resolve_tenant(&by_header, &none, &cfg);
+        assert_eq!(d.tenant_id.as_deref(), Some("initech"));
+        assert_eq!(d.source, TenantSource::Header);
+
+        let by_domain = TenantRequest {
+            host: Some("App.Acme.com:443"),
+            ..TenantRequest::default()
+        };
+        let d = resolve_tenant(&by_domain, &none, &cfg);
+        assert_eq!(d.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(d.source, TenantSource::CustomDomain);
+    }
+
+    #[test]
+    fn strict_mode_reports_mismatch() {
+        let claims = serde_json::json!({"tenant_id": "globex"});
+        let lenient = TenantConfig {
+            host_root: Some("example.com".to_string()),
+            ..TenantConfig::default()
+        };
+        let d = derive_tenant(Some("acme.example.com"), &claims, &lenient);
+        assert_eq!(d.tenant_id.as_deref(), Some("acme"));
+        assert!(!d.is_mismatch());
+
+        let strict = TenantConfig {
+            strict: true,
+            ..lenient
+        };
+        let d = derive_tenant(Some("acme.example.com"), &claims, &strict);
+        assert_eq!(d.tenant_id, None);
+        assert_eq!(
+            d.mismatch,
+            Some(TenantMismatch {
+                first: (TenantSource::Host, "acme".to_string()),
+                second: (TenantSource::Claim, "globex".to_string()),
+            })
+        );
+
+        let agree = serde_json::json!({"tenant_id": "acme"});
+        let d = derive_tenant(Some("acme.example.com"), &agree, &strict);
+        assert_eq!(d.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(d.source, TenantSource::Host);
+    }
 }
@@ -0,0 +1,58 @@
+//! Local audit log for sensitive operations.
+//!
+//! One JSON object per line in `audit.jsonl` next to `runtime.toml`, opened in
+//! append mode and readable by the owner only. Entries name the action, who ran
+//! it (logged-in email, else `user@host`), the auth context and session, plus
+//! action-specific details. Secret values never go in here.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::commands::{auth_context, auth_session, presence};
+
+const AUDIT_FILE: &str = "audit.jsonl";
+
+pub fn audit_path() -> PathBuf {
+    presence::config_dir().join(AUDIT_FILE)
+}
+
+fn actor() -> String {
+    crate::supabase::load_auth()
+        .and_then(|a| a.email.or(a.user_id))
+        .unwrap_or_else(|| {
+            let user = std::env::var("USER").unwrap_or_else(|_| "unknown".into());
+            format!("{user}@{}", crate::get_hostname())
+        })
+}
+
+/// Append an entry for `action`.
+pub fn record(action: &str, details: &serde_json::Value) -> anyhow::Result<()> {
+    let entry = serde_json::json!({
+        "ts": crate::chrono_now(),
+        "action": action,
+        "actor": actor(),
+        "host": crate::get_hostname(),
+        "context": auth_context::active_name(),
+        "session_id": auth_session::load_session().map(|s| s.session_id),
+        "details": details,
+    });
+
+    let path = audit_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut opts = std::fs::OpenOptions::new();
+    opts.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts
+        .open(&path)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+    writeln!(file, "{entry}").with_context(|| format!("Failed to write audit log {}", path.display()))?;
+    Ok(())
+}
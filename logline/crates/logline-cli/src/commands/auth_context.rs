@@ -0,0 +1,295 @@
+//! Named auth contexts, like kubectl contexts.
+//!
+//! A context is a Supabase project (url, anon key, optional daemon url) plus
+//! whoever is logged into it. Each context keeps its tokens, passkey and
+//! unlocked session under its own keyring service (`logline-cli:<name>`), so
+//! logging into one never touches another. The implicit `default` context uses
+//! the legacy `logline-cli` service and config sources, so existing logins keep
+//! working unchanged.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{bail, ensure};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::presence;
+use crate::commands::vault::VaultEntry;
+
+pub const DEFAULT_CONTEXT: &str = "default";
+const CONTEXTS_FILE: &str = "contexts.json";
+const KEYRING_SERVICE: &str = "logline-cli";
+
+/// Keyring users that belong to a context (see `supabase` and `auth_session`).
+pub const CONTEXT_KEYRING_USERS: &[&str] = &["auth_tokens", "passkey_ed25519", "logline_session"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthContext {
+    pub url: String,
+    pub anon_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daemon_url: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ContextFile {
+    #[serde(default)]
+    current: Option<String>,
+    #[serde(default)]
+    contexts: BTreeMap<String, AuthContext>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ContextCommands {
+    /// List contexts (* = active)
+    List,
+    /// Make a context the default for subsequent commands
+    Use {
+        name: String,
+    },
+    /// Add a context for a Supabase project
+    Add {
+        name: String,
+        /// Supabase project URL
+        #[arg(long)]
+        url: String,
+        /// Supabase anon key
+        #[arg(long)]
+        anon_key: String,
+        /// Logline daemon URL for this context (default: global setting)
+        #[arg(long)]
+        daemon_url: Option<String>,
+        /// Switch to the new context
+        #[arg(long = "use")]
+        activate: bool,
+    },
+    /// Remove a context and its stored tokens, passkey and session
+    Rm {
+        name: String,
+    },
+}
+
+/// Context chosen with `--context` / `LOGLINE_CONTEXT` for this invocation.
+static SELECTED: OnceLock<String> = OnceLock::new();
+/// contexts.json as read by [`select`], which `main` runs before any command.
+static LOADED: OnceLock<ContextFile> = OnceLock::new();
+
+fn contexts_path() -> PathBuf {
+    presence::config_dir().join(CONTEXTS_FILE)
+}
+
+/// contexts.json; empty if it doesn't exist yet. A file that can't be read or
+/// parsed is an error, so the selected project is never silently swapped for
+/// `default` and the file is never saved over.
+fn load_file() -> anyhow::Result<ContextFile> {
+    let path = contexts_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ContextFile::default()),
+        Err(e) => bail!("Failed to read {}: {e}", path.display()),
+    };
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("{} is corrupt ({e}). Fix or remove it, then re-add your contexts.", path.display()))
+}
+
+/// The contexts loaded by [`select`] (empty before it runs).
+fn loaded() -> &'static ContextFile {
+    LOADED.get_or_init(ContextFile::default)
+}
+
+fn save_file(file: &ContextFile) -> anyhow::Result<()> {
+    let path = contexts_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(file)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))
+}
+
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    ensure!(
+        !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "Invalid context name '{name}'. Use letters, digits, '-' and '_'."
+    );
+    Ok(())
+}
+
+/// Load contexts.json and pin the context for this invocation (`--context`,
+/// else `LOGLINE_CONTEXT`, else the current one).
+pub fn select(flag: Option<String>) -> anyhow::Result<()> {
+    let loaded = load_file()?;
+    let file = LOADED.get_or_init(|| loaded);
+    let Some(name) = flag.or_else(|| std::env::var("LOGLINE_CONTEXT").ok().filter(|v| !v.is_empty())) else {
+        return Ok(());
+    };
+    ensure!(
+        name == DEFAULT_CONTEXT || file.contexts.contains_key(&name),
+        "Unknown context '{name}'. See `logline auth context list`."
+    );
+    let _ = SELECTED.set(name);
+    Ok(())
+}
+
+/// True when the context was chosen for this invocation rather than defaulted.
+pub fn explicitly_selected() -> bool {
+    SELECTED.get().is_some()
+}
+
+/// Name of the context in effect.
+pub fn active_name() -> String {
+    SELECTED
+        .get()
+        .cloned()
+        .or_else(|| loaded().current.clone())
+        .unwrap_or_else(|| DEFAULT_CONTEXT.to_string())
+}
+
+/// Project settings of the active context; `None` for `default`.
+pub fn active_context() -> Option<AuthContext> {
+    let name = active_name();
+    loaded().contexts.get(&name).cloned()
+}
+
+fn service_for(name: &str) -> String {
+    if name == DEFAULT_CONTEXT {
+        KEYRING_SERVICE.to_string()
+    } else {
+        format!("{KEYRING_SERVICE}:{name}")
+    }
+}
+
+/// Vault entry `user` in the active context's namespace.
+pub fn entry(user: &str) -> VaultEntry {
+    VaultEntry::new(&service_for(&active_name()), user)
+}
+
+/// Keyring services of every context, `default` first.
+pub fn all_services() -> Vec<String> {
+    std::iter::once(DEFAULT_CONTEXT.to_string())
+        .chain(loaded().contexts.keys().cloned())
+        .map(|name| service_for(&name))
+        .collect()
+}
+
+fn logged_in_email(name: &str) -> Option<String> {
+    let json = VaultEntry::new(&service_for(name), "auth_tokens").get()?;
+    let auth: serde_json::Value = serde_json::from_str(&json).ok()?;
+    Some(auth["email"].as_str().unwrap_or("?").to_string())
+}
+
+pub fn cmd_context(command: &ContextCommands, json: bool) -> anyhow::Result<()> {
+    match command {
+        ContextCommands::List => cmd_list(json),
+        ContextCommands::Use { name } => {
+            validate_name(name)?;
+            let mut file = load_file()?;
+            ensure!(
+                name == DEFAULT_CONTEXT || file.contexts.contains_key(name),
+                "Unknown context '{name}'. Add it with `logline auth context add {name} --url ... --anon-key ...`."
+            );
+            file.current = (name != DEFAULT_CONTEXT).then(|| name.clone());
+            save_file(&file)?;
+            crate::pout(
+                json,
+                serde_json::json!({"ok": true, "current": name}),
+                &format!("✓ Switched to context '{name}'"),
+            )
+        }
+        ContextCommands::Add { name, url, anon_key, daemon_url, activate } => {
+            validate_name(name)?;
+            ensure!(name != DEFAULT_CONTEXT, "'{DEFAULT_CONTEXT}' is implicit and cannot be added.");
+            let mut file = load_file()?;
+            ensure!(
+                !file.contexts.contains_key(name),
+                "Context '{name}' already exists. Remove it first with `logline auth context rm {name}`."
+            );
+            file.contexts.insert(
+                name.clone(),
+                AuthContext {
+                    url: url.trim_end_matches('/').to_string(),
+                    anon_key: anon_key.clone(),
+                    daemon_url: daemon_url.as_ref().map(|u| u.trim_end_matches('/').to_string()),
+                },
+            );
+            if *activate {
+                file.current = Some(name.clone());
+            }
+            save_file(&file)?;
+            crate::pout(
+                json,
+                serde_json::json!({"ok": true, "name": name, "url": url, "current": activate}),
+                &format!(
+                    "✓ Added context '{name}'{}\nLog in with: logline --context {name} auth login",
+                    if *activate { " (active)" } else { "" }
+                ),
+            )
+        }
+        ContextCommands::Rm { name } => {
+            validate_name(name)?;
+            ensure!(name != DEFAULT_CONTEXT, "The '{DEFAULT_CONTEXT}' context cannot be removed.");
+            let mut file = load_file()?;
+            if file.contexts.remove(name).is_none() {
+                bail!("Unknown context '{name}'.");
+            }
+            let had_passkey = VaultEntry::new(&service_for(name), "passkey_ed25519").get().is_some();
+            for user in CONTEXT_KEYRING_USERS {
+                VaultEntry::new(&service_for(name), user).delete()?;
+            }
+            if file.current.as_deref() == Some(name) {
+                file.current = None;
+            }
+            save_file(&file)?;
+            let note = if had_passkey {
+                "\nIts passkey is still active server-side; revoke it from another context with `logline auth passkey revoke`."
+            } else {
+                ""
+            };
+            crate::pout(
+                json,
+                serde_json::json!({"ok": true, "removed": name, "passkey_left_active": had_passkey}),
+                &format!("✓ Removed context '{name}' and its stored credentials{note}"),
+            )
+        }
+    }
+}
+
+fn cmd_list(json: bool) -> anyhow::Result<()> {
+    let file = load_file()?;
+    let active = active_name();
+
+    let mut rows = vec![serde_json::json!({
+        "name": DEFAULT_CONTEXT,
+        "url": crate::supabase::SupabaseConfig::legacy().ok().map(|c| c.url),
+        "email": logged_in_email(DEFAULT_CONTEXT),
+        "active": active == DEFAULT_CONTEXT,
+    })];
+    for (name, ctx) in &file.contexts {
+        rows.push(serde_json::json!({
+            "name": name,
+            "url": ctx.url,
+            "email": logged_in_email(name),
+            "active": &active == name,
+        }));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    println!("   {:<16} {:<44} USER", "NAME", "URL");
+    for r in &rows {
+        println!(
+            "{:<2} {:<16} {:<44} {}",
+            if r["active"].as_bool() == Some(true) { "*" } else { "" },
+            r["name"].as_str().unwrap_or("?"),
+            r["url"].as_str().unwrap_or("-"),
+            r["email"].as_str().unwrap_or("(not logged in)"),
+        );
+    }
+    Ok(())
+}
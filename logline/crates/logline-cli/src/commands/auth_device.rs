@@ -0,0 +1,301 @@
+//! Challenge-based logins against the logline daemon.
+//!
+//! Both flows open a `cli_auth_challenges` row:
+//! - browser approval ("device authorization"): show the approval URL and a
+//!   short code, then poll until a signed-in user approves or denies it (or
+//!   the challenge expires). Works for SSO accounts and on headless machines.
+//! - passkey: sign the challenge nonce with the registered Ed25519 key and
+//!   submit the signature; the daemon verifies it against
+//!   `cli_passkey_credentials` before minting a session.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::commands::auth_context;
+use crate::supabase::{config_dir, save_auth, StoredAuth, SupabaseClient};
+
+const DEFAULT_DAEMON_URL: &str = "https://logline.voulezvous.tv";
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 3;
+/// Upper bound on how long we poll, even if the server never expires the challenge.
+const MAX_WAIT_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Deserialize)]
+struct ChallengeResponse {
+    challenge_id: String,
+    nonce: String,
+    challenge_url: String,
+    #[serde(default)]
+    expires_at: Option<String>,
+    /// Optional server-provided code; otherwise derived from the nonce.
+    #[serde(default)]
+    user_code: Option<String>,
+    #[serde(default)]
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChallengeStatus {
+    status: String,
+    #[serde(default)]
+    access_token: Option<String>,
+    /// Older daemons return only `session_token` (the access token).
+    #[serde(default)]
+    session_token: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    user_id: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+/// Base URL of the logline daemon that owns `/v1/cli/auth/*`.
+///
+/// `LOGLINE_DAEMON_URL` env, then `daemon_url` in ~/.config/logline/config.json,
+/// then the hosted default (same resolution order as the web app).
+pub fn daemon_url() -> String {
+    if let Ok(url) = std::env::var("LOGLINE_DAEMON_URL") {
+        if !url.trim().is_empty() {
+            return url.trim().trim_end_matches('/').to_string();
+        }
+    }
+    if let Some(url) = auth_context::active_context().and_then(|c| c.daemon_url) {
+        return url;
+    }
+    let from_config = std::fs::read_to_string(config_dir().join("config.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        .and_then(|v| v["daemon_url"].as_str().map(str::to_string));
+    match from_config {
+        Some(url) if !url.trim().is_empty() => url.trim().trim_end_matches('/').to_string(),
+        _ => DEFAULT_DAEMON_URL.to_string(),
+    }
+}
+
+/// Short human-comparable code shown in the terminal and on the approval page.
+///
+/// First 8 alphanumerics of the nonce, upper-cased, as `XXXX-XXXX`.
+fn short_code(nonce: &str) -> String {
+    let chars: String = nonce
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(8)
+        .collect::<String>()
+        .to_uppercase();
+    if chars.len() == 8 {
+        format!("{}-{}", &chars[..4], &chars[4..])
+    } else {
+        chars
+    }
+}
+
+pub fn cmd_login_device(
+    client: &SupabaseClient,
+    device_name: Option<String>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let base = daemon_url();
+    let http = Client::builder().timeout(Duration::from_secs(15)).build()?;
+    let device = device_name.unwrap_or_else(crate::get_hostname);
+
+    let challenge = create_challenge(&http, &base, &serde_json::json!({ "device_name": device }))?;
+    let code = challenge
+        .user_code
+        .clone()
+        .unwrap_or_else(|| short_code(&challenge.nonce));
+
+    // Prompts go to stderr so `--json` keeps stdout machine-readable.
+    eprintln!("To approve this login, open:\n\n  {}\n", challenge.challenge_url);
+    eprintln!("and confirm the code:  {code}");
+    eprintln!("Device: {device}");
+    if let Some(exp) = &challenge.expires_at {
+        eprintln!("Expires: {exp}");
+    }
+    eprintln!("\nWaiting for approval (Ctrl-C to cancel)...");
+
+    let interval = Duration::from_secs(
+        challenge
+            .interval
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS)
+            .max(1),
+    );
+    let deadline = Instant::now() + Duration::from_secs(MAX_WAIT_SECS);
+    let status_url = format!(
+        "{base}/v1/cli/auth/challenge/{}/status",
+        challenge.challenge_id
+    );
+
+    let approved = loop {
+        if Instant::now() >= deadline {
+            bail!("Timed out waiting for approval. Run `logline auth login --device` again.");
+        }
+        std::thread::sleep(interval);
+
+        let resp = match http.get(&status_url).send() {
+            Ok(r) => r,
+            Err(e) => {
+                // Transient network errors: keep polling until the deadline.
+                eprintln!("  (poll failed: {e}; retrying)");
+                continue;
+            }
+        };
+        match resp.status() {
+            StatusCode::GONE => bail!("Auth challenge expired. Run `logline auth login --device` again."),
+            StatusCode::CONFLICT => bail!("Auth challenge was already used. Run `logline auth login --device` again."),
+            s if !s.is_success() => {
+                let body = resp.text().unwrap_or_default();
+                bail!("Checking auth challenge failed ({s}): {body}");
+            }
+            _ => {}
+        }
+
+        let status: ChallengeStatus = resp.json().context("Invalid challenge status response")?;
+        match status.status.as_str() {
+            "pending" => {}
+            "approved" => break status,
+            "denied" => bail!("Login was denied in the browser."),
+            "expired" => bail!("Auth challenge expired. Run `logline auth login --device` again."),
+            other => bail!("Unexpected challenge status: {other}"),
+        }
+    };
+
+    let (user_id, email) = store_session(client, approved, "device")?;
+
+    crate::pout(json, serde_json::json!({
+        "ok": true,
+        "user_id": user_id,
+        "email": email,
+        "device_name": device,
+        "auth_method": "device",
+    }), &format!("✓ Logged in as {} ({user_id}) via browser approval", email.as_deref().unwrap_or("?")))?;
+
+    Ok(())
+}
+
+
+/// Answer a passkey challenge with the stored Ed25519 key and store the session.
+///
+/// `passkey` is the keychain record written by `auth passkey-register`.
+/// Returns `(user_id, email)` of the new session.
+pub fn login_with_passkey(
+    client: &SupabaseClient,
+    passkey: &serde_json::Value,
+    user_id: &str,
+) -> anyhow::Result<(String, Option<String>)> {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let device = passkey["device_name"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Stored passkey has no device_name. Re-run `logline auth passkey-register`."))?;
+    let private_key: [u8; 32] = passkey["private_key"]
+        .as_str()
+        .and_then(|h| hex::decode(h).ok())
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Stored passkey is corrupt. Re-run `logline auth passkey-register`."))?;
+    let signing_key = SigningKey::from_bytes(&private_key);
+
+    let base = daemon_url();
+    let http = Client::builder().timeout(Duration::from_secs(15)).build()?;
+    let challenge = create_challenge(&http, &base, &serde_json::json!({
+        "device_name": device,
+        "user_id": user_id,
+        "method": "passkey",
+    }))?;
+
+    let payload = logline_auth::passkey_signing_payload(
+        &challenge.challenge_id,
+        &challenge.nonce,
+        user_id,
+        device,
+    );
+    let assertion = logline_auth::PasskeyAssertion {
+        challenge_id: challenge.challenge_id.clone(),
+        user_id: user_id.to_string(),
+        device_name: device.to_string(),
+        signature: hex::encode(signing_key.sign(&payload).to_bytes()),
+    };
+
+    let resp = http
+        .post(format!("{base}/v1/cli/auth/challenge/{}/passkey", challenge.challenge_id))
+        .json(&assertion)
+        .send()
+        .with_context(|| format!("Failed to reach logline daemon at {base}"))?;
+    match resp.status() {
+        StatusCode::GONE => bail!("Passkey challenge expired. Try again."),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            let body = resp.text().unwrap_or_default();
+            bail!("Passkey rejected: {body}\nIf this device's key was rotated or revoked, run `logline auth passkey-register`.");
+        }
+        s if !s.is_success() => {
+            let body = resp.text().unwrap_or_default();
+            bail!("Passkey login failed ({s}): {body}");
+        }
+        _ => {}
+    }
+
+    let approved: ChallengeStatus = resp.json().context("Invalid passkey login response")?;
+    if approved.status != "approved" {
+        bail!("Passkey challenge not approved (status: {})", approved.status);
+    }
+    store_session(client, approved, "passkey")
+}
+
+fn create_challenge(
+    http: &Client,
+    base: &str,
+    body: &serde_json::Value,
+) -> anyhow::Result<ChallengeResponse> {
+    let resp = http
+        .post(format!("{base}/v1/cli/auth/challenge"))
+        .json(body)
+        .send()
+        .with_context(|| format!("Failed to reach logline daemon at {base}"))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().unwrap_or_default();
+        bail!("Creating auth challenge failed ({status}): {body}");
+    }
+    resp.json().context("Invalid challenge response")
+}
+
+/// Persist the tokens of an approved challenge via `save_auth`.
+fn store_session(
+    client: &SupabaseClient,
+    approved: ChallengeStatus,
+    auth_method: &str,
+) -> anyhow::Result<(String, Option<String>)> {
+    let access_token = approved
+        .access_token
+        .or(approved.session_token)
+        .ok_or_else(|| anyhow::anyhow!("Challenge approved but no token was returned"))?;
+
+    // Fill in identity from Supabase when the daemon doesn't echo it.
+    let (user_id, email) = if let Some(id) = approved.user_id {
+        (id, approved.email)
+    } else {
+        let user = client.get_user(&access_token)?;
+        let id = user["id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Cannot determine user_id"))?
+            .to_string();
+        (id, user["email"].as_str().map(str::to_string))
+    };
+
+    let stored = StoredAuth {
+        access_token,
+        // Without a refresh token, get_valid_token asks for a new login on expiry.
+        refresh_token: approved.refresh_token.unwrap_or_default(),
+        user_id: Some(user_id.clone()),
+        email: email.clone(),
+        expires_at: approved.expires_in.map(|s| crate::now_secs() + s),
+        auth_method: Some(auth_method.into()),
+    };
+    save_auth(&stored)?;
+    Ok((user_id, email))
+}
@@ -1,28 +1,120 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, ensure};
 use clap::Subcommand;
 
-use crate::commands::secrets;
+use crate::commands::auth_context;
+use crate::commands::presence;
 
+/// Keyring user of the sealed session, in the active auth context's namespace.
 const SESSION_KEY: &str = "logline_session";
+const DEVICE_KEY_FILE: &str = "session.key";
+const DEVICE_KEY_ID: &str = "device";
 
+/// An unlocked session. Stored in the keyring as a value sealed with the
+/// device key (see [`session_codec`]), so editing any field breaks the MAC.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SessionToken {
     pub session_id: String,
-    pub expires_at: u64,
+    pub user_id: Option<String>,
+    pub host: String,
     pub opened_by: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    /// Granted scope -> expiry (epoch secs). `*` and `area:*` are wildcards.
+    #[serde(default)]
+    pub scopes: BTreeMap<String, u64>,
+}
+
+/// Scopes privileged commands can require.
+pub const KNOWN_SCOPES: &[&str] = &[
+    "secrets:read",
+    "secrets:exec",
+    "secrets:export",
+    "secrets:import",
+    "secrets:rotate",
+    "db:read",
+    "db:write",
+    "db:migrate",
+    "deploy:preview",
+    "deploy:prod",
+    "cicd:run",
+    "dev:run",
+];
+
+/// Production-affecting scopes get a shorter maximum TTL, even under `*`.
+const SCOPE_TTL_CAPS: &[(&str, u64)] = &[("deploy:prod", 15 * 60), ("db:migrate", 15 * 60)];
+
+fn scope_ttl_cap(scope: &str) -> Option<u64> {
+    SCOPE_TTL_CAPS.iter().find(|(s, _)| *s == scope).map(|(_, cap)| *cap)
+}
+
+fn scope_covers(grant: &str, scope: &str) -> bool {
+    grant == "*"
+        || grant == scope
+        || grant
+            .strip_suffix(":*")
+            .is_some_and(|area| scope.split(':').next() == Some(area))
+}
+
+fn validate_scope(scope: &str) -> anyhow::Result<()> {
+    ensure!(
+        KNOWN_SCOPES.iter().any(|known| scope_covers(scope, known)),
+        "Unknown scope '{scope}'. Known scopes: {}",
+        KNOWN_SCOPES.join(", ")
+    );
+    Ok(())
+}
+
+/// Expand requested scopes into grants. Capped scopes covered by a wildcard
+/// get their own, shorter entry so the cap also applies to `*`.
+fn grant_scopes(requested: &[String], now: u64, ttl_secs: u64) -> anyhow::Result<BTreeMap<String, u64>> {
+    let mut grants = BTreeMap::new();
+    for scope in requested {
+        validate_scope(scope)?;
+        let ttl = scope_ttl_cap(scope).map_or(ttl_secs, |cap| ttl_secs.min(cap));
+        grants.insert(scope.clone(), now + ttl);
+        for (capped, cap) in SCOPE_TTL_CAPS {
+            if scope != capped && scope_covers(scope, capped) && ttl_secs > *cap {
+                grants.entry((*capped).to_string()).or_insert(now + cap);
+            }
+        }
+    }
+    Ok(grants)
+}
+
+impl SessionToken {
+    /// Expiry of `scope` in this session: the most specific grant wins.
+    #[must_use]
+    pub fn scope_expiry(&self, scope: &str) -> Option<u64> {
+        let area_wildcard = scope.split(':').next().map(|area| format!("{area}:*"));
+        [Some(scope.to_string()), area_wildcard, Some("*".to_string())]
+            .into_iter()
+            .flatten()
+            .find_map(|grant| self.scopes.get(&grant).copied())
+    }
+
+    /// True if `scope` is granted and not yet expired.
+    #[must_use]
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scope_expiry(scope).is_some_and(|exp| exp > now_secs())
+    }
 }
 
 #[derive(Debug, Subcommand)]
 pub enum SessionCommands {
-    /// Unlock session with Touch ID (required before any privileged command)
+    /// Unlock session after a user-presence check (required before any privileged command)
     Unlock {
         /// Session TTL (e.g. "5m", "30m", "2h"). Default: 30m
         #[arg(long, default_value = "30m")]
         ttl: String,
+        /// Scopes to unlock, comma-separated (e.g. "secrets:read,db:read"). Default: all
+        #[arg(long, value_delimiter = ',')]
+        scope: Vec<String>,
     },
     /// Lock session immediately (revoke access)
     Lock,
-    /// Show session status and remaining TTL
+    /// Show session status and the time left on each scope
     Status,
 }
 
@@ -43,112 +135,177 @@ fn parse_ttl(ttl: &str) -> anyhow::Result<u64> {
     bail!("Invalid TTL format: {ttl}. Use e.g. '5m', '30m', '2h'")
 }
 
+/// 128 random bits from the OS RNG.
 fn generate_session_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
+    use rand::RngCore;
+    let mut bytes = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("s_{}", hex::encode(bytes))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
-        .as_millis();
-    format!("s_{:x}", ts & 0xFFFF_FFFF)
+        .as_secs()
+}
+
+fn device_key_path() -> std::path::PathBuf {
+    presence::config_dir().join(DEVICE_KEY_FILE)
 }
 
-fn touch_id_prompt() -> anyhow::Result<()> {
-    if !cfg!(target_os = "macos") {
-        eprint!("Press Enter to confirm identity: ");
-        let mut buf = String::new();
-        std::io::stdin().read_line(&mut buf)?;
-        return Ok(());
+/// Per-machine MAC key. Kept in a 0600 file rather than the keyring, so a
+/// process that can only write the keyring cannot mint or extend a session.
+fn load_device_key() -> Option<Vec<u8>> {
+    let hex_key = std::fs::read_to_string(device_key_path()).ok()?;
+    hex::decode(hex_key.trim()).ok().filter(|k| k.len() == 32)
+}
+
+/// Write `body` to `path`, readable by the owner only.
+pub fn write_private_file(path: &std::path::Path, body: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
     }
+    let mut file = opts
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))?;
+    file.write_all(body)?;
+    Ok(())
+}
 
-    eprintln!("Touch ID required...");
-    let result = std::process::Command::new("swift")
-        .arg("-e")
-        .arg(
-            r#"
-import LocalAuthentication
-import Foundation
-let ctx = LAContext()
-var err: NSError?
-guard ctx.canEvaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, error: &err) else {
-    fputs("biometrics unavailable: \(err?.localizedDescription ?? "unknown")\n", stderr)
-    exit(1)
-}
-let sema = DispatchSemaphore(value: 0)
-var ok = false
-ctx.evaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, localizedReason: "Logline CLI — unlock session") { success, _ in
-    ok = success
-    sema.signal()
-}
-sema.wait()
-exit(ok ? 0 : 1)
-"#,
-        )
-        .output();
+fn load_or_create_device_key() -> anyhow::Result<Vec<u8>> {
+    use rand::RngCore;
 
-    match result {
-        Ok(out) if out.status.success() => Ok(()),
-        Ok(_) => bail!("Touch ID authentication failed or was cancelled."),
-        Err(e) => {
-            eprintln!("Touch ID unavailable ({e}), falling back to Enter confirmation.");
-            eprint!("Press Enter to confirm identity: ");
-            let mut buf = String::new();
-            std::io::stdin().read_line(&mut buf)?;
-            Ok(())
-        }
+    if let Some(key) = load_device_key() {
+        return Ok(key);
     }
+    let mut key = vec![0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    write_private_file(&device_key_path(), hex::encode(&key).as_bytes())?;
+    Ok(key)
 }
 
-fn now_secs() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs()
+fn session_codec(device_key: &[u8]) -> anyhow::Result<logline_auth::SessionCodec> {
+    let key = logline_auth::SessionKey::new(DEVICE_KEY_ID, device_key)
+        .map_err(|e| anyhow::anyhow!("Invalid device key: {e}"))?;
+    Ok(logline_auth::SessionCodec::new(key))
+}
+
+fn current_user_id() -> Option<String> {
+    crate::supabase::load_auth().and_then(|a| a.user_id)
+}
+
+enum SessionCheck {
+    Locked,
+    Expired,
+    Invalid(String),
+    Active(SessionToken),
+}
+
+/// Verify the stored session: MAC, expiry, host and user. Fails closed.
+fn check_session() -> SessionCheck {
+    let Some(sealed) = auth_context::entry(SESSION_KEY).get() else {
+        return SessionCheck::Locked;
+    };
+    let Some(device_key) = load_device_key() else {
+        return SessionCheck::Invalid("device key missing".into());
+    };
+    let codec = match session_codec(&device_key) {
+        Ok(c) => c,
+        Err(e) => return SessionCheck::Invalid(e.to_string()),
+    };
+    let session: SessionToken = match codec.open_json(&sealed) {
+        Ok(s) => s,
+        Err(logline_auth::Error::CookieExpired) => return SessionCheck::Expired,
+        Err(e) => return SessionCheck::Invalid(format!("signature check failed: {e}")),
+    };
+    if session.expires_at <= now_secs() {
+        return SessionCheck::Expired;
+    }
+    if session.host != crate::get_hostname() {
+        return SessionCheck::Invalid(format!("opened on another host ({})", session.host));
+    }
+    if session.user_id != current_user_id() {
+        return SessionCheck::Invalid("opened by another user".into());
+    }
+    SessionCheck::Active(session)
 }
 
+/// The active session, if one verifies.
 pub fn load_session() -> Option<SessionToken> {
-    let json = secrets::load_credential(SESSION_KEY)?;
-    serde_json::from_str(&json).ok()
+    match check_session() {
+        SessionCheck::Active(s) => Some(s),
+        _ => None,
+    }
 }
 
-fn save_session(token: &SessionToken) -> anyhow::Result<()> {
-    let json = serde_json::to_string(token)?;
-    secrets::store_credential(SESSION_KEY, &json)
+fn save_session(token: &SessionToken, ttl_secs: u64) -> anyhow::Result<()> {
+    let codec = session_codec(&load_or_create_device_key()?)?;
+    let sealed = codec
+        .seal_json(token, ttl_secs)
+        .map_err(|e| anyhow::anyhow!("Failed to seal session: {e}"))?;
+    auth_context::entry(SESSION_KEY)
+        .set(&sealed)
+        .map_err(|e| anyhow::anyhow!("Failed to store session: {e}"))
 }
 
 fn delete_session() -> anyhow::Result<()> {
-    let entry = keyring::Entry::new("logline-cli", SESSION_KEY)
-        .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-    match entry.delete_credential() {
-        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
-        Err(e) => bail!("Failed to clear session: {e}"),
-    }
+    auth_context::entry(SESSION_KEY)
+        .delete()
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!("Failed to clear session: {e}"))
 }
 
-/// Gate: call at the top of every privileged command.
-/// Returns the active session or a clear error.
-pub fn require_unlocked() -> anyhow::Result<SessionToken> {
-    let session = load_session().ok_or_else(|| {
-        anyhow::anyhow!("Session locked. Run `logline auth unlock` first.")
-    })?;
-    ensure!(
-        session.expires_at > now_secs(),
-        "Session expired. Run `logline auth unlock` to re-authenticate."
-    );
-    Ok(session)
+/// Gate: call at the top of every privileged command with the scope it needs.
+/// Returns the active session or a clear error; a tampered session is rejected.
+pub fn require_unlocked(scope: &str) -> anyhow::Result<SessionToken> {
+    let session = match check_session() {
+        SessionCheck::Active(s) => s,
+        SessionCheck::Locked => bail!("Session locked. Run `logline auth unlock --scope {scope}` first."),
+        SessionCheck::Expired => {
+            bail!("Session expired. Run `logline auth unlock --scope {scope}` to re-authenticate.")
+        }
+        SessionCheck::Invalid(why) => bail!(
+            "Session rejected ({why}). Run `logline auth unlock --scope {scope}` to re-authenticate."
+        ),
+    };
+    match session.scope_expiry(scope) {
+        Some(exp) if exp > now_secs() => Ok(session),
+        Some(_) => bail!("Scope '{scope}' expired. Run `logline auth unlock --scope {scope}`."),
+        None => bail!(
+            "Session does not grant '{scope}'. Run `logline auth unlock --scope {scope}`."
+        ),
+    }
 }
 
 pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<()> {
     match command {
-        SessionCommands::Unlock { ttl } => {
+        SessionCommands::Unlock { ttl, scope } => {
             let ttl_secs = parse_ttl(&ttl)?;
-            touch_id_prompt()?;
+            let requested = if scope.is_empty() { vec!["*".to_string()] } else { scope };
+            let now = now_secs();
+            let scopes = grant_scopes(&requested, now, ttl_secs)?;
+            let method = presence::require_presence("Logline CLI — unlock session")?;
 
             let session = SessionToken {
                 session_id: generate_session_id(),
-                expires_at: now_secs() + ttl_secs,
-                opened_by: "touch_id".into(),
+                user_id: current_user_id(),
+                host: crate::get_hostname(),
+                opened_by: method.into(),
+                issued_at: now,
+                expires_at: scopes.values().copied().max().unwrap_or(now),
+                scopes,
             };
-            save_session(&session)?;
+            save_session(&session, session.expires_at - now)?;
 
             let expires_str = format_expires(session.expires_at);
             crate::pout(
@@ -158,10 +315,14 @@ pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<
                     "session_id": session.session_id,
                     "expires_at": session.expires_at,
                     "ttl_seconds": ttl_secs,
+                    "opened_by": session.opened_by,
+                    "host": session.host,
+                    "scopes": session.scopes,
                 }),
                 &format!(
-                    "Session active until {expires_str}. ID: {}",
-                    session.session_id
+                    "Session active until {expires_str}. ID: {}\n{}",
+                    session.session_id,
+                    format_scopes(&session.scopes)
                 ),
             )
         }
@@ -174,9 +335,9 @@ pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<
             )
         }
         SessionCommands::Status => {
-            match load_session() {
-                Some(s) if s.expires_at > now_secs() => {
-                    let remaining = s.expires_at - now_secs();
+            match check_session() {
+                SessionCheck::Active(s) => {
+                    let remaining = s.expires_at.saturating_sub(now_secs());
                     let mins = remaining / 60;
                     let secs = remaining % 60;
                     crate::pout(
@@ -186,14 +347,19 @@ pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<
                             "session_id": s.session_id,
                             "expires_at": s.expires_at,
                             "remaining_seconds": remaining,
+                            "opened_by": s.opened_by,
+                            "host": s.host,
+                            "scopes": scope_status(&s.scopes),
                         }),
                         &format!(
-                            "Unlocked — {mins}m {secs}s remaining. ID: {}",
-                            s.session_id
+                            "Unlocked via {} — {mins}m {secs}s remaining. ID: {}\n{}",
+                            s.opened_by,
+                            s.session_id,
+                            format_scopes(&s.scopes)
                         ),
                     )
                 }
-                Some(_) => {
+                SessionCheck::Expired => {
                     delete_session()?;
                     crate::pout(
                         json,
@@ -201,7 +367,15 @@ pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<
                         "Session expired. Run `logline auth unlock` to re-authenticate.",
                     )
                 }
-                None => crate::pout(
+                SessionCheck::Invalid(why) => {
+                    delete_session()?;
+                    crate::pout(
+                        json,
+                        serde_json::json!({"unlocked": false, "reason": "invalid", "detail": why}),
+                        &format!("Session rejected ({why}) and cleared. Run `logline auth unlock`."),
+                    )
+                }
+                SessionCheck::Locked => crate::pout(
                     json,
                     serde_json::json!({"unlocked": false, "reason": "no_session"}),
                     "No active session. Run `logline auth unlock` first.",
@@ -211,6 +385,36 @@ pub fn cmd_auth_session(command: SessionCommands, json: bool) -> anyhow::Result<
     }
 }
 
+fn scope_status(scopes: &BTreeMap<String, u64>) -> Vec<serde_json::Value> {
+    let now = now_secs();
+    scopes
+        .iter()
+        .map(|(scope, exp)| {
+            serde_json::json!({
+                "scope": scope,
+                "expires_at": exp,
+                "remaining_seconds": exp.saturating_sub(now),
+            })
+        })
+        .collect()
+}
+
+fn format_scopes(scopes: &BTreeMap<String, u64>) -> String {
+    let now = now_secs();
+    scopes
+        .iter()
+        .map(|(scope, exp)| {
+            if *exp > now {
+                let remaining = exp - now;
+                format!("  {scope:<16} {}m {}s", remaining / 60, remaining % 60)
+            } else {
+                format!("  {scope:<16} expired")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn format_expires(epoch: u64) -> String {
     let now = now_secs();
     if epoch <= now {
@@ -226,116 +430,135 @@ fn format_expires(epoch: u64) -> String {
 // Auth Identity — WHO is logged in, HOW, and WHAT capabilities they have
 // ═══════════════════════════════════════════════════════════════════════════
 
-const IDENTITY_CACHE_KEY: &str = "logline_identity_cache";
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Founder status as far as verified token claims can prove it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum FounderStatus {
+    Founder,
+    NotFounder,
+    /// Could not be verified (offline, no JWKS, unverifiable token, ...).
+    Unknown(String),
+}
+
+/// How an infra gate treats [`FounderStatus::Unknown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnUnknownFounder {
+    Refuse,
+    Allow,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct AuthIdentity {
     pub user_id: String,
     pub email: Option<String>,
     pub auth_method: String,
-    pub is_founder: bool,
+    pub founder: FounderStatus,
     pub profile: String,
+    /// Decision taken by `require_non_founder`, for receipts.
+    pub founder_gate: Option<&'static str>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct IdentityCache {
-    user_id: String,
-    is_founder: bool,
-    cached_at: u64,
-}
-
-impl IdentityCache {
-    fn is_valid(&self) -> bool {
-        now_secs().saturating_sub(self.cached_at) < 1800
+impl AuthIdentity {
+    pub fn is_founder(&self) -> bool {
+        self.founder == FounderStatus::Founder
     }
 }
 
-fn load_identity_cache() -> Option<IdentityCache> {
-    let json = secrets::load_credential(IDENTITY_CACHE_KEY)?;
-    serde_json::from_str(&json).ok()
-}
-
-fn save_identity_cache(cache: &IdentityCache) -> anyhow::Result<()> {
-    let json = serde_json::to_string(cache)?;
-    secrets::store_credential(IDENTITY_CACHE_KEY, &json)
-}
-
-fn check_founder_remote(user_id: &str) -> anyhow::Result<bool> {
-    let supabase_url = secrets::load_credential_or_env("supabase_url", "NEXT_PUBLIC_SUPABASE_URL")
-        .ok_or_else(|| anyhow::anyhow!("supabase_url not configured"))?;
-    let anon_key = secrets::load_credential_or_env("supabase_anon_key", "NEXT_PUBLIC_SUPABASE_ANON_KEY")
-        .ok_or_else(|| anyhow::anyhow!("supabase_anon_key not configured"))?;
-
-    let auth = crate::supabase::load_auth()
-        .ok_or_else(|| anyhow::anyhow!("Not logged in"))?;
-
-    let url = format!(
-        "{}/rest/v1/user_capabilities?select=capability&user_id=eq.{}&capability=eq.founder",
-        supabase_url, user_id
-    );
-
+fn fetch_jwks(supabase_url: &str, anon_key: &str) -> anyhow::Result<logline_auth::JwksSet> {
     let client = reqwest::blocking::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
         .build()?;
-
     let resp = client
-        .get(&url)
-        .header("apikey", &anon_key)
-        .header("Authorization", format!("Bearer {}", auth.access_token))
+        .get(format!("{supabase_url}/auth/v1/.well-known/jwks.json"))
+        .header("apikey", anon_key)
         .send()?;
-
     if !resp.status().is_success() {
-        bail!("PostgREST query failed: {}", resp.status());
+        bail!("JWKS request failed: {}", resp.status());
     }
-
-    let body: serde_json::Value = resp.json()?;
-    let is_founder = body
-        .as_array()
-        .is_some_and(|arr| !arr.is_empty());
-
-    Ok(is_founder)
+    Ok(resp.json()?)
 }
 
-fn resolve_founder_status(user_id: &str) -> bool {
-    if let Some(cache) = load_identity_cache() {
-        if cache.user_id == user_id && cache.is_valid() {
-            return cache.is_founder;
+/// Fresh JWKS when reachable, else the last one fetched for this auth context
+/// (kept next to the device key, outside the keyring).
+fn load_jwks(supabase_url: &str, anon_key: &str) -> anyhow::Result<logline_auth::JwksSet> {
+    let path = presence::config_dir().join(format!("jwks.{}.json", auth_context::active_name()));
+    match fetch_jwks(supabase_url, anon_key) {
+        Ok(set) => {
+            let _ = write_private_file(&path, serde_json::to_string(&set)?.as_bytes());
+            Ok(set)
+        }
+        Err(e) => {
+            let cached = std::fs::read_to_string(&path)
+                .map_err(|_| anyhow::anyhow!("cannot fetch JWKS ({e}) and none cached"))?;
+            Ok(serde_json::from_str(&cached)?)
         }
     }
+}
 
-    match check_founder_remote(user_id) {
-        Ok(is_founder) => {
-            let _ = save_identity_cache(&IdentityCache {
-                user_id: user_id.to_string(),
-                is_founder,
-                cached_at: now_secs(),
-            });
-            is_founder
-        }
-        Err(_) => {
-            if let Some(cache) = load_identity_cache() {
-                if cache.user_id == user_id {
-                    return cache.is_founder;
-                }
-            }
-            true
+/// Founder status from the `capabilities` claim of the verified access token.
+/// Never guesses: anything that cannot be verified is `Unknown`.
+fn resolve_founder_status(access_token: &str, user_id: &str) -> FounderStatus {
+    let Ok(config) = crate::supabase::SupabaseConfig::from_env_or_file() else {
+        return FounderStatus::Unknown("supabase config not found".into());
+    };
+    let jwks = match load_jwks(&config.url, &config.anon_key) {
+        Ok(j) => j,
+        Err(e) => return FounderStatus::Unknown(e.to_string()),
+    };
+    let opts = logline_auth::VerifyOptions {
+        issuer: Some(format!("{}/auth/v1", config.url.trim_end_matches('/'))),
+        audience: Some("authenticated".into()),
+        ..Default::default()
+    };
+    let verified = match logline_auth::JwtVerifier::default().verify_with_jwks(access_token, &jwks, &opts) {
+        Ok(v) => v,
+        Err(e) => return FounderStatus::Unknown(format!("access token not verifiable: {e}")),
+    };
+    if verified.sub() != Some(user_id) {
+        return FounderStatus::Unknown("access token is for another user".into());
+    }
+    if verified.claim("capabilities").is_none() {
+        return FounderStatus::Unknown("access token carries no capabilities claim".into());
+    }
+    match verified.supabase_claims() {
+        Ok(claims) if claims.is_founder() => FounderStatus::Founder,
+        Ok(_) => FounderStatus::NotFounder,
+        Err(e) => FounderStatus::Unknown(format!("malformed claims: {e}")),
+    }
+}
+
+/// Current access token, refreshed first if it has expired (best effort).
+fn current_access_token(auth: &crate::supabase::StoredAuth) -> String {
+    let expired = auth.expires_at.is_some_and(|exp| exp <= now_secs());
+    if expired {
+        let refreshed = crate::supabase::SupabaseConfig::from_env_or_file()
+            .and_then(crate::supabase::SupabaseClient::new)
+            .and_then(|client| crate::supabase::get_valid_token(&client));
+        if let Ok(token) = refreshed {
+            return token;
         }
     }
+    auth.access_token.clone()
 }
 
 pub fn load_identity() -> Option<AuthIdentity> {
     let auth = crate::supabase::load_auth()?;
     let user_id = auth.user_id.clone()?;
-    let method = auth.auth_method.unwrap_or_else(|| "unknown".into());
-    let is_founder = resolve_founder_status(&user_id);
-    let profile = if is_founder { "founder" } else { "operator" };
+    let founder = resolve_founder_status(&current_access_token(&auth), &user_id);
+    let profile = match founder {
+        FounderStatus::Founder => "founder",
+        FounderStatus::NotFounder => "operator",
+        FounderStatus::Unknown(_) => "unknown",
+    };
 
     Some(AuthIdentity {
         user_id,
         email: auth.email,
-        auth_method: method,
-        is_founder,
+        auth_method: auth.auth_method.unwrap_or_else(|| "unknown".into()),
+        founder,
         profile: profile.into(),
+        founder_gate: None,
     })
 }
 
@@ -360,24 +583,40 @@ pub fn require_passkey_identity() -> anyhow::Result<AuthIdentity> {
     Ok(identity)
 }
 
-pub fn require_non_founder(identity: &AuthIdentity) -> anyhow::Result<()> {
-    ensure!(
-        !identity.is_founder,
-        "Infra commands cannot run as founder/god mode.\n\
-         Current identity: {} ({})\n\
-         Founder mode is reserved for `logline founder bootstrap` only.\n\
-         Fix: log in as your operator/service user, not the founder account.",
-        identity.email.as_deref().unwrap_or("?"),
-        identity.user_id
-    );
+/// Refuse founders; `on_unknown` decides when founder status cannot be verified.
+/// The decision is recorded in `identity.founder_gate`.
+pub fn require_non_founder(identity: &mut AuthIdentity, on_unknown: OnUnknownFounder) -> anyhow::Result<()> {
+    match (&identity.founder, on_unknown) {
+        (FounderStatus::Founder, _) => bail!(
+            "Infra commands cannot run as founder/god mode.\n\
+             Current identity: {} ({})\n\
+             Founder mode is reserved for `logline founder bootstrap` only.\n\
+             Fix: log in as your operator/service user, not the founder account.",
+            identity.email.as_deref().unwrap_or("?"),
+            identity.user_id
+        ),
+        (FounderStatus::NotFounder, _) => identity.founder_gate = Some("verified_non_founder"),
+        (FounderStatus::Unknown(why), OnUnknownFounder::Refuse) => bail!(
+            "Cannot verify founder status: {why}.\n\
+             This command refuses to run unverified. Reconnect and retry, or re-login:\n\
+             logline auth login --passkey"
+        ),
+        (FounderStatus::Unknown(why), OnUnknownFounder::Allow) => {
+            eprintln!("⚠ Founder status unknown ({why}); allowed by this gate and recorded in the receipt.");
+            identity.founder_gate = Some("unknown_allowed");
+        }
+    }
     Ok(())
 }
 
 /// Single uber-gate for all infra commands (deploy, cicd, db migrate).
-/// Chains: require_unlocked + require_passkey_identity + require_non_founder.
-pub fn require_infra_identity() -> anyhow::Result<(SessionToken, AuthIdentity)> {
-    let session = require_unlocked()?;
-    let identity = require_passkey_identity()?;
-    require_non_founder(&identity)?;
+/// Chains: require_unlocked(scope) + require_passkey_identity + require_non_founder.
+pub fn require_infra_identity(
+    scope: &str,
+    on_unknown: OnUnknownFounder,
+) -> anyhow::Result<(SessionToken, AuthIdentity)> {
+    let session = require_unlocked(scope)?;
+    let mut identity = require_passkey_identity()?;
+    require_non_founder(&mut identity, on_unknown)?;
     Ok((session, identity))
 }
@@ -6,7 +6,8 @@ use anyhow::bail;
 use clap::Subcommand;
 use serde::{Deserialize, Serialize};
 
-use crate::commands::secrets;
+use crate::commands::auth_session::OnUnknownFounder;
+use crate::commands::{secret_scan, secrets};
 
 fn now_iso() -> String {
     let secs = std::time::SystemTime::now()
@@ -68,6 +69,9 @@ struct PipelineFile {
     #[serde(default)]
     #[allow(dead_code)]
     artifacts: Vec<String>,
+    /// Run `secrets scan` over the working tree before any step.
+    #[serde(default)]
+    secret_scan: bool,
 }
 
 fn default_on_failure() -> String {
@@ -129,7 +133,10 @@ fn cmd_cicd_run(
     json: bool,
 ) -> anyhow::Result<()> {
     let identity = if !non_interactive {
-        let (_session, id) = crate::require_infra_identity()?;
+        let (_session, id) = crate::require_infra_identity(
+            "cicd:run",
+            OnUnknownFounder::Refuse,
+        )?;
         Some(id)
     } else {
         None
@@ -188,6 +195,9 @@ fn cmd_cicd_run(
             let cmd = step.run.as_deref().or(step.cmd.as_deref()).unwrap_or("?");
             eprintln!("  [{}/{}] {} — {cmd}", i + 1, total, step.step);
         }
+        if file.secret_scan {
+            eprintln!("  secret scan gate: on");
+        }
         return crate::pout(
             json,
             serde_json::json!({"dry_run": true, "pipeline": name, "steps": total}),
@@ -195,6 +205,10 @@ fn cmd_cicd_run(
         );
     }
 
+    if file.secret_scan {
+        run_secret_scan_gate(non_interactive)?;
+    }
+
     eprintln!("Pipeline: {name} ({total} steps)\n");
 
     let rid = cicd_receipt_id();
@@ -245,6 +259,8 @@ fn cmd_cicd_run(
                         "email": id.email,
                         "auth_method": id.auth_method,
                         "profile": id.profile,
+                        "founder": id.founder,
+                        "founder_gate": id.founder_gate,
                     }));
 
                     let receipt = serde_json::json!({
@@ -277,6 +293,8 @@ fn cmd_cicd_run(
         "email": id.email,
         "auth_method": id.auth_method,
         "profile": id.profile,
+        "founder": id.founder,
+        "founder_gate": id.founder_gate,
     }));
 
     let receipt = serde_json::json!({
@@ -433,6 +451,34 @@ fn run_logline_command(cmd: &str, non_interactive: bool) -> anyhow::Result<()> {
     }
 }
 
+/// Gate: block the pipeline if `secrets scan` finds anything. In CI the vault
+/// is unavailable, so the LOGLINE_* credentials are what gets looked for.
+fn run_secret_scan_gate(non_interactive: bool) -> anyhow::Result<()> {
+    let known = if non_interactive {
+        PIPELINE_SECRETS
+            .iter()
+            .filter_map(|&(key, env)| std::env::var(env).ok().map(|v| (key.to_string(), v)))
+            .collect()
+    } else {
+        secret_scan::vault_secrets()?
+    };
+    let root = std::env::current_dir()?;
+    let report = secret_scan::scan(&secret_scan::ScanOptions { root: &root, history: false, max_commits: None }, known)?;
+    if !report.ok {
+        let lines: Vec<String> = report.findings.iter().map(secret_scan::describe).collect();
+        bail!(
+            "CI/CD blocked: secrets scan found {} potential secret(s):\n  {}\n\n\
+             Remove them, or mark an accepted line with `{}`.\n\
+             Run:\n  logline secrets scan",
+            lines.len(),
+            lines.join("\n  "),
+            secret_scan::ALLOW_MARKER
+        );
+    }
+    eprintln!("✓ secrets scan: {} file(s) clean\n", report.scanned_files);
+    Ok(())
+}
+
 fn write_receipt(receipt: &serde_json::Value) {
     if let Ok(s) = serde_json::to_string_pretty(receipt) {
         let _ = std::fs::write("receipt.json", s);
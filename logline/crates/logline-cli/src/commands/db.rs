@@ -1,9 +1,16 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
 
-use anyhow::bail;
+use anyhow::{bail, ensure};
 use clap::Subcommand;
+use postgres::{Client, GenericClient};
+use sha2::{Digest, Sha256};
 
-use crate::commands::secrets;
+use crate::commands::auth_session::{AuthIdentity, OnUnknownFounder};
+use crate::commands::{audit, auth_session, db_plan, presence, secrets};
+use crate::integrations::pg;
 
 #[derive(Debug, Subcommand)]
 pub enum DbCommands {
@@ -14,9 +21,9 @@ pub enum DbCommands {
     },
     /// List all tables with row counts
     Tables,
-    /// Describe a table: columns, types, constraints
+    /// Describe a table: columns, indexes, constraints, foreign keys, RLS policies, triggers
     Describe {
-        /// Table name
+        /// Table name, optionally schema-qualified (schema.table)
         table: String,
     },
     /// Migration management
@@ -34,25 +41,65 @@ pub enum DbCommands {
 
 #[derive(Debug, Subcommand)]
 pub enum MigrateCommands {
-    /// Show migration status (applied vs pending)
+    /// Show migration status (applied, pending, modified, missing, out of order)
     Status,
-    /// Review pending migrations (generates diff, stores review receipt)
-    Review,
+    /// Review pending migrations in a pager or $EDITOR, then store a receipt bound to their contents
+    Review {
+        /// Environment the reviewed migrations are meant for
+        #[arg(long, default_value = "production")]
+        env: String,
+        /// Open the files in $VISUAL/$EDITOR instead of a pager
+        #[arg(long)]
+        editor: bool,
+        /// Review a rollback to this migration instead of pending migrations
+        #[arg(long, value_name = "MIGRATION", conflicts_with = "steps")]
+        to: Option<String>,
+        /// Review a rollback of the last N applied migrations instead of pending migrations
+        #[arg(long, value_name = "N")]
+        steps: Option<usize>,
+    },
     /// Apply pending migrations (requires recent review receipt + infra identity)
     Apply {
         /// Environment label
         #[arg(long, default_value = "production")]
         env: String,
+        /// Apply even though applied migrations were modified or removed (recorded in the audit log)
+        #[arg(long)]
+        accept_drift: bool,
+    },
+    /// Roll back applied migrations with their down scripts in supabase/rollbacks/ (requires recent review receipt + infra identity)
+    Rollback {
+        /// Roll back every migration applied after this one
+        #[arg(long, value_name = "MIGRATION", conflicts_with = "steps", required_unless_present = "steps")]
+        to: Option<String>,
+        /// Roll back the last N applied migrations
+        #[arg(long, value_name = "N")]
+        steps: Option<usize>,
+        /// Environment label
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    /// Dry-run pending migrations in a rolled-back transaction and show the schema diff
+    Plan {
+        /// Environment label
+        #[arg(long, default_value = "production")]
+        env: String,
+        /// Run against the database stored as `shadow_database_url` instead
+        #[arg(long)]
+        shadow: bool,
     },
     /// [Legacy] Apply all pending migrations without review gate
     Up {
         /// Environment label
         #[arg(long, default_value = "production")]
         env: String,
+        /// Apply even though applied migrations were modified or removed (recorded in the audit log)
+        #[arg(long)]
+        accept_drift: bool,
     },
 }
 
-fn get_db_url() -> anyhow::Result<String> {
+pub fn get_db_url() -> anyhow::Result<String> {
     if std::env::var("DATABASE_URL").ok().is_some_and(|v| !v.is_empty()) {
         eprintln!("WARNING: DATABASE_URL found in environment. This is a security risk.");
         eprintln!("  Store it in Keychain instead: logline secrets set database_url");
@@ -68,45 +115,67 @@ fn get_db_url() -> anyhow::Result<String> {
 }
 
 pub fn cmd_db(command: DbCommands, json: bool) -> anyhow::Result<()> {
-    crate::require_unlocked()?;
+    let scope = match &command {
+        DbCommands::Migrate {
+            command:
+                MigrateCommands::Apply { .. }
+                | MigrateCommands::Rollback { .. }
+                | MigrateCommands::Plan { .. }
+                | MigrateCommands::Up { .. },
+        } => "db:migrate",
+        _ => "db:read",
+    };
+    let session = crate::require_unlocked(scope)?;
 
     match command {
-        DbCommands::Query { sql } => cmd_db_query(&sql, json),
+        // Without db:write the query runs in a read-only transaction.
+        DbCommands::Query { sql } => cmd_db_query(&sql, json, !session.allows("db:write")),
         DbCommands::Tables => cmd_db_tables(json),
         DbCommands::Describe { table } => cmd_db_describe(&table, json),
         DbCommands::Migrate { command: sub } => match sub {
             MigrateCommands::Status => cmd_migrate_status(json),
-            MigrateCommands::Review => cmd_migrate_review(json),
-            MigrateCommands::Apply { env } => cmd_migrate_apply(&env, json),
-            MigrateCommands::Up { env } => cmd_migrate_up(&env, json),
+            MigrateCommands::Review { env, editor, to, steps } => {
+                cmd_migrate_review(&env, editor, to.as_deref(), steps, json)
+            }
+            MigrateCommands::Apply { env, accept_drift } => cmd_migrate_apply(&env, accept_drift, json),
+            MigrateCommands::Rollback { to, steps, env } => cmd_migrate_rollback(&env, to.as_deref(), steps, json),
+            MigrateCommands::Plan { env, shadow } => db_plan::cmd_migrate_plan(&env, shadow, json),
+            MigrateCommands::Up { env, accept_drift } => cmd_migrate_up(&env, accept_drift, None, json),
         },
         DbCommands::VerifyRls { env } => cmd_verify_rls(&env, json),
     }
 }
 
-fn cmd_db_query(sql: &str, json: bool) -> anyhow::Result<()> {
-    let url = get_db_url()?;
-    let output = std::process::Command::new("psql")
-        .arg(&url)
-        .arg("-c")
-        .arg(sql)
-        .arg("--no-psqlrc")
-        .args(if json { vec!["--tuples-only", "--csv"] } else { vec![] })
-        .env("PGCONNECT_TIMEOUT", "10")
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run psql: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Query failed: {stderr}");
-    }
+fn cmd_db_query(sql: &str, json: bool, read_only: bool) -> anyhow::Result<()> {
+    let mut client = pg::connect(&get_db_url()?)?;
+    let result = if read_only {
+        client.build_transaction().read_only(true).start().and_then(|mut tx| {
+            let result = pg::query(&mut tx, sql, &[])?;
+            tx.commit()?;
+            Ok(result)
+        })
+    } else {
+        pg::query(&mut client, sql, &[])
+    };
+    let result = result.map_err(|e| {
+        if read_only && pg::is_read_only_violation(&e) {
+            anyhow::anyhow!(
+                "Query failed: {}\nWrites need the db:write scope: logline auth unlock --scope db:write",
+                pg::describe_error(&e)
+            )
+        } else {
+            anyhow::anyhow!("Query failed: {}", pg::describe_error(&e))
+        }
+    })?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
     if json {
-        let rows: Vec<&str> = stdout.lines().filter(|l| !l.is_empty()).collect();
-        println!("{}", serde_json::to_string_pretty(&rows)?);
+        let value = match result.affected {
+            Some(n) => serde_json::json!({"ok": true, "rows_affected": n}),
+            None => result.to_json(),
+        };
+        println!("{}", serde_json::to_string_pretty(&value)?);
     } else {
-        print!("{stdout}");
+        println!("{}", result.to_table());
     }
     Ok(())
 }
@@ -121,39 +190,225 @@ fn cmd_db_tables(json: bool) -> anyhow::Result<()> {
         WHERE schemaname IN ('public', 'app')
         ORDER BY schemaname, tablename;
     ";
-    cmd_db_query(sql.trim(), json)
+    cmd_db_query(sql.trim(), json, true)
+}
+
+/// Split `schema.table` into its parts, folding unquoted names to lower case
+/// and unescaping `"quoted"` ones the way Postgres does.
+fn parse_qualified_name(input: &str) -> anyhow::Result<(Option<String>, String)> {
+    let mut parts = Vec::new();
+    let mut chars = input.trim().chars().peekable();
+    loop {
+        let mut part = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        part.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => part.push(c),
+                    None => bail!("Unterminated quoted identifier in '{input}'"),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == '.' {
+                    break;
+                }
+                part.extend(c.to_lowercase());
+                chars.next();
+            }
+        }
+        if part.is_empty() {
+            bail!("Invalid table name '{input}'. Use table or schema.table");
+        }
+        parts.push(part);
+        match chars.next() {
+            None => break,
+            Some('.') => {}
+            Some(_) => bail!("Invalid table name '{input}'. Use table or schema.table"),
+        }
+    }
+    match <[String; 2]>::try_from(parts) {
+        Ok([schema, table]) => Ok((Some(schema), table)),
+        Err(parts) => match <[String; 1]>::try_from(parts) {
+            Ok([table]) => Ok((None, table)),
+            Err(_) => bail!("Invalid table name '{input}'. Use table or schema.table"),
+        },
+    }
+}
+
+/// Resolve a table to its oid and `(schema, name)`. Unqualified names prefer
+/// the one visible on the search path, then a unique match in any schema.
+fn resolve_table(client: &mut impl GenericClient, input: &str) -> anyhow::Result<(u32, String, String)> {
+    let (schema, table) = parse_qualified_name(input)?;
+    let rows = client
+        .query(
+            r"
+            SELECT c.oid, n.nspname::text, c.relname::text, pg_table_is_visible(c.oid)
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relname = $1
+              AND ($2::text IS NULL OR n.nspname = $2)
+              AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+            ORDER BY 4 DESC, 2
+            ",
+            &[&table, &schema],
+        )
+        .map_err(|e| anyhow::anyhow!("Query failed: {}", pg::describe_error(&e)))?;
+    let found: Vec<(u32, String, String, bool)> = rows.iter().map(|r| (r.get(0), r.get(1), r.get(2), r.get(3))).collect();
+    match found.as_slice() {
+        [] => bail!("Table '{input}' not found"),
+        [(oid, schema, name, _)] | [(oid, schema, name, true), ..] => Ok((*oid, schema.clone(), name.clone())),
+        _ => {
+            let schemas: Vec<&str> = found.iter().map(|(_, s, _, _)| s.as_str()).collect();
+            bail!("'{input}' exists in several schemas ({}). Qualify it, e.g. {}.{table}", schemas.join(", "), schemas[0])
+        }
+    }
 }
 
+const DESCRIBE_SECTIONS: &[(&str, &str)] = &[
+    ("columns", r"
+        SELECT a.attname AS column, format_type(a.atttypid, a.atttypmod) AS type,
+               NOT a.attnotnull AS nullable, pg_get_expr(d.adbin, d.adrelid) AS default
+        FROM pg_attribute a
+        LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+        WHERE a.attrelid = $1 AND a.attnum > 0 AND NOT a.attisdropped
+        ORDER BY a.attnum"),
+    ("indexes", r"
+        SELECT i.relname AS name, ix.indisprimary AS primary, ix.indisunique AS unique,
+               pg_get_indexdef(ix.indexrelid) AS definition
+        FROM pg_index ix
+        JOIN pg_class i ON i.oid = ix.indexrelid
+        WHERE ix.indrelid = $1
+        ORDER BY i.relname"),
+    ("constraints", r"
+        SELECT conname AS name,
+               CASE contype WHEN 'p' THEN 'primary key' WHEN 'u' THEN 'unique' WHEN 'c' THEN 'check'
+                            WHEN 'x' THEN 'exclusion' ELSE contype::text END AS kind,
+               pg_get_constraintdef(oid) AS definition
+        FROM pg_constraint
+        WHERE conrelid = $1 AND contype <> 'f'
+        ORDER BY conname"),
+    ("foreign_keys", r"
+        SELECT conname AS name, confrelid::regclass::text AS references, pg_get_constraintdef(oid) AS definition
+        FROM pg_constraint
+        WHERE conrelid = $1 AND contype = 'f'
+        ORDER BY conname"),
+    ("referenced_by", r"
+        SELECT conname AS name, conrelid::regclass::text AS table, pg_get_constraintdef(oid) AS definition
+        FROM pg_constraint
+        WHERE confrelid = $1 AND contype = 'f'
+        ORDER BY conname"),
+    ("policies", r"
+        SELECT p.polname AS name,
+               CASE p.polcmd WHEN 'r' THEN 'SELECT' WHEN 'a' THEN 'INSERT' WHEN 'w' THEN 'UPDATE'
+                             WHEN 'd' THEN 'DELETE' ELSE 'ALL' END AS command,
+               p.polpermissive AS permissive,
+               CASE WHEN p.polroles = '{0}' THEN ARRAY['public']
+                    ELSE ARRAY(SELECT rolname::text FROM pg_roles WHERE oid = ANY(p.polroles) ORDER BY 1) END AS roles,
+               pg_get_expr(p.polqual, p.polrelid) AS using,
+               pg_get_expr(p.polwithcheck, p.polrelid) AS with_check
+        FROM pg_policy p
+        WHERE p.polrelid = $1
+        ORDER BY p.polname"),
+    ("triggers", r"
+        SELECT tgname AS name, tgenabled <> 'D' AS enabled, pg_get_triggerdef(oid) AS definition
+        FROM pg_trigger
+        WHERE tgrelid = $1 AND NOT tgisinternal
+        ORDER BY tgname"),
+];
+
 fn cmd_db_describe(table: &str, json: bool) -> anyhow::Result<()> {
-    let sql = format!(
-        r"
-        SELECT column_name, data_type, is_nullable, column_default
-        FROM information_schema.columns
-        WHERE table_name = '{table}'
-        ORDER BY ordinal_position;
-        "
-    );
-    cmd_db_query(sql.trim(), json)
+    let mut client = pg::connect(&get_db_url()?)?;
+    let mut tx = client
+        .build_transaction()
+        .read_only(true)
+        .start()
+        .map_err(|e| anyhow::anyhow!("Query failed: {}", pg::describe_error(&e)))?;
+    let (oid, schema, name) = resolve_table(&mut tx, table)?;
+    let rls = tx
+        .query_one("SELECT relrowsecurity, relforcerowsecurity FROM pg_class WHERE oid = $1", &[&oid])
+        .map_err(|e| anyhow::anyhow!("Query failed: {}", pg::describe_error(&e)))?;
+    let (rls_enabled, rls_forced): (bool, bool) = (rls.get(0), rls.get(1));
+
+    let mut sections = Vec::new();
+    for &(section, sql) in DESCRIBE_SECTIONS {
+        let result = pg::query(&mut tx, sql, &[&oid])
+            .map_err(|e| anyhow::anyhow!("Failed to describe {section}: {}", pg::describe_error(&e)))?;
+        sections.push((section, result));
+    }
+
+    if json {
+        let mut report = serde_json::json!({
+            "schema": schema,
+            "table": name,
+            "rls": {"enabled": rls_enabled, "forced": rls_forced},
+        });
+        for (section, result) in &sections {
+            report[*section] = result.to_json();
+        }
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Table {schema}.{name}");
+    let rls_state = match (rls_enabled, rls_forced) {
+        (false, _) => "disabled",
+        (true, false) => "enabled",
+        (true, true) => "enabled, forced",
+    };
+    println!("RLS: {rls_state}");
+    for (section, result) in &sections {
+        println!("\n{}:", section.replace('_', " "));
+        if result.rows.is_empty() {
+            println!("  (none)");
+        } else {
+            println!("{}", result.to_table());
+        }
+    }
+    Ok(())
 }
 
 fn cmd_migrate_status(json: bool) -> anyhow::Result<()> {
     let migrations_dir = find_migrations_dir()?;
     let files = list_migration_files(&migrations_dir)?;
 
-    let url = get_db_url()?;
-    let applied = get_applied_migrations(&url)?;
+    let mut client = pg::connect(&get_db_url()?)?;
+    let applied = get_applied_migrations(&mut client)?;
+
+    let drift = detect_drift(&files, &applied)?;
 
     let mut statuses = Vec::new();
     for file in &files {
-        let name = file
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let is_applied = applied.contains(&name);
+        let name = migration_name(file);
+        let status = if drift.modified.contains(&name) {
+            "modified"
+        } else if drift.out_of_order.contains(&name) {
+            "out_of_order"
+        } else if applied.get(&name).is_some_and(Option::is_none) {
+            "unverified"
+        } else if applied.contains_key(&name) {
+            "applied"
+        } else {
+            "pending"
+        };
         statuses.push(serde_json::json!({
             "migration": name,
-            "applied": is_applied,
+            "applied": applied.contains_key(&name),
+            "status": status,
+            "checksum": applied.get(&name).cloned().flatten(),
+        }));
+    }
+    for name in &drift.missing {
+        statuses.push(serde_json::json!({
+            "migration": name,
+            "applied": true,
+            "status": "missing",
+            "checksum": applied.get(name).cloned().flatten(),
         }));
     }
 
@@ -162,31 +417,147 @@ fn cmd_migrate_status(json: bool) -> anyhow::Result<()> {
     } else {
         for s in &statuses {
             let name = s["migration"].as_str().unwrap_or("?");
-            let applied = s["applied"].as_bool().unwrap_or(false);
-            let mark = if applied { "✓" } else { "PENDING" };
-            println!("  {mark:<8} {name}");
+            let mark = match s["status"].as_str().unwrap_or("?") {
+                "applied" => "✓",
+                "pending" => "PENDING",
+                "modified" => "✗ MODIFIED",
+                "missing" => "✗ MISSING",
+                "out_of_order" => "✗ OUT-OF-ORDER",
+                _ => "✓ (no checksum)",
+            };
+            println!("  {mark:<16} {name}");
+        }
+        if !drift.is_empty() {
+            println!();
+            println!("Drift detected. `migrate apply` will refuse until it is resolved or --accept-drift is given.");
         }
     }
     Ok(())
 }
 
-fn cmd_migrate_up(env: &str, json: bool) -> anyhow::Result<()> {
+/// Differences between the migrations directory and `_logline_migrations`.
+#[derive(Debug, Default)]
+struct Drift {
+    /// Applied, but the file's checksum no longer matches the recorded one.
+    modified: Vec<String>,
+    /// Applied, but the file is gone.
+    missing: Vec<String>,
+    /// Pending, but sorts before the latest applied migration.
+    out_of_order: Vec<String>,
+    /// Checksums of the modified files as they are now.
+    current: BTreeMap<String, String>,
+}
+
+impl Drift {
+    fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.out_of_order.is_empty()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "modified": self.modified,
+            "missing": self.missing,
+            "out_of_order": self.out_of_order,
+        })
+    }
+}
+
+fn detect_drift(files: &[PathBuf], applied: &AppliedMigrations) -> anyhow::Result<Drift> {
+    let mut drift = Drift::default();
+    let on_disk: Vec<String> = files.iter().map(|f| migration_name(f)).collect();
+    let latest_applied = applied.keys().filter(|name| on_disk.contains(name)).max();
+
+    for (file, name) in files.iter().zip(&on_disk) {
+        match applied.get(name) {
+            Some(Some(recorded)) => {
+                let current = migration_checksum(file)?;
+                if &current != recorded {
+                    drift.modified.push(name.clone());
+                    drift.current.insert(name.clone(), current);
+                }
+            }
+            Some(None) => {}
+            None => {
+                if latest_applied.is_some_and(|latest| name < latest) {
+                    drift.out_of_order.push(name.clone());
+                }
+            }
+        }
+    }
+    drift.missing = applied.keys().filter(|name| !on_disk.contains(name)).cloned().collect();
+    Ok(drift)
+}
+
+/// Apply pending migrations. With `reviewed`, the pending files and their
+/// contents must match the review receipt exactly.
+fn cmd_migrate_up(
+    env: &str,
+    accept_drift: bool,
+    reviewed: Option<&[ReviewedMigration]>,
+    json: bool,
+) -> anyhow::Result<()> {
     let migrations_dir = find_migrations_dir()?;
     let files = list_migration_files(&migrations_dir)?;
-    let url = get_db_url()?;
+    let mut client = pg::connect(&get_db_url()?)?;
+    let applied = get_applied_migrations(&mut client)?;
+
+    let drift = detect_drift(&files, &applied)?;
+    if !drift.is_empty() {
+        let mut report = String::new();
+        for (label, names) in [
+            ("modified since applied", &drift.modified),
+            ("applied but missing", &drift.missing),
+            ("pending but older than applied migrations", &drift.out_of_order),
+        ] {
+            for name in names {
+                let _ = write!(report, "\n  {name} — {label}");
+            }
+        }
+        if !accept_drift {
+            bail!(
+                "Migration drift detected:{report}\n\
+                 Restore the original files, or re-run with --accept-drift to proceed (recorded in the audit log)."
+            );
+        }
+        eprintln!("WARNING: proceeding despite migration drift (--accept-drift):{report}");
+    }
+
+    // Read each file once: what is checked against the review is what runs.
+    let mut pending: Vec<(String, String, String)> = Vec::new();
+    for file in files.iter().filter(|f| !applied.contains_key(&migration_name(f))) {
+        let sql = std::fs::read_to_string(file)?;
+        let checksum = sha256_hex(sql.as_bytes());
+        pending.push((migration_name(file), sql, checksum));
+    }
 
-    ensure_migrations_table(&url)?;
-    let applied = get_applied_migrations(&url)?;
+    if let Some(reviewed) = reviewed {
+        let current: Vec<ReviewedMigration> =
+            pending.iter().map(|(name, _, checksum)| (name.clone(), checksum.clone())).collect();
+        check_matches_review(reviewed, &current, &format!("logline db migrate review --env {env}"))?;
+    }
 
-    let pending: Vec<_> = files
-        .iter()
-        .filter(|f| {
-            let name = f.file_name().unwrap_or_default().to_string_lossy().to_string();
-            !applied.contains(&name)
-        })
-        .collect();
+    if !drift.is_empty() {
+        audit::record(
+            "db.migrate.accept_drift",
+            &serde_json::json!({"env": env, "drift": drift.to_json()}),
+        )?;
+    }
+    // Accepted edits become the new baseline, so the next run is clean; rows
+    // applied before checksums were tracked take the file's current checksum.
+    // Both are written with the first migration, or alone if none is pending.
+    let mut checksums = missing_checksums(&files, &applied)?;
+    checksums.extend(drift.current);
 
     if pending.is_empty() {
+        if !checksums.is_empty() {
+            client
+                .transaction()
+                .and_then(|mut tx| {
+                    record_checksums(&mut tx, &checksums)?;
+                    tx.commit()
+                })
+                .map_err(|e| anyhow::anyhow!("Failed to record checksums: {}", pg::describe_error(&e)))?;
+        }
         return crate::pout(
             json,
             serde_json::json!({"ok": true, "applied": 0, "env": env}),
@@ -197,28 +568,21 @@ fn cmd_migrate_up(env: &str, json: bool) -> anyhow::Result<()> {
     eprintln!("Applying {} pending migration(s) to {env}...", pending.len());
 
     let mut applied_count = 0u32;
-    for file in &pending {
-        let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
-        let sql = std::fs::read_to_string(file)?;
-
+    for (name, sql, checksum) in &pending {
         eprintln!("  Applying: {name}...");
-        let full_sql = format!(
-            "BEGIN;\n{sql}\nINSERT INTO _logline_migrations (name) VALUES ('{name}');\nCOMMIT;\n"
-        );
-
-        let output = std::process::Command::new("psql")
-            .arg(&url)
-            .arg("-c")
-            .arg(&full_sql)
-            .arg("--no-psqlrc")
-            .env("PGCONNECT_TIMEOUT", "30")
-            .output()
-            .map_err(|e| anyhow::anyhow!("psql failed: {e}"))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            bail!("Migration '{name}' failed: {stderr}");
-        }
+        // The migration and its bookkeeping row commit together or not at all.
+        client
+            .transaction()
+            .and_then(|mut tx| {
+                tx.batch_execute(sql)?;
+                tx.execute(
+                    "INSERT INTO _logline_migrations (name, checksum) VALUES ($1, $2)",
+                    &[name, checksum],
+                )?;
+                record_checksums(&mut tx, &std::mem::take(&mut checksums))?;
+                tx.commit()
+            })
+            .map_err(|e| anyhow::anyhow!("Migration '{name}' failed: {}", pg::describe_error(&e)))?;
 
         eprintln!("  ✓ {name}");
         applied_count += 1;
@@ -231,128 +595,314 @@ fn cmd_migrate_up(env: &str, json: bool) -> anyhow::Result<()> {
     )
 }
 
-const REVIEW_RECEIPT_KEY: &str = "logline_migrate_review_receipt";
+pub const REVIEW_RECEIPT_KEY: &str = "logline_migrate_review_receipt";
 const REVIEW_RECEIPT_TTL_SECS: u64 = 3600; // 1 hour
 
-fn cmd_migrate_review(json: bool) -> anyhow::Result<()> {
+/// A migration as the reviewer saw it: the file to open and the SQL that will run.
+struct ReviewItem {
+    name: String,
+    file: PathBuf,
+    sql: String,
+}
+
+/// (migration name, `sha256:` checksum of the reviewed SQL)
+type ReviewedMigration = (String, String);
+
+fn review_items(
+    client: &mut Client,
+    migrations_dir: &PathBuf,
+    to: Option<&str>,
+    steps: Option<usize>,
+) -> anyhow::Result<Vec<ReviewItem>> {
+    let mut items = Vec::new();
+    if to.is_some() || steps.is_some() {
+        for name in rollback_plan(client, to, steps)? {
+            let (file, sql) = load_down_script(migrations_dir, &name)?;
+            items.push(ReviewItem { name, file, sql });
+        }
+    } else {
+        let applied = get_applied_migrations(client)?;
+        for file in list_migration_files(migrations_dir)? {
+            let name = migration_name(&file);
+            if !applied.contains_key(&name) {
+                let sql = std::fs::read_to_string(&file)?;
+                items.push(ReviewItem { name, file, sql });
+            }
+        }
+    }
+    Ok(items)
+}
+
+fn cmd_migrate_review(
+    env: &str,
+    editor: bool,
+    to: Option<&str>,
+    steps: Option<usize>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let reviewer = auth_session::require_logged_in()?;
     let migrations_dir = find_migrations_dir()?;
     let files = list_migration_files(&migrations_dir)?;
-    let url = get_db_url()?;
+    let db_url = get_db_url()?;
+    let mut client = pg::connect(&db_url)?;
+    let applied = get_applied_migrations(&mut client)?;
 
-    ensure_migrations_table(&url)?;
-    let applied = get_applied_migrations(&url)?;
+    let drift = detect_drift(&files, &applied)?;
+    if !drift.is_empty() {
+        eprintln!("WARNING: migration drift detected. Run `logline db migrate status` for details.\n");
+    }
 
-    let pending: Vec<_> = files
-        .iter()
-        .filter(|f| {
-            let name = f.file_name().unwrap_or_default().to_string_lossy().to_string();
-            !applied.contains(&name)
-        })
-        .collect();
+    let rollback = to.is_some() || steps.is_some();
+    let mut items = review_items(&mut client, &migrations_dir, to, steps)?;
 
-    if pending.is_empty() {
-        return crate::pout(
-            json,
-            serde_json::json!({"ok": true, "pending": 0, "review": "nothing_to_review"}),
-            "No pending migrations to review.",
-        );
+    if items.is_empty() {
+        let (review, text) = if rollback {
+            ("nothing_to_roll_back", "No applied migrations to roll back.")
+        } else {
+            ("nothing_to_review", "No pending migrations to review.")
+        };
+        return crate::pout(json, serde_json::json!({"ok": true, "pending": 0, "review": review}), text);
     }
 
-    eprintln!("Reviewing {} pending migration(s):\n", pending.len());
-
-    let mut pending_names: Vec<String> = Vec::new();
-    for file in &pending {
-        let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
-        let sql = std::fs::read_to_string(file)?;
+    ensure!(
+        !presence::is_headless(),
+        "Migration review needs an interactive terminal: the receipt records that a person read these files."
+    );
 
-        eprintln!("━━━ {} ━━━", name);
-        eprintln!("{sql}");
-        eprintln!();
-        pending_names.push(name);
-    }
-
-    // Try to open DataGrip if available
-    let mut datagrip_opened = false;
-    for file in &pending {
-        let result = std::process::Command::new("open")
-            .arg("-a")
-            .arg("DataGrip")
-            .arg(file)
-            .output();
-        if result.is_ok_and(|o| o.status.success()) {
-            datagrip_opened = true;
-        }
+    if rollback {
+        eprintln!("Reviewing rollback of {} migration(s) for {env}, in the order they will run.", items.len());
+    } else {
+        eprintln!("Reviewing {} pending migration(s) for {env}.", items.len());
+    }
+    if editor {
+        open_in_editor(&items)?;
+        // Whatever is on disk after the editor closes is what was reviewed.
+        items = review_items(&mut client, &migrations_dir, to, steps)?;
+    } else {
+        show_in_pager(&items, rollback);
     }
 
-    if datagrip_opened {
-        eprintln!("Opened migration file(s) in DataGrip for review.");
+    for item in &items {
+        eprintln!("  {}", item.name);
+    }
+    if !rollback {
+        eprintln!("Schema diff and destructive-change check: logline db migrate plan --env {env}");
+    }
+    eprint!("Approve {} migration(s) for {env}? [y/N] ", items.len());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        bail!("Review not approved. No receipt stored.");
     }
 
+    let checksums: Vec<serde_json::Value> = items
+        .iter()
+        .map(|item| serde_json::json!({"name": item.name, "checksum": sha256_hex(item.sql.as_bytes())}))
+        .collect();
+    let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+
     // Store review receipt in Keychain
+    let action = if rollback { "rollback" } else { "apply" };
     let now = now_secs();
     let receipt = serde_json::json!({
+        "action": action,
+        "env": env,
+        "target": target_fingerprint(&db_url)?,
+        "reviewed_by": {"user_id": reviewer.user_id, "email": reviewer.email},
         "reviewed_at": now,
         "expires_at": now + REVIEW_RECEIPT_TTL_SECS,
-        "migrations": pending_names,
+        "migrations": checksums,
     });
     secrets::store_credential(REVIEW_RECEIPT_KEY, &serde_json::to_string(&receipt)?)?;
 
     eprintln!("Review receipt stored (valid for 1 hour).");
-    eprintln!("To apply: logline db migrate apply --env prod");
+    match (to, steps) {
+        (Some(target), _) => eprintln!("To roll back: logline db migrate rollback --to {target} --env {env}"),
+        (None, Some(count)) => eprintln!("To roll back: logline db migrate rollback --steps {count} --env {env}"),
+        (None, None) => eprintln!("To apply: logline db migrate apply --env {env}"),
+    }
 
     crate::pout(
         json,
         serde_json::json!({
             "ok": true,
-            "pending": pending_names.len(),
-            "migrations": pending_names,
+            "action": action,
+            "env": env,
+            "pending": names.len(),
+            "migrations": checksums,
             "review_expires_at": now + REVIEW_RECEIPT_TTL_SECS,
-            "datagrip": datagrip_opened,
         }),
-        &format!("{} migration(s) reviewed. Receipt valid until {}.",
-            pending_names.len(),
+        &format!("{} migration(s) reviewed: {}. Receipt valid until {}.",
+            names.len(),
+            names.join(", "),
             format_ttl_remaining(REVIEW_RECEIPT_TTL_SECS)),
     )
 }
 
-fn cmd_migrate_apply(env: &str, json: bool) -> anyhow::Result<()> {
-    // Gate 1: require infra identity (Touch ID + passkey + non-founder)
-    let (_session, identity) = crate::require_infra_identity()?;
-    eprintln!("Identity: {} ({})", identity.email.as_deref().unwrap_or("?"), identity.profile);
+/// Open the review files in `$VISUAL`/`$EDITOR` and wait for it to exit.
+fn open_in_editor(items: &[ReviewItem]) -> anyhow::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("--editor needs $VISUAL or $EDITOR to be set"))?;
+
+    let mut files: Vec<&PathBuf> = items.iter().map(|item| &item.file).collect();
+    files.dedup();
+    // Through the shell, so values like "code --wait" work.
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg("editor")
+        .args(files)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run editor '{editor}': {e}"))?;
+    ensure!(status.success(), "Editor '{editor}' exited with {status}. No receipt stored.");
+    Ok(())
+}
 
-    // Gate 2: require recent review receipt
+/// Page the SQL through `$PAGER` (default `less`), or print it when no pager runs.
+fn show_in_pager(items: &[ReviewItem], rollback: bool) {
+    let mut doc = String::new();
+    for item in items {
+        let suffix = if rollback { " (down)" } else { "" };
+        let _ = write!(doc, "━━━ {}{suffix} ━━━\n{}\n\n", item.name, item.sql);
+    }
+
+    if std::io::stdout().is_terminal() {
+        let pager = std::env::var("PAGER").ok().filter(|p| !p.trim().is_empty()).unwrap_or_else(|| "less".into());
+        let child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&pager)
+            .stdin(std::process::Stdio::piped())
+            .spawn();
+        if let Ok(mut child) = child {
+            if let Some(mut stdin) = child.stdin.take() {
+                // A pager quit early closes the pipe; that is not an error.
+                let _ = stdin.write_all(doc.as_bytes());
+            }
+            if child.wait().is_ok_and(|status| status.success()) {
+                return;
+            }
+        }
+    }
+    eprint!("\n{doc}");
+}
+
+/// The reviewed migrations from an unexpired, unused receipt made for
+/// `action` on `env`.
+fn require_review_receipt(action: &str, env: &str, review_cmd: &str) -> anyhow::Result<Vec<ReviewedMigration>> {
     let receipt_json = secrets::load_credential(REVIEW_RECEIPT_KEY)
         .ok_or_else(|| anyhow::anyhow!(
             "No review receipt found.\n\
-             You must review migrations before applying them.\n\
-             Run: logline db migrate review"
+             You must review migrations before running them.\n\
+             Run: {review_cmd}"
         ))?;
     let receipt: serde_json::Value = serde_json::from_str(&receipt_json)
-        .map_err(|_| anyhow::anyhow!("Corrupt review receipt. Run: logline db migrate review"))?;
+        .map_err(|_| anyhow::anyhow!("Corrupt review receipt. Run: {review_cmd}"))?;
+    if receipt["consumed"].as_bool().unwrap_or(false) {
+        bail!("Review receipt was already used. Review again: {review_cmd}");
+    }
 
     let expires_at = receipt["expires_at"].as_u64().unwrap_or(0);
     let now = now_secs();
     if now > expires_at {
         bail!(
             "Review receipt expired ({} ago).\n\
-             Re-review the migrations: logline db migrate review",
+             Re-review the migrations: {review_cmd}",
             format_ttl_remaining(now - expires_at)
         );
     }
 
-    let reviewed_migrations: Vec<String> = receipt["migrations"]
+    let reviewed_action = receipt["action"].as_str().unwrap_or("apply");
+    if reviewed_action != action {
+        bail!("Review receipt was made for '{reviewed_action}', not '{action}'.\nRun: {review_cmd}");
+    }
+    let reviewed_env = receipt["env"].as_str().unwrap_or("?");
+    if reviewed_env != env {
+        bail!("Review receipt was made for env '{reviewed_env}', not '{env}'.\nRun: {review_cmd}");
+    }
+    // The env label is free-form; the receipt is also bound to the database itself.
+    if receipt["target"].as_str() != Some(target_fingerprint(&get_db_url()?)?.as_str()) {
+        bail!("Review receipt was made for a different database (host, port or database name changed).\nRun: {review_cmd}");
+    }
+
+    let reviewed = receipt["migrations"]
         .as_array()
-        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-        .unwrap_or_default();
+        .and_then(|arr| {
+            arr.iter()
+                .map(|m| Some((m["name"].as_str()?.to_string(), m["checksum"].as_str()?.to_string())))
+                .collect::<Option<Vec<ReviewedMigration>>>()
+        })
+        .ok_or_else(|| anyhow::anyhow!("Review receipt has no content checksums. Run: {review_cmd}"))?;
+
+    let reviewed_by = receipt["reviewed_by"]["email"]
+        .as_str()
+        .or_else(|| receipt["reviewed_by"]["user_id"].as_str())
+        .unwrap_or("?");
+    let names: Vec<&str> = reviewed.iter().map(|(name, _)| name.as_str()).collect();
+    eprintln!("Review receipt valid. Reviewed by {reviewed_by}: {}", names.join(", "));
+    Ok(reviewed)
+}
+
+/// Refuse unless `current` is exactly the set of migrations, with the same
+/// contents, that the receipt covers.
+fn check_matches_review(
+    reviewed: &[ReviewedMigration],
+    current: &[ReviewedMigration],
+    review_cmd: &str,
+) -> anyhow::Result<()> {
+    let reviewed_map: BTreeMap<&str, &str> =
+        reviewed.iter().map(|(name, checksum)| (name.as_str(), checksum.as_str())).collect();
+    let current_map: BTreeMap<&str, &str> =
+        current.iter().map(|(name, checksum)| (name.as_str(), checksum.as_str())).collect();
+    if reviewed_map == current_map {
+        return Ok(());
+    }
+
+    let mut report = String::new();
+    for (name, checksum) in &current_map {
+        match reviewed_map.get(name) {
+            None => {
+                let _ = write!(report, "\n  {name} — not reviewed");
+            }
+            Some(seen) if seen != checksum => {
+                let _ = write!(report, "\n  {name} — changed since review");
+            }
+            Some(_) => {}
+        }
+    }
+    for name in reviewed_map.keys().filter(|name| !current_map.contains_key(*name)) {
+        let _ = write!(report, "\n  {name} — reviewed but no longer part of this run");
+    }
+    bail!("Migrations differ from what was reviewed:{report}\nRe-review: {review_cmd}")
+}
+
+/// Invalidate the review receipt once the reviewed operation has run.
+fn consume_review_receipt(identity: &AuthIdentity) -> anyhow::Result<()> {
+    secrets::store_credential(REVIEW_RECEIPT_KEY,
+        &serde_json::to_string(&serde_json::json!({
+            "consumed": true,
+            "consumed_at": now_secs(),
+            "applied_by": identity.user_id,
+            "founder": identity.founder,
+            "founder_gate": identity.founder_gate,
+        }))?)
+}
+
+fn cmd_migrate_apply(env: &str, accept_drift: bool, json: bool) -> anyhow::Result<()> {
+    // Gate 1: require infra identity (Touch ID + passkey + non-founder)
+    let (_session, identity) = crate::require_infra_identity("db:migrate", OnUnknownFounder::Refuse)?;
+    eprintln!("Identity: {} ({})", identity.email.as_deref().unwrap_or("?"), identity.profile);
 
-    eprintln!("Review receipt valid. Reviewed: {}", reviewed_migrations.join(", "));
+    // Gate 2: require a recent review receipt for exactly these files
+    let reviewed = require_review_receipt("apply", env, &format!("logline db migrate review --env {env}"))?;
 
     // Apply migrations (reuses existing logic)
-    cmd_migrate_up(env, json)?;
+    cmd_migrate_up(env, accept_drift, Some(&reviewed), json)?;
 
     // Invalidate the review receipt after successful apply
-    let _ = secrets::store_credential(REVIEW_RECEIPT_KEY,
-        &serde_json::to_string(&serde_json::json!({"consumed": true, "consumed_at": now}))?);
+    let _ = consume_review_receipt(&identity);
 
     // Auto-run RLS verification
     eprintln!("\nPost-migration RLS verification...");
@@ -361,6 +911,106 @@ fn cmd_migrate_apply(env: &str, json: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn cmd_migrate_rollback(env: &str, to: Option<&str>, steps: Option<usize>, json: bool) -> anyhow::Result<()> {
+    // Same gates as apply: infra identity, then a review receipt for exactly this rollback.
+    let (_session, identity) = crate::require_infra_identity("db:migrate", OnUnknownFounder::Refuse)?;
+    eprintln!("Identity: {} ({})", identity.email.as_deref().unwrap_or("?"), identity.profile);
+
+    let review_cmd = match (to, steps) {
+        (Some(target), _) => format!("logline db migrate review --to {target} --env {env}"),
+        (None, count) => format!("logline db migrate review --steps {} --env {env}", count.unwrap_or(1)),
+    };
+    let reviewed = require_review_receipt("rollback", env, &review_cmd)?;
+
+    let migrations_dir = find_migrations_dir()?;
+    let files = list_migration_files(&migrations_dir)?;
+    let mut client = pg::connect(&get_db_url()?)?;
+    let plan = rollback_plan(&mut client, to, steps)?;
+
+    if plan.is_empty() {
+        return crate::pout(
+            json,
+            serde_json::json!({"ok": true, "rolled_back": [], "env": env}),
+            "Nothing to roll back.",
+        );
+    }
+    let drift = detect_drift(&files, &get_applied_migrations(&mut client)?)?;
+    if let Some(name) = plan.iter().find(|name| drift.modified.contains(name)) {
+        bail!(
+            "{name} was modified after it was applied; its down script may not match the schema.\n\
+             Restore the original file, or accept the change with: logline db migrate apply --accept-drift"
+        );
+    }
+
+    // Every down script must exist, and match the review, before anything runs.
+    let scripts = plan
+        .iter()
+        .map(|name| load_down_script(&migrations_dir, name).map(|(_, sql)| (name, sql)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let current: Vec<ReviewedMigration> =
+        scripts.iter().map(|(name, sql)| ((*name).clone(), sha256_hex(sql.as_bytes()))).collect();
+    check_matches_review(&reviewed, &current, &review_cmd)?;
+
+    eprintln!("Rolling back {} migration(s) on {env}...", plan.len());
+    for (done, (name, sql)) in scripts.iter().enumerate() {
+        eprintln!("  Rolling back: {name}...");
+        // The down script and the ledger delete commit together or not at all.
+        client
+            .transaction()
+            .and_then(|mut tx| {
+                tx.batch_execute(sql)?;
+                tx.execute("DELETE FROM _logline_migrations WHERE name = $1", &[name])?;
+                tx.commit()
+            })
+            .map_err(|e| anyhow::anyhow!(
+                "Rollback of '{name}' failed: {}\n{done} migration(s) were rolled back before the failure.",
+                pg::describe_error(&e)
+            ))?;
+        eprintln!("  ✓ {name}");
+    }
+
+    let _ = consume_review_receipt(&identity);
+    audit::record("db.migrate.rollback", &serde_json::json!({"env": env, "migrations": plan}))?;
+
+    crate::pout(
+        json,
+        serde_json::json!({"ok": true, "rolled_back": plan, "env": env}),
+        &format!("{} migration(s) rolled back on {env}.", plan.len()),
+    )?;
+
+    eprintln!("\nPost-rollback RLS verification...");
+    cmd_verify_rls(env, json)
+}
+
+/// Applied migrations to undo, newest first: everything applied after `to`,
+/// or the last `steps`.
+fn rollback_plan(client: &mut Client, to: Option<&str>, steps: Option<usize>) -> anyhow::Result<Vec<String>> {
+    ensure_migrations_table(client)?;
+    let rows = client
+        .query("SELECT name FROM _logline_migrations ORDER BY id DESC", &[])
+        .map_err(|e| anyhow::anyhow!("Failed to query migrations: {}", pg::describe_error(&e)))?;
+    let applied: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+
+    match (to, steps) {
+        (Some(target), None) => {
+            let pos = applied
+                .iter()
+                .position(|name| name == target)
+                .ok_or_else(|| anyhow::anyhow!("'{target}' is not an applied migration. See: logline db migrate status"))?;
+            Ok(applied[..pos].to_vec())
+        }
+        (None, Some(count)) => {
+            ensure!(
+                count <= applied.len(),
+                "Cannot roll back {count} migration(s): only {} applied",
+                applied.len()
+            );
+            Ok(applied[..count].to_vec())
+        }
+        _ => bail!("Specify exactly one of --to <migration> or --steps <N>"),
+    }
+}
+
 fn format_ttl_remaining(secs: u64) -> String {
     if secs < 60 { return format!("{secs}s"); }
     let mins = secs / 60;
@@ -381,16 +1031,14 @@ fn now_secs() -> u64 {
 pub fn get_pending_migration_names() -> anyhow::Result<Vec<String>> {
     let migrations_dir = find_migrations_dir()?;
     let files = list_migration_files(&migrations_dir)?;
-    let url = get_db_url()?;
-
-    ensure_migrations_table(&url)?;
-    let applied = get_applied_migrations(&url)?;
+    let mut client = pg::connect(&get_db_url()?)?;
+    let applied = get_applied_migrations(&mut client)?;
 
     Ok(files
         .iter()
         .filter_map(|f| {
             let name = f.file_name()?.to_string_lossy().to_string();
-            if applied.contains(&name) { None } else { Some(name) }
+            if applied.contains_key(&name) { None } else { Some(name) }
         })
         .collect())
 }
@@ -407,15 +1055,17 @@ const SENSITIVE_TABLES: &[&str] = &[
 const APPEND_ONLY_TABLES: &[&str] = &["fuel_events"];
 
 fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
-    let url = get_db_url()?;
+    let mut client = pg::connect(&get_db_url()?)?;
     let mut issues = Vec::new();
     let mut warnings = Vec::new();
     let mut tables_checked = 0u32;
 
-    // Gate 1: RLS enabled + policy count on all tables
+    // Gate 1: RLS enabled + policy count on all tables. Names for suggested
+    // fixes are quoted by the server (format %I), so odd table names stay valid SQL.
     let rls_sql = r"
-        SELECT t.schemaname, t.tablename, c.relrowsecurity AS rls_enabled,
-               (SELECT count(*) FROM pg_policies p WHERE p.tablename = t.tablename AND p.schemaname = t.schemaname) AS policy_count
+        SELECT t.schemaname::text, t.tablename::text, c.relrowsecurity AS rls_enabled,
+               (SELECT count(*) FROM pg_policies p WHERE p.tablename = t.tablename AND p.schemaname = t.schemaname) AS policy_count,
+               format('%I.%I', t.schemaname, t.tablename) AS quoted
         FROM pg_tables t
         JOIN pg_class c ON c.relname = t.tablename
         JOIN pg_namespace n ON n.oid = c.relnamespace AND n.nspname = t.schemaname
@@ -423,18 +1073,16 @@ fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
         ORDER BY t.schemaname, t.tablename;
     ";
 
-    let output = run_psql_query(&url, rls_sql)?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    for line in stdout.lines() {
-        let line = line.trim();
-        if line.is_empty() { continue; }
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() < 4 { continue; }
-        let schema = parts[0].trim();
-        let table = parts[1].trim();
-        let rls_on = parts[2].trim() == "t";
-        let policy_count: u32 = parts[3].trim().parse().unwrap_or(0);
+    let rows = client
+        .query(rls_sql, &[])
+        .map_err(|e| anyhow::anyhow!("Query failed: {}", pg::describe_error(&e)))?;
+
+    for row in &rows {
+        let schema: &str = row.get(0);
+        let table: &str = row.get(1);
+        let rls_on: bool = row.get(2);
+        let policy_count: i64 = row.get(3);
+        let quoted: &str = row.get(4);
         tables_checked += 1;
 
         let full_name = format!("{schema}.{table}");
@@ -444,7 +1092,7 @@ fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
                 "table": full_name,
                 "severity": "critical",
                 "issue": "RLS not enabled",
-                "fix": format!("ALTER TABLE {full_name} ENABLE ROW LEVEL SECURITY;"),
+                "fix": format!("ALTER TABLE {quoted} ENABLE ROW LEVEL SECURITY;"),
             }));
         }
         if policy_count == 0 {
@@ -468,46 +1116,40 @@ fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
 
     // Gate 2: fuel_events must be append-only (no UPDATE/DELETE grants or policies)
     for &table in APPEND_ONLY_TABLES {
-        let grant_sql = format!(
-            r"SELECT privilege_type FROM information_schema.role_table_grants
-              WHERE table_name = '{table}' AND grantee IN ('authenticated', 'anon', 'public')
-              AND privilege_type IN ('UPDATE', 'DELETE');"
-        );
-
-        if let Ok(out) = run_psql_query(&url, &grant_sql) {
-            let text = String::from_utf8_lossy(&out.stdout);
-            for line in text.lines() {
-                let priv_type = line.trim();
-                if priv_type == "UPDATE" || priv_type == "DELETE" {
-                    issues.push(serde_json::json!({
-                        "table": table,
-                        "severity": "critical",
-                        "issue": format!("{priv_type} grant exists on append-only table"),
-                        "fix": format!("REVOKE {priv_type} ON {table} FROM authenticated, anon, public;"),
-                    }));
-                }
+        let grant_sql = r"
+            SELECT privilege_type::text, format('%I.%I', table_schema, table_name)
+            FROM information_schema.role_table_grants
+            WHERE table_name = $1 AND grantee IN ('authenticated', 'anon', 'public')
+              AND privilege_type IN ('UPDATE', 'DELETE');
+        ";
+
+        if let Ok(rows) = client.query(grant_sql, &[&table]) {
+            for row in &rows {
+                let priv_type: &str = row.get(0);
+                let quoted: &str = row.get(1);
+                issues.push(serde_json::json!({
+                    "table": table,
+                    "severity": "critical",
+                    "issue": format!("{priv_type} grant exists on append-only table"),
+                    "fix": format!("REVOKE {priv_type} ON {quoted} FROM authenticated, anon, public;"),
+                }));
             }
         }
 
-        let policy_sql = format!(
-            r"SELECT polname, polcmd FROM pg_policies WHERE tablename = '{table}' AND polcmd IN ('w', 'd');"
-        );
-        if let Ok(out) = run_psql_query(&url, &policy_sql) {
-            let text = String::from_utf8_lossy(&out.stdout);
-            for line in text.lines() {
-                let line = line.trim();
-                if line.is_empty() { continue; }
-                let cmd_char = line.rsplit(',').next().unwrap_or("").trim();
-                let cmd_name = match cmd_char {
-                    "w" => "UPDATE",
-                    "d" => "DELETE",
-                    _ => continue,
-                };
+        let policy_sql = r"
+            SELECT cmd, format('%I ON %I.%I', policyname, schemaname, tablename)
+            FROM pg_policies
+            WHERE tablename = $1 AND cmd IN ('UPDATE', 'DELETE');
+        ";
+        if let Ok(rows) = client.query(policy_sql, &[&table]) {
+            for row in &rows {
+                let cmd_name: &str = row.get(0);
+                let policy: &str = row.get(1);
                 issues.push(serde_json::json!({
                     "table": table,
                     "severity": "critical",
                     "issue": format!("{cmd_name} policy exists on append-only table"),
-                    "fix": format!("DROP the {cmd_name} policy on {table} — append-only tables must not allow modifications"),
+                    "fix": format!("DROP POLICY {policy}; — append-only tables must not allow modifications"),
                 }));
             }
         }
@@ -515,31 +1157,29 @@ fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
 
     // Gate 3: SECURITY DEFINER functions must have SET search_path
     let definer_sql = r"
-        SELECT n.nspname, p.proname, p.prosecdef,
-               (p.proconfig IS NOT NULL AND array_to_string(p.proconfig, ',') LIKE '%search_path%') AS has_search_path
+        SELECT n.nspname::text, p.proname::text, p.prosecdef,
+               (p.proconfig IS NOT NULL AND array_to_string(p.proconfig, ',') LIKE '%search_path%') AS has_search_path,
+               p.oid::regprocedure::text AS signature, quote_ident(n.nspname) AS quoted_schema
         FROM pg_proc p
         JOIN pg_namespace n ON n.oid = p.pronamespace
         WHERE n.nspname IN ('public', 'app')
           AND p.prosecdef = true;
     ";
 
-    if let Ok(out) = run_psql_query(&url, definer_sql) {
-        let text = String::from_utf8_lossy(&out.stdout);
-        for line in text.lines() {
-            let line = line.trim();
-            if line.is_empty() { continue; }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 4 { continue; }
-            let schema = parts[0].trim();
-            let func = parts[1].trim();
-            let has_path = parts[3].trim() == "t";
+    if let Ok(rows) = client.query(definer_sql, &[]) {
+        for row in &rows {
+            let schema: &str = row.get(0);
+            let func: &str = row.get(1);
+            let has_path: bool = row.get(3);
+            let signature: &str = row.get(4);
+            let quoted_schema: &str = row.get(5);
 
             if !has_path {
                 issues.push(serde_json::json!({
                     "table": format!("{schema}.{func}()"),
                     "severity": "critical",
                     "issue": "SECURITY DEFINER function without SET search_path",
-                    "fix": format!("ALTER FUNCTION {schema}.{func} SET search_path = {schema};"),
+                    "fix": format!("ALTER FUNCTION {signature} SET search_path = {quoted_schema};"),
                 }));
             }
         }
@@ -593,28 +1233,9 @@ fn cmd_verify_rls(env: &str, json: bool) -> anyhow::Result<()> {
     }
 }
 
-fn run_psql_query(url: &str, sql: &str) -> anyhow::Result<std::process::Output> {
-    let output = std::process::Command::new("psql")
-        .arg(url)
-        .arg("-c")
-        .arg(sql)
-        .arg("--no-psqlrc")
-        .arg("--tuples-only")
-        .arg("--csv")
-        .env("PGCONNECT_TIMEOUT", "10")
-        .output()
-        .map_err(|e| anyhow::anyhow!("psql failed: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Query failed: {stderr}");
-    }
-    Ok(output)
-}
-
 // ─── Helpers ────────────────────────────────────────────────────────────────
 
-fn find_migrations_dir() -> anyhow::Result<PathBuf> {
+pub fn find_migrations_dir() -> anyhow::Result<PathBuf> {
     let candidates = [
         std::env::current_dir()
             .unwrap_or_default()
@@ -634,7 +1255,8 @@ fn find_migrations_dir() -> anyhow::Result<PathBuf> {
     bail!("Cannot find supabase/migrations/ directory")
 }
 
-fn list_migration_files(dir: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
+/// Forward migrations in `dir`, sorted.
+pub fn list_migration_files(dir: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
         .filter_map(|e| e.ok())
         .map(|e| e.path())
@@ -644,52 +1266,86 @@ fn list_migration_files(dir: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn ensure_migrations_table(url: &str) -> anyhow::Result<()> {
+/// Down scripts live in `supabase/rollbacks/`, next to `supabase/migrations/`
+/// rather than inside it, so `supabase db push` never applies them.
+fn rollbacks_dir(migrations_dir: &Path) -> PathBuf {
+    migrations_dir.parent().unwrap_or(migrations_dir).join("rollbacks")
+}
+
+/// The down script for an applied migration: `supabase/rollbacks/<name>`.
+/// Returns the file it came from.
+fn load_down_script(migrations_dir: &Path, name: &str) -> anyhow::Result<(PathBuf, String)> {
+    let down_file = rollbacks_dir(migrations_dir).join(name);
+    if !down_file.is_file() {
+        bail!("No down migration for {name}. Add {}.", down_file.display());
+    }
+    let sql = std::fs::read_to_string(&down_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", down_file.display()))?;
+    ensure!(!sql.trim().is_empty(), "Down migration {} is empty", down_file.display());
+    Ok((down_file, sql))
+}
+
+pub fn migration_name(file: &Path) -> String {
+    file.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Hash of the host, port and database `url` points at.
+fn target_fingerprint(url: &str) -> anyhow::Result<String> {
+    Ok(sha256_hex(pg::target(url)?.as_bytes()))
+}
+
+fn migration_checksum(file: &Path) -> anyhow::Result<String> {
+    let content = std::fs::read(file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", file.display()))?;
+    Ok(sha256_hex(&content))
+}
+
+/// Checksums for applied rows recorded before checksums were tracked.
+fn missing_checksums(files: &[PathBuf], applied: &AppliedMigrations) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    for file in files {
+        let name = migration_name(file);
+        if applied.get(&name).is_some_and(Option::is_none) {
+            checksums.insert(name, migration_checksum(file)?);
+        }
+    }
+    Ok(checksums)
+}
+
+fn record_checksums(db: &mut impl GenericClient, checksums: &BTreeMap<String, String>) -> Result<(), postgres::Error> {
+    for (name, checksum) in checksums {
+        db.execute("UPDATE _logline_migrations SET checksum = $2 WHERE name = $1", &[name, checksum])?;
+        eprintln!("  Recorded checksum for {name}");
+    }
+    Ok(())
+}
+
+fn ensure_migrations_table(client: &mut Client) -> anyhow::Result<()> {
     let sql = r"
         CREATE TABLE IF NOT EXISTS _logline_migrations (
             id SERIAL PRIMARY KEY,
             name TEXT NOT NULL UNIQUE,
             applied_at TIMESTAMPTZ DEFAULT now()
         );
+        ALTER TABLE _logline_migrations ADD COLUMN IF NOT EXISTS checksum TEXT;
     ";
-    let output = std::process::Command::new("psql")
-        .arg(url)
-        .arg("-c")
-        .arg(sql)
-        .arg("--no-psqlrc")
-        .env("PGCONNECT_TIMEOUT", "10")
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to create migrations table: {stderr}");
-    }
-    Ok(())
+    client
+        .batch_execute(sql)
+        .map_err(|e| anyhow::anyhow!("Failed to create migrations table: {}", pg::describe_error(&e)))
 }
 
-fn get_applied_migrations(url: &str) -> anyhow::Result<Vec<String>> {
-    ensure_migrations_table(url)?;
+/// Applied migration name -> recorded checksum (`None` for rows from before checksums).
+pub type AppliedMigrations = BTreeMap<String, Option<String>>;
 
-    let sql = "SELECT name FROM _logline_migrations ORDER BY name;";
-    let output = std::process::Command::new("psql")
-        .arg(url)
-        .arg("-c")
-        .arg(sql)
-        .arg("--no-psqlrc")
-        .arg("--tuples-only")
-        .arg("--no-align")
-        .env("PGCONNECT_TIMEOUT", "10")
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to query migrations: {stderr}");
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout
-        .lines()
-        .map(|l| l.trim().to_string())
-        .filter(|l| !l.is_empty())
-        .collect())
+pub fn get_applied_migrations(client: &mut Client) -> anyhow::Result<AppliedMigrations> {
+    ensure_migrations_table(client)?;
+
+    let rows = client
+        .query("SELECT name, checksum FROM _logline_migrations ORDER BY name", &[])
+        .map_err(|e| anyhow::anyhow!("Failed to query migrations: {}", pg::describe_error(&e)))?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
 }
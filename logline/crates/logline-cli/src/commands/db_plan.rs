@@ -0,0 +1,527 @@
+//! `logline db migrate plan` — dry-run pending migrations and diff the schema.
+//!
+//! The pending migrations run inside one transaction that is always rolled
+//! back, either on the target database or, with `--shadow`, on the database
+//! stored as `shadow_database_url`. The catalog is captured before and after
+//! and compared section by section (tables, columns, indexes, constraints,
+//! policies, functions, grants).
+//!
+//! Destructive changes are flagged from two sources: top-level statements in
+//! the migration text (`DROP`, `TRUNCATE`, `DELETE`) and the catalog diff
+//! (dropped columns, type changes, `NOT NULL` on existing columns).
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use postgres::GenericClient;
+
+use crate::commands::{db, secrets};
+use crate::integrations::pg;
+
+pub const SHADOW_DB_KEY: &str = "shadow_database_url";
+
+/// Keeps the plan from queueing behind (and then blocking) live traffic.
+const PLAN_LOCK_TIMEOUT: &str = "5s";
+/// Bounds how long one migration statement may hold its locks on a live database.
+const PLAN_STATEMENT_TIMEOUT: &str = "60s";
+/// The server ends the plan if the CLI stalls mid-transaction with locks held.
+const PLAN_IDLE_TIMEOUT: &str = "30s";
+
+/// Schemas owned by Postgres itself.
+const USER_SCHEMAS: &str = "n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg\\_%'";
+
+/// (section, SQL returning `key text, value text`). `{schemas}` is replaced by [`USER_SCHEMAS`].
+const CATALOG_SECTIONS: &[(&str, &str)] = &[
+    ("tables", r"
+        SELECT format('%I.%I', n.nspname, c.relname),
+               CASE c.relkind WHEN 'r' THEN 'table' WHEN 'p' THEN 'partitioned table' WHEN 'v' THEN 'view'
+                              WHEN 'm' THEN 'materialized view' ELSE 'foreign table' END
+               || CASE WHEN c.relkind IN ('r', 'p') AND c.relrowsecurity THEN ', RLS enabled'
+                       WHEN c.relkind IN ('r', 'p') THEN ', RLS disabled' ELSE '' END
+        FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') AND {schemas}"),
+    ("indexes", r"
+        SELECT format('%I.%I', n.nspname, ic.relname), pg_get_indexdef(i.indexrelid)
+        FROM pg_index i
+        JOIN pg_class ic ON ic.oid = i.indexrelid
+        JOIN pg_namespace n ON n.oid = ic.relnamespace
+        WHERE {schemas}"),
+    ("constraints", r"
+        SELECT format('%I.%I: %I', n.nspname, c.relname, con.conname), pg_get_constraintdef(con.oid)
+        FROM pg_constraint con
+        JOIN pg_class c ON c.oid = con.conrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE {schemas}"),
+    ("policies", r"
+        SELECT format('%I.%I: %I', p.schemaname, p.tablename, p.policyname),
+               concat_ws(' ', p.permissive, 'FOR ' || p.cmd, 'TO ' || array_to_string(p.roles, ', '),
+                         'USING (' || p.qual || ')', 'WITH CHECK (' || p.with_check || ')')
+        FROM pg_policies p JOIN pg_namespace n ON n.nspname = p.schemaname
+        WHERE {schemas}"),
+    ("functions", r"
+        SELECT format('%I.%I(%s)', n.nspname, p.proname, pg_get_function_identity_arguments(p.oid)),
+               CASE WHEN p.prokind IN ('f', 'p') THEN pg_get_functiondef(p.oid) ELSE p.prokind::text END
+        FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+        WHERE {schemas}
+          AND NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.objid = p.oid AND d.deptype = 'e')"),
+    ("grants", r"
+        SELECT format('%s on %I.%I to %s', a.privilege_type, n.nspname, c.relname,
+                      CASE WHEN a.grantee = 0 THEN 'PUBLIC' ELSE pg_get_userbyid(a.grantee)::text END), ''
+        FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace, aclexplode(c.relacl) a
+        WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') AND a.grantee <> c.relowner AND {schemas}"),
+];
+
+/// Sections whose values are too long to print; only their keys are listed.
+const KEY_ONLY_SECTIONS: &[&str] = &["functions", "grants"];
+
+const COLUMNS_SQL: &str = r"
+    SELECT format('%I.%I.%I', n.nspname, c.relname, a.attname),
+           format('%I.%I', n.nspname, c.relname),
+           c.relkind IN ('r', 'p') AS is_table,
+           format_type(a.atttypid, a.atttypmod),
+           t.typname::text,
+           a.atttypmod,
+           a.attnotnull,
+           pg_get_expr(d.adbin, d.adrelid)
+    FROM pg_attribute a
+    JOIN pg_class c ON c.oid = a.attrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    JOIN pg_type t ON t.oid = a.atttypid
+    LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+    WHERE a.attnum > 0 AND NOT a.attisdropped AND c.relkind IN ('r', 'p', 'v', 'm', 'f') AND {schemas}";
+
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnInfo {
+    /// Owning relation, quoted by the server.
+    table: String,
+    is_table: bool,
+    type_name: String,
+    base_type: String,
+    typmod: i32,
+    not_null: bool,
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    sections: BTreeMap<&'static str, BTreeMap<String, String>>,
+    columns: BTreeMap<String, ColumnInfo>,
+}
+
+fn snapshot(client: &mut impl GenericClient) -> anyhow::Result<Snapshot> {
+    let mut snap = Snapshot::default();
+    for (section, sql) in CATALOG_SECTIONS {
+        let rows = client
+            .query(&sql.replace("{schemas}", USER_SCHEMAS), &[])
+            .map_err(|e| anyhow::anyhow!("Failed to read {section} from the catalog: {}", pg::describe_error(&e)))?;
+        snap.sections.insert(section, rows.iter().map(|row| (row.get(0), row.get(1))).collect());
+    }
+
+    let rows = client
+        .query(&COLUMNS_SQL.replace("{schemas}", USER_SCHEMAS), &[])
+        .map_err(|e| anyhow::anyhow!("Failed to read columns from the catalog: {}", pg::describe_error(&e)))?;
+    let mut display = BTreeMap::new();
+    for row in &rows {
+        let key: String = row.get(0);
+        let info = ColumnInfo {
+            table: row.get(1),
+            is_table: row.get(2),
+            type_name: row.get(3),
+            base_type: row.get(4),
+            typmod: row.get(5),
+            not_null: row.get(6),
+        };
+        let default: Option<String> = row.get(7);
+        let mut value = info.type_name.clone();
+        if info.not_null {
+            value.push_str(" NOT NULL");
+        }
+        if let Some(default) = default {
+            value.push_str(" DEFAULT ");
+            value.push_str(&default);
+        }
+        display.insert(key.clone(), value);
+        snap.columns.insert(key, info);
+    }
+    snap.sections.insert("columns", display);
+    Ok(snap)
+}
+
+#[derive(Debug, Default)]
+struct SectionDiff {
+    added: Vec<(String, String)>,
+    removed: Vec<(String, String)>,
+    changed: Vec<(String, String, String)>,
+}
+
+impl SectionDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "added": self.added.iter().map(|(k, v)| serde_json::json!({"name": k, "definition": v})).collect::<Vec<_>>(),
+            "removed": self.removed.iter().map(|(k, v)| serde_json::json!({"name": k, "definition": v})).collect::<Vec<_>>(),
+            "changed": self.changed.iter().map(|(k, before, after)| {
+                serde_json::json!({"name": k, "before": before, "after": after})
+            }).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn diff_section(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> SectionDiff {
+    let mut diff = SectionDiff::default();
+    for (key, value) in after {
+        match before.get(key) {
+            None => diff.added.push((key.clone(), value.clone())),
+            Some(old) if old != value => diff.changed.push((key.clone(), old.clone(), value.clone())),
+            Some(_) => {}
+        }
+    }
+    for (key, value) in before {
+        if !after.contains_key(key) {
+            diff.removed.push((key.clone(), value.clone()));
+        }
+    }
+    diff
+}
+
+/// A change that can lose data or fail against production data.
+#[derive(Debug)]
+struct Risk {
+    migration: Option<String>,
+    object: String,
+    detail: String,
+}
+
+/// Top-level statements of `sql` with comments removed and whitespace collapsed.
+/// Splits on `;` outside quotes, comments and dollar-quoted bodies.
+fn sql_statements(sql: &str) -> Vec<String> {
+    fn finish(current: &mut String, out: &mut Vec<String>) {
+        let stmt = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !stmt.is_empty() {
+            out.push(stmt);
+        }
+        current.clear();
+    }
+
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut pos = 0;
+    while pos < sql.len() {
+        let rest = &sql[pos..];
+        let ch = rest.chars().next().unwrap_or_default();
+        if rest.starts_with("--") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+            current.push(' ');
+        } else if rest.starts_with("/*") {
+            pos += rest.find("*/").map_or(rest.len(), |end| end + 2);
+            current.push(' ');
+        } else if ch == '\'' || ch == '"' {
+            let end = rest[1..].find(ch).map_or(rest.len(), |end| end + 2);
+            current.push_str(&rest[..end]);
+            pos += end;
+        } else if let Some(tag) = dollar_tag(rest) {
+            let body = &rest[tag.len()..];
+            let end = body.find(tag).map_or(rest.len(), |end| tag.len() + end + tag.len());
+            current.push_str(&rest[..end]);
+            pos += end;
+        } else if ch == ';' {
+            finish(&mut current, &mut out);
+            pos += 1;
+        } else {
+            current.push(ch);
+            pos += ch.len_utf8();
+        }
+    }
+    finish(&mut current, &mut out);
+    out
+}
+
+/// `$$` or `$tag$` at the start of `s`; `$1` parameters are not tags.
+fn dollar_tag(s: &str) -> Option<&str> {
+    let inner = s.strip_prefix('$')?;
+    let end = inner.find('$')?;
+    let tag = &inner[..end];
+    let valid = !tag.starts_with(|c: char| c.is_ascii_digit())
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '_');
+    valid.then(|| &s[..end + 2])
+}
+
+fn first_keyword(stmt: &str) -> String {
+    stmt.split_whitespace().next().unwrap_or_default().to_ascii_uppercase()
+}
+
+fn is_transaction_control(stmt: &str) -> bool {
+    matches!(first_keyword(stmt).as_str(), "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "ABORT" | "START")
+}
+
+fn statement_risks(name: &str, up_sql: &str) -> Vec<Risk> {
+    sql_statements(up_sql)
+        .into_iter()
+        .filter(|stmt| matches!(first_keyword(stmt).as_str(), "DROP" | "TRUNCATE" | "DELETE"))
+        .map(|stmt| {
+            let mut object: String = stmt.chars().take(100).collect();
+            if object.len() < stmt.len() {
+                object.push('…');
+            }
+            Risk { migration: Some(name.to_string()), object, detail: "removes data or objects".into() }
+        })
+        .collect()
+}
+
+/// Position in a widening chain within a type family.
+fn type_rank(base_type: &str) -> Option<(u8, u8)> {
+    match base_type {
+        "int2" => Some((0, 1)),
+        "int4" => Some((0, 2)),
+        "int8" => Some((0, 3)),
+        "numeric" => Some((0, 4)),
+        "float4" => Some((1, 1)),
+        "float8" => Some((1, 2)),
+        "bpchar" => Some((2, 1)),
+        "varchar" => Some((2, 2)),
+        "text" => Some((2, 3)),
+        _ => None,
+    }
+}
+
+/// Why a column type change can lose data, or `None` for a widening.
+fn type_change_risk(before: &ColumnInfo, after: &ColumnInfo) -> Option<&'static str> {
+    // -1 means no length/precision limit.
+    let limit_shrinks = after.typmod != -1 && (before.typmod == -1 || after.typmod < before.typmod);
+    if before.base_type == after.base_type {
+        return limit_shrinks.then_some("narrows the type");
+    }
+    match (type_rank(&before.base_type), type_rank(&after.base_type)) {
+        (Some((from_family, from_rank)), Some((to_family, to_rank))) if from_family == to_family => {
+            (to_rank < from_rank || limit_shrinks).then_some("narrows the type")
+        }
+        _ => Some("changes the type; existing values must convert"),
+    }
+}
+
+/// Whether `table` has rows, cached per table. `table` comes from
+/// `format('%I.%I')`, so it is already a quoted identifier.
+fn rows_note(
+    client: &mut impl GenericClient,
+    cache: &mut BTreeMap<String, bool>,
+    table: &str,
+) -> anyhow::Result<&'static str> {
+    let populated = if let Some(&known) = cache.get(table) {
+        known
+    } else {
+        let row = client
+            .query_one(&format!("SELECT EXISTS (SELECT 1 FROM {table})"), &[])
+            .map_err(|e| anyhow::anyhow!("Failed to inspect {table}: {}", pg::describe_error(&e)))?;
+        let known: bool = row.get(0);
+        cache.insert(table.to_string(), known);
+        known
+    };
+    Ok(if populated { "table has rows" } else { "table is empty here" })
+}
+
+fn catalog_risks(
+    client: &mut impl GenericClient,
+    before: &Snapshot,
+    after: &Snapshot,
+) -> anyhow::Result<Vec<Risk>> {
+    let mut cache = BTreeMap::new();
+    let mut risks = Vec::new();
+    for (key, old) in &before.columns {
+        if !old.is_table {
+            continue;
+        }
+        let Some(new) = after.columns.get(key) else {
+            // Columns of dropped tables are covered by the DROP statement itself.
+            if after.sections["tables"].contains_key(&old.table) {
+                risks.push(Risk { migration: None, object: key.clone(), detail: "drops the column".into() });
+            }
+            continue;
+        };
+        if old.type_name != new.type_name {
+            if let Some(why) = type_change_risk(old, new) {
+                let note = rows_note(client, &mut cache, &new.table)?;
+                risks.push(Risk {
+                    migration: None,
+                    object: key.clone(),
+                    detail: format!("{} -> {}: {why} ({note})", old.type_name, new.type_name),
+                });
+            }
+        }
+        if new.not_null && !old.not_null {
+            let note = rows_note(client, &mut cache, &new.table)?;
+            risks.push(Risk {
+                migration: None,
+                object: key.clone(),
+                detail: format!("SET NOT NULL on an existing column ({note})"),
+            });
+        }
+    }
+    Ok(risks)
+}
+
+/// Print a diff section in text mode.
+fn print_section(section: &str, diff: &SectionDiff) {
+    println!("{section}:");
+    let key_only = KEY_ONLY_SECTIONS.contains(&section);
+    for (key, value) in &diff.added {
+        if key_only || value.is_empty() {
+            println!("  + {key}");
+        } else {
+            println!("  + {key}  {value}");
+        }
+    }
+    for (key, value) in &diff.removed {
+        if key_only || value.is_empty() {
+            println!("  - {key}");
+        } else {
+            println!("  - {key}  {value}");
+        }
+    }
+    for (key, old, new) in &diff.changed {
+        if key_only {
+            println!("  ~ {key}");
+        } else {
+            println!("  ~ {key}\n      before: {old}\n      after:  {new}");
+        }
+    }
+    println!();
+}
+
+fn print_plan(target: &str, names: &[&str], diffs: &[(&str, SectionDiff)], risks: &[Risk], escapes_transaction: bool) {
+    println!("Plan for {} pending migration(s) on {target}:", names.len());
+    for name in names {
+        println!("  {name}");
+    }
+    println!();
+    if diffs.iter().all(|(_, diff)| diff.is_empty()) {
+        println!("No schema changes.\n");
+    }
+    for (section, diff) in diffs.iter().filter(|(_, diff)| !diff.is_empty()) {
+        print_section(section, diff);
+    }
+
+    if risks.is_empty() {
+        println!("✓ No destructive changes detected.");
+    } else {
+        println!("Destructive changes ({}):", risks.len());
+        for risk in risks {
+            if let Some(name) = &risk.migration {
+                println!("  ✗ [{name}] {} — {}", risk.object, risk.detail);
+            } else {
+                println!("  ✗ {} — {}", risk.object, risk.detail);
+            }
+        }
+    }
+    if escapes_transaction {
+        println!("\nTransaction control in the migrations may have committed changes to the shadow database.");
+    } else {
+        println!("\nNothing was committed.");
+    }
+}
+
+/// `migrate plan`: dry-run the pending migrations and report the schema diff.
+pub fn cmd_migrate_plan(env: &str, shadow: bool, json: bool) -> anyhow::Result<()> {
+    let url = if shadow {
+        secrets::require_credential(SHADOW_DB_KEY).map_err(|_| anyhow::anyhow!(
+            "No shadow database configured.\n\
+             Store one with: logline secrets set {SHADOW_DB_KEY}"
+        ))?
+    } else {
+        db::get_db_url()?
+    };
+    let target = if shadow { "shadow database" } else { env };
+
+    let migrations_dir = db::find_migrations_dir()?;
+    let files = db::list_migration_files(&migrations_dir)?;
+    let mut client = pg::connect(&url)?;
+    let applied = db::get_applied_migrations(&mut client)?;
+
+    let mut pending: Vec<(String, String)> = Vec::new();
+    for file in &files {
+        let name = db::migration_name(file);
+        if !applied.contains_key(&name) {
+            let sql = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+            pending.push((name, sql));
+        }
+    }
+
+    if pending.is_empty() {
+        return crate::pout(
+            json,
+            serde_json::json!({"ok": true, "env": env, "shadow": shadow, "migrations": [], "destructive": [], "escapes_transaction": false}),
+            &format!("No pending migrations to plan on {target}."),
+        );
+    }
+
+    let mut risks = Vec::new();
+    let mut escapes_transaction = false;
+    for (name, up_sql) in &pending {
+        if let Some(stmt) = sql_statements(up_sql).into_iter().find(|stmt| is_transaction_control(stmt)) {
+            if !shadow {
+                bail!(
+                    "{name} contains transaction control (`{stmt}`), which could commit the plan's changes to {env}.\n\
+                     Plan it against a shadow database instead: logline db migrate plan --shadow"
+                );
+            }
+            escapes_transaction = true;
+            eprintln!("WARNING: {name} contains transaction control (`{stmt}`); the plan's changes may be committed to the shadow database.");
+        }
+        risks.extend(statement_risks(name, up_sql));
+    }
+
+    eprintln!("Planning {} pending migration(s) on {target} (rolled back afterwards)...", pending.len());
+    let mut tx = client
+        .transaction()
+        .map_err(|e| anyhow::anyhow!("Failed to start plan transaction: {}", pg::describe_error(&e)))?;
+    if !shadow {
+        tx.batch_execute(&format!(
+            "SET LOCAL lock_timeout = '{PLAN_LOCK_TIMEOUT}';\
+             SET LOCAL statement_timeout = '{PLAN_STATEMENT_TIMEOUT}';\
+             SET LOCAL idle_in_transaction_session_timeout = '{PLAN_IDLE_TIMEOUT}';"
+        ))
+        .map_err(|e| anyhow::anyhow!("Failed to set plan timeouts: {}", pg::describe_error(&e)))?;
+    }
+    let before = snapshot(&mut tx)?;
+    for (name, up_sql) in &pending {
+        tx.batch_execute(up_sql)
+            .map_err(|e| anyhow::anyhow!("Migration '{name}' would fail: {}", pg::describe_error(&e)))?;
+    }
+    let after = snapshot(&mut tx)?;
+    risks.extend(catalog_risks(&mut tx, &before, &after)?);
+    tx.rollback()
+        .map_err(|e| anyhow::anyhow!("Failed to roll back plan transaction: {}", pg::describe_error(&e)))?;
+
+    let order = ["tables", "columns", "indexes", "constraints", "policies", "functions", "grants"];
+    let diffs: Vec<(&str, SectionDiff)> = order
+        .iter()
+        .map(|section| (*section, diff_section(&before.sections[section], &after.sections[section])))
+        .collect();
+    let names: Vec<&str> = pending.iter().map(|(name, _)| name.as_str()).collect();
+
+    if json {
+        let diff: serde_json::Map<String, serde_json::Value> =
+            diffs.iter().map(|(section, diff)| ((*section).to_string(), diff.to_json())).collect();
+        let destructive: Vec<serde_json::Value> = risks
+            .iter()
+            .map(|risk| serde_json::json!({"migration": risk.migration, "object": risk.object, "detail": risk.detail}))
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "ok": true,
+                "env": env,
+                "shadow": shadow,
+                "migrations": names,
+                "diff": diff,
+                "destructive": destructive,
+                "escapes_transaction": escapes_transaction,
+            }))?
+        );
+        return Ok(());
+    }
+
+    print_plan(target, &names, &diffs, &risks, escapes_transaction);
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use clap::Subcommand;
 
+use crate::commands::auth_session::OnUnknownFounder;
 use crate::integrations::{github, supabase_migrate, vercel};
 
 fn now_iso() -> String {
@@ -60,8 +61,25 @@ pub enum DeployCommands {
     },
 }
 
+/// Scope a deploy to `env` needs; production gets the short-lived scope.
+fn deploy_scope(env: &str) -> &'static str {
+    if matches!(env, "production" | "prod") { "deploy:prod" } else { "deploy:preview" }
+}
+
+/// Production deploys refuse an unverified founder status; previews allow it.
+fn on_unknown_founder(scope: &str) -> OnUnknownFounder {
+    if scope == "deploy:prod" { OnUnknownFounder::Refuse } else { OnUnknownFounder::Allow }
+}
+
 pub fn cmd_deploy(command: DeployCommands, json: bool) -> anyhow::Result<()> {
-    crate::require_infra_identity()?;
+    let scope = match &command {
+        DeployCommands::All { env } | DeployCommands::Supabase { env } | DeployCommands::Vercel { env } => {
+            deploy_scope(env)
+        }
+        // Tagging a release publishes it; a bare PR does not.
+        DeployCommands::Github { tag, .. } => if tag.is_some() { "deploy:prod" } else { "deploy:preview" },
+    };
+    crate::require_infra_identity(scope, on_unknown_founder(scope))?;
 
     match command {
         DeployCommands::All { env } => cmd_deploy_all(&env, json),
@@ -83,7 +101,8 @@ fn cmd_deploy_all(env: &str, json: bool) -> anyhow::Result<()> {
     let mut gates: Vec<serde_json::Value> = Vec::new();
 
     eprintln!("[1/7] Verifying identity ..........");
-    let (session, identity) = crate::require_infra_identity()?;
+    let scope = deploy_scope(env);
+    let (session, identity) = crate::require_infra_identity(scope, on_unknown_founder(scope))?;
     gates.push(serde_json::json!({
         "gate": "auth_identity",
         "passed": true,
@@ -91,6 +110,8 @@ fn cmd_deploy_all(env: &str, json: bool) -> anyhow::Result<()> {
         "user_id": identity.user_id,
         "auth_method": identity.auth_method,
         "profile": identity.profile,
+        "founder": identity.founder,
+        "founder_gate": identity.founder_gate,
     }));
     eprintln!("  ✓ {} ({}, {})", identity.email.as_deref().unwrap_or("?"), identity.auth_method, identity.profile);
 
@@ -139,6 +160,8 @@ fn cmd_deploy_all(env: &str, json: bool) -> anyhow::Result<()> {
             "email": identity.email,
             "auth_method": identity.auth_method,
             "profile": identity.profile,
+            "founder": identity.founder,
+            "founder_gate": identity.founder_gate,
         },
         "gates": gates,
         "push": push_result,
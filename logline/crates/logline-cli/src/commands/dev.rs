@@ -53,7 +53,7 @@ fn run_with_db_env(program: &str, args: &[&str], use_unpooled: bool) -> anyhow::
 }
 
 pub fn cmd_dev(command: DevCommands, _json: bool) -> anyhow::Result<()> {
-    crate::require_unlocked()?;
+    crate::require_unlocked("dev:run")?;
 
     match command {
         DevCommands::Build => {
@@ -1,6 +1,18 @@
+pub mod audit;
+pub mod auth_context;
+pub mod auth_device;
 pub mod auth_session;
 pub mod db;
+pub mod db_plan;
 pub mod deploy;
 pub mod dev;
+pub mod passkey;
+pub mod presence;
 pub mod cicd;
+pub mod secret_bundle;
+pub mod secret_exec;
+pub mod secret_index;
+pub mod secret_rotate;
+pub mod secret_scan;
 pub mod secrets;
+pub mod vault;
@@ -0,0 +1,242 @@
+//! CLI passkey lifecycle: register, list, rotate, revoke.
+//!
+//! The private key lives in the OS keychain of one machine; the public key is
+//! a row in `cli_passkey_credentials`. Only one row per (user, device) is
+//! `active`; rotating or revoking marks the old row `revoked` instead of
+//! deleting it. Local key material is only written after the server accepted
+//! the new public key, and is removed whenever this device's key is revoked.
+
+use anyhow::bail;
+use clap::Subcommand;
+
+use crate::supabase::{
+    delete_passkey, encode_query_value, get_valid_token, load_passkey, save_passkey,
+    SupabaseClient,
+};
+
+const TABLE: &str = "cli_passkey_credentials";
+
+#[derive(Debug, Subcommand)]
+pub enum PasskeyCommands {
+    /// Register a passkey for this machine (replaces its active key, if any)
+    Register {
+        /// Device name for this passkey (default: hostname)
+        #[arg(long)]
+        device_name: Option<String>,
+    },
+    /// List your passkeys across all devices
+    List,
+    /// Replace this machine's passkey with a new keypair (old key is revoked)
+    Rotate,
+    /// Revoke the active passkey of a device (e.g. a lost laptop)
+    Revoke {
+        /// Device name as shown by `logline auth passkey list`
+        #[arg(long)]
+        device: String,
+    },
+}
+
+pub fn cmd_passkey(client: &SupabaseClient, command: PasskeyCommands, json: bool) -> anyhow::Result<()> {
+    match command {
+        PasskeyCommands::Register { device_name } => cmd_register(client, device_name, json),
+        PasskeyCommands::List => cmd_list(client, json),
+        PasskeyCommands::Rotate => cmd_rotate(client, json),
+        PasskeyCommands::Revoke { device } => cmd_revoke(client, &device, json),
+    }
+}
+
+fn current_user_id(client: &SupabaseClient, token: &str) -> anyhow::Result<String> {
+    let user = client.get_user(token)?;
+    user["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine user_id"))
+}
+
+/// Device name of the key stored on this machine, if any.
+pub fn local_device_name() -> Option<String> {
+    load_passkey().and_then(|p| p["device_name"].as_str().map(str::to_string))
+}
+
+/// Mark every active key of `device` revoked. Returns the number of rows revoked.
+fn revoke_remote(client: &SupabaseClient, token: &str, user_id: &str, device: &str) -> anyhow::Result<usize> {
+    let query = format!(
+        "user_id=eq.{}&device_name=eq.{}&status=eq.active",
+        encode_query_value(user_id),
+        encode_query_value(device)
+    );
+    let body = serde_json::json!({
+        "status": "revoked",
+        "revoked_at": crate::chrono_now(),
+    });
+    let rows = client.postgrest_update(TABLE, &query, &body, token)?;
+    Ok(rows.as_array().map_or(0, Vec::len))
+}
+
+/// Generate a keypair for `device`, swap it in server-side, then store it locally.
+fn enroll(client: &SupabaseClient, device: &str) -> anyhow::Result<(String, usize)> {
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    let token = get_valid_token(client)?;
+    let user_id = current_user_id(client, &token)?;
+
+    let signing_key = SigningKey::generate(&mut OsRng);
+    let public_key_hex = hex::encode(signing_key.verifying_key().as_bytes());
+    let private_key_hex = hex::encode(signing_key.to_bytes());
+
+    // Only one active key per device: revoke the old one before inserting.
+    let revoked = revoke_remote(client, &token, &user_id, device)?;
+
+    let cred = serde_json::json!({
+        "user_id": user_id,
+        "device_name": device,
+        "public_key": public_key_hex,
+        "algorithm": logline_auth::PASSKEY_ALGORITHM,
+        "status": "active",
+    });
+    if let Err(e) = client.postgrest_insert(TABLE, &cred, &token) {
+        bail!(
+            "Registering the new public key failed: {e}\n\
+             The previous key for '{device}' was revoked. Run `logline auth passkey register` again."
+        );
+    }
+
+    let passkey_data = serde_json::json!({
+        "user_id": user_id,
+        "device_name": device,
+        "private_key": private_key_hex,
+        "public_key": public_key_hex,
+        "algorithm": logline_auth::PASSKEY_ALGORITHM,
+    });
+    if let Err(e) = save_passkey(&passkey_data) {
+        // Keep server and keychain consistent: an unusable key must not stay active.
+        let _ = revoke_remote(client, &token, &user_id, device);
+        return Err(e);
+    }
+
+    Ok((public_key_hex, revoked))
+}
+
+pub fn cmd_register(client: &SupabaseClient, device_name: Option<String>, json: bool) -> anyhow::Result<()> {
+    let device = device_name.unwrap_or_else(crate::get_hostname);
+    if let Some(existing) = local_device_name() {
+        if existing != device {
+            bail!(
+                "This machine already holds the passkey for '{existing}'.\n\
+                 Use `logline auth passkey rotate`, or revoke it first with `logline auth passkey revoke --device {existing}`."
+            );
+        }
+    }
+
+    let (public_key_hex, replaced) = enroll(client, &device)?;
+
+    crate::pout(json, serde_json::json!({
+        "ok": true,
+        "device_name": device,
+        "public_key": public_key_hex,
+        "replaced": replaced,
+    }), &format!("Passkey registered for device '{device}'\nPublic key: {public_key_hex}"))?;
+
+    Ok(())
+}
+
+fn cmd_rotate(client: &SupabaseClient, json: bool) -> anyhow::Result<()> {
+    let Some(device) = local_device_name() else {
+        bail!("No passkey on this machine. Run `logline auth passkey register` first.");
+    };
+
+    let (public_key_hex, revoked) = enroll(client, &device)?;
+
+    crate::pout(json, serde_json::json!({
+        "ok": true,
+        "device_name": device,
+        "public_key": public_key_hex,
+        "revoked": revoked,
+    }), &format!("✓ Passkey for '{device}' rotated ({revoked} old key(s) revoked)\nPublic key: {public_key_hex}"))?;
+
+    Ok(())
+}
+
+fn cmd_revoke(client: &SupabaseClient, device: &str, json: bool) -> anyhow::Result<()> {
+    let token = get_valid_token(client)?;
+    let user_id = current_user_id(client, &token)?;
+
+    let revoked = revoke_remote(client, &token, &user_id, device)?;
+
+    let local_removed = local_device_name().as_deref() == Some(device);
+    if local_removed {
+        delete_passkey()?;
+    }
+
+    if revoked == 0 && !local_removed {
+        bail!("No active passkey found for device '{device}'. See `logline auth passkey list`.");
+    }
+
+    crate::pout(json, serde_json::json!({
+        "ok": true,
+        "device_name": device,
+        "revoked": revoked,
+        "local_key_removed": local_removed,
+    }), &format!(
+        "✓ Revoked passkey for '{device}'{}",
+        if local_removed { " (local key removed)" } else { "" }
+    ))?;
+
+    Ok(())
+}
+
+fn cmd_list(client: &SupabaseClient, json: bool) -> anyhow::Result<()> {
+    let token = get_valid_token(client)?;
+    let user_id = current_user_id(client, &token)?;
+    let rows = client.postgrest_get(
+        TABLE,
+        &format!(
+            "select=device_name,public_key,algorithm,status,created_at,last_used_at,revoked_at\
+             &user_id=eq.{}&order=status.asc,created_at.desc",
+            encode_query_value(&user_id)
+        ),
+        &token,
+    )?;
+
+    let local_public_key = load_passkey().and_then(|p| p["public_key"].as_str().map(str::to_string));
+    let rows = rows.as_array().cloned().unwrap_or_default();
+
+    if json {
+        let annotated: Vec<serde_json::Value> = rows
+            .into_iter()
+            .map(|mut r| {
+                let this = local_public_key.is_some() && r["public_key"].as_str() == local_public_key.as_deref();
+                r["this_device"] = serde_json::json!(this);
+                r
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&annotated)?);
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!("No passkeys registered. Run `logline auth passkey register`.");
+        return Ok(());
+    }
+
+    println!("   {:<24} {:<8} {:<18} {:<22} LAST USED", "DEVICE", "STATUS", "KEY", "CREATED");
+    for r in &rows {
+        let public_key = r["public_key"].as_str().unwrap_or("");
+        let marker = if local_public_key.as_deref() == Some(public_key) { "*" } else { "" };
+        println!(
+            "{:<2} {:<24} {:<8} {:<18} {:<22} {}",
+            marker,
+            r["device_name"].as_str().unwrap_or("?"),
+            r["status"].as_str().unwrap_or("?"),
+            public_key.get(..16).unwrap_or(public_key),
+            r["created_at"].as_str().unwrap_or("-"),
+            r["last_used_at"].as_str().unwrap_or("-"),
+        );
+    }
+    if local_public_key.is_some() {
+        println!("\n* = this machine");
+    }
+
+    Ok(())
+}
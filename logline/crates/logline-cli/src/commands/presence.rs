@@ -0,0 +1,559 @@
+//! User-presence gate.
+//!
+//! Privileged actions (`auth unlock`, passkey use) must be confirmed by a
+//! human at the keyboard. The backend comes from `[auth.presence]` in
+//! runtime.toml; `auto` picks the first one available on this machine.
+//! "Press Enter" is only used when explicitly configured (`confirm`), and
+//! headless environments (CI, no TTY) are refused unless `headless = "allow"`.
+
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, ensure, Context};
+use clap::Subcommand;
+use logline_core::{default_config_dir, load_presence_config, HeadlessPolicy, PresenceBackend, PresenceConfig};
+
+use crate::commands::secrets;
+
+pub const TOTP_SECRET_KEY: &str = "logline_presence_totp";
+pub const TOTP_LAST_STEP_KEY: &str = "logline_presence_totp_last";
+const TOTP_STEP_SECS: u64 = 30;
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` (the global `--config-dir`) instead of the default for runtime.toml.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR.set(dir);
+}
+
+/// The config dir in effect (`--config-dir` or the default).
+pub fn config_dir() -> PathBuf {
+    CONFIG_DIR.get().cloned().unwrap_or_else(default_config_dir)
+}
+
+fn load_config() -> anyhow::Result<PresenceConfig> {
+    load_presence_config(&config_dir()).map_err(|e| anyhow::anyhow!("{e}"))
+}
+
+/// A way to prove a human is present.
+pub trait PresenceVerifier {
+    /// Backend name, recorded as the unlock method.
+    fn name(&self) -> &'static str;
+    /// Whether the backend can run on this machine right now.
+    fn available(&self) -> bool;
+    /// Prompt the user; `Ok` only on positive confirmation.
+    fn verify(&self, reason: &str) -> anyhow::Result<()>;
+}
+
+fn verifier_for(kind: PresenceBackend, cfg: &PresenceConfig) -> Box<dyn PresenceVerifier> {
+    match kind {
+        PresenceBackend::MacosBiometrics | PresenceBackend::Auto => Box::new(MacosBiometrics),
+        PresenceBackend::Polkit => Box::new(Polkit { action: cfg.polkit_action.clone() }),
+        PresenceBackend::Pam => Box::new(Pam { service: cfg.pam_service.clone() }),
+        PresenceBackend::Fido2 => Box::new(Fido2 {
+            device: cfg.fido2_device.clone().map(PathBuf::from),
+            timeout: Duration::from_secs(cfg.timeout_seconds.max(1)),
+        }),
+        PresenceBackend::Totp => Box::new(Totp),
+        PresenceBackend::Confirm => Box::new(Confirm),
+    }
+}
+
+/// Candidates tried by `backend = "auto"`, strongest first.
+const AUTO_ORDER: [PresenceBackend; 5] = [
+    PresenceBackend::MacosBiometrics,
+    PresenceBackend::Fido2,
+    PresenceBackend::Polkit,
+    PresenceBackend::Pam,
+    PresenceBackend::Totp,
+];
+
+/// True when no human can answer a prompt.
+pub fn is_headless() -> bool {
+    let ci = std::env::var("CI")
+        .is_ok_and(|v| !v.is_empty() && v != "0" && !v.eq_ignore_ascii_case("false"));
+    ci || !std::io::stdin().is_terminal()
+}
+
+/// Require user presence for `reason`. Returns the backend name that confirmed it.
+pub fn require_presence(reason: &str) -> anyhow::Result<&'static str> {
+    let cfg = load_config()?;
+
+    if is_headless() {
+        match cfg.headless {
+            HeadlessPolicy::Refuse => bail!(
+                "Refusing: user presence cannot be verified in a headless/CI environment.\n\
+                 Run interactively, or set `headless = \"allow\"` under [auth.presence] in runtime.toml for trusted runners."
+            ),
+            HeadlessPolicy::Allow => {
+                eprintln!("⚠ Headless environment: user-presence check skipped (headless = \"allow\").");
+                return Ok("headless");
+            }
+        }
+    }
+
+    let verifier = if cfg.backend == PresenceBackend::Auto {
+        AUTO_ORDER
+            .iter()
+            .map(|k| verifier_for(*k, &cfg))
+            .find(|v| v.available())
+            .ok_or_else(|| anyhow::anyhow!(
+                "No user-presence backend available on this machine.\n\
+                 Enroll one (e.g. `logline auth presence totp-enroll`) or set [auth.presence] backend in runtime.toml."
+            ))?
+    } else {
+        let v = verifier_for(cfg.backend, &cfg);
+        if !v.available() {
+            bail!("Configured presence backend '{}' is not available on this machine.", v.name());
+        }
+        v
+    };
+
+    verifier.verify(reason)?;
+    Ok(verifier.name())
+}
+
+// ─── Backends ───────────────────────────────────────────────────────────────
+
+fn which(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|p| p.is_file())
+}
+
+struct MacosBiometrics;
+
+impl PresenceVerifier for MacosBiometrics {
+    fn name(&self) -> &'static str {
+        "macos-biometrics"
+    }
+
+    fn available(&self) -> bool {
+        cfg!(target_os = "macos") && which("swift").is_some()
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        eprintln!("Touch ID required...");
+        let out = Command::new("swift")
+            .arg("-e")
+            .arg(
+                r#"
+import LocalAuthentication
+import Foundation
+let reason = ProcessInfo.processInfo.environment["LOGLINE_PRESENCE_REASON"] ?? "Logline CLI"
+let ctx = LAContext()
+var err: NSError?
+guard ctx.canEvaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, error: &err) else {
+    fputs("biometrics unavailable: \(err?.localizedDescription ?? "unknown")\n", stderr)
+    exit(1)
+}
+let sema = DispatchSemaphore(value: 0)
+var ok = false
+ctx.evaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, localizedReason: reason) { success, _ in
+    ok = success
+    sema.signal()
+}
+sema.wait()
+exit(ok ? 0 : 1)
+"#,
+            )
+            .env("LOGLINE_PRESENCE_REASON", reason)
+            .output()
+            .context("Failed to run Touch ID prompt")?;
+        if !out.status.success() {
+            bail!(
+                "Touch ID authentication failed or was cancelled. {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+struct Polkit {
+    action: String,
+}
+
+impl PresenceVerifier for Polkit {
+    fn name(&self) -> &'static str {
+        "polkit"
+    }
+
+    fn available(&self) -> bool {
+        cfg!(target_os = "linux") && which("pkcheck").is_some()
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        eprintln!("Authenticate to continue: {reason}");
+        let status = Command::new("pkcheck")
+            .args(["--action-id", &self.action, "--process"])
+            .arg(std::process::id().to_string())
+            .arg("--allow-user-interaction")
+            .status()
+            .context("Failed to run pkcheck")?;
+        if !status.success() {
+            bail!("polkit authentication failed or was cancelled.");
+        }
+        Ok(())
+    }
+}
+
+struct Pam {
+    service: String,
+}
+
+impl PresenceVerifier for Pam {
+    fn name(&self) -> &'static str {
+        "pam"
+    }
+
+    fn available(&self) -> bool {
+        which("pamtester").is_some()
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        let user = std::env::var("USER")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Cannot determine current user ($USER) for PAM"))?;
+        eprintln!("Authenticate to continue: {reason}");
+        let status = Command::new("pamtester")
+            .args([&self.service, &user, "authenticate"])
+            .status()
+            .context("Failed to run pamtester")?;
+        if !status.success() {
+            bail!("PAM authentication failed.");
+        }
+        Ok(())
+    }
+}
+
+struct Confirm;
+
+impl PresenceVerifier for Confirm {
+    fn name(&self) -> &'static str {
+        "confirm"
+    }
+
+    fn available(&self) -> bool {
+        true
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        eprint!("{reason} — press Enter to confirm: ");
+        let mut buf = String::new();
+        std::io::stdin().read_line(&mut buf)?;
+        Ok(())
+    }
+}
+
+// ─── TOTP ───────────────────────────────────────────────────────────────────
+
+struct Totp;
+
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    use hmac::{Hmac, Mac};
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let h = mac.finalize().into_bytes();
+    let offset = usize::from(h[19] & 0x0f);
+    let bin = u32::from_be_bytes([h[offset] & 0x7f, h[offset + 1], h[offset + 2], h[offset + 3]]);
+    bin % 1_000_000
+}
+
+fn current_step() -> u64 {
+    crate::now_secs() / TOTP_STEP_SECS
+}
+
+/// Step of the code matching `code` within ±1 step of `now_step`.
+fn totp_match(secret: &[u8], code: &str, now_step: u64) -> Option<u64> {
+    let code: u32 = code.trim().parse().ok()?;
+    (now_step.saturating_sub(1)..=now_step + 1).find(|step| hotp(secret, *step) == code)
+}
+
+impl PresenceVerifier for Totp {
+    fn name(&self) -> &'static str {
+        "totp"
+    }
+
+    fn available(&self) -> bool {
+        secrets::load_credential(TOTP_SECRET_KEY).is_some()
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        let encoded = secrets::load_credential(TOTP_SECRET_KEY)
+            .ok_or_else(|| anyhow::anyhow!("No TOTP secret enrolled. Run `logline auth presence totp-enroll`."))?;
+        let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &encoded)
+            .ok_or_else(|| anyhow::anyhow!("Stored TOTP secret is corrupt. Re-run `logline auth presence totp-enroll`."))?;
+
+        eprintln!("{reason}");
+        let code = rpassword::prompt_password("Authenticator code: ")?;
+        let Some(step) = totp_match(&secret, &code, current_step()) else {
+            bail!("Invalid authenticator code.");
+        };
+
+        // Each code is single-use.
+        let last: u64 = secrets::load_credential(TOTP_LAST_STEP_KEY)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        if step <= last {
+            bail!("Authenticator code already used. Wait for the next one.");
+        }
+        secrets::store_credential(TOTP_LAST_STEP_KEY, &step.to_string())?;
+        Ok(())
+    }
+}
+
+// ─── FIDO2 / U2F over hidraw ────────────────────────────────────────────────
+
+const CTAPHID_MSG: u8 = 0x83;
+const CTAPHID_INIT: u8 = 0x86;
+const CTAPHID_KEEPALIVE: u8 = 0xBB;
+const CTAPHID_ERROR: u8 = 0xBF;
+const HID_PACKET: usize = 64;
+const BROADCAST_CID: [u8; 4] = [0xFF; 4];
+/// How long a key may go silent mid-exchange before it is treated as hung.
+/// Keepalive packets restart the clock.
+const HID_RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+/// HID usage page 0xF1D0 (FIDO alliance), as it appears in a report descriptor.
+const FIDO_USAGE_PAGE: [u8; 3] = [0x06, 0xD0, 0xF1];
+
+struct Fido2 {
+    device: Option<PathBuf>,
+    timeout: Duration,
+}
+
+impl Fido2 {
+    fn find_device(&self) -> Option<PathBuf> {
+        if let Some(dev) = &self.device {
+            return dev.exists().then(|| dev.clone());
+        }
+        let entries = fs::read_dir("/sys/class/hidraw").ok()?;
+        entries.flatten().find_map(|entry| {
+            let descriptor = fs::read(entry.path().join("device/report_descriptor")).ok()?;
+            descriptor
+                .windows(FIDO_USAGE_PAGE.len())
+                .any(|w| w == FIDO_USAGE_PAGE)
+                .then(|| Path::new("/dev").join(entry.file_name()))
+        })
+    }
+}
+
+fn hid_send(dev: &mut File, cid: [u8; 4], cmd: u8, data: &[u8]) -> anyhow::Result<()> {
+    let len = u16::try_from(data.len()).context("CTAPHID payload too large")?;
+    // Leading 0x00 is the HID report id.
+    let mut packet = [0u8; HID_PACKET + 1];
+    packet[1..5].copy_from_slice(&cid);
+    packet[5] = cmd;
+    packet[6..8].copy_from_slice(&len.to_be_bytes());
+    let first = data.len().min(HID_PACKET - 7);
+    packet[8..8 + first].copy_from_slice(&data[..first]);
+    dev.write_all(&packet)?;
+
+    for (seq, chunk) in data[first..].chunks(HID_PACKET - 5).enumerate() {
+        let mut packet = [0u8; HID_PACKET + 1];
+        packet[1..5].copy_from_slice(&cid);
+        packet[5] = u8::try_from(seq).context("CTAPHID payload too large")?;
+        packet[6..6 + chunk.len()].copy_from_slice(chunk);
+        dev.write_all(&packet)?;
+    }
+    Ok(())
+}
+
+/// Read one HID report, polling the non-blocking device until `deadline`.
+fn hid_read(dev: &mut File, buf: &mut [u8; HID_PACKET], deadline: Instant) -> anyhow::Result<()> {
+    loop {
+        match dev.read(buf) {
+            Ok(0) => bail!("Security key was disconnected."),
+            Ok(n) => {
+                buf[n..].fill(0);
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                ensure!(
+                    Instant::now() < deadline,
+                    "Security key stopped responding (nothing received for {}s). Reinsert it and try again.",
+                    HID_RESPONSE_TIMEOUT.as_secs()
+                );
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(e) => return Err(e).context("Failed to read from security key"),
+        }
+    }
+}
+
+fn hid_recv(dev: &mut File, cid: [u8; 4]) -> anyhow::Result<(u8, Vec<u8>)> {
+    let mut buf = [0u8; HID_PACKET];
+    let mut deadline = Instant::now() + HID_RESPONSE_TIMEOUT;
+    let (cmd, len, mut data) = loop {
+        hid_read(dev, &mut buf, deadline)?;
+        if buf[..4] != cid {
+            continue;
+        }
+        if buf[4] == CTAPHID_KEEPALIVE {
+            deadline = Instant::now() + HID_RESPONSE_TIMEOUT;
+            continue;
+        }
+        let len = usize::from(u16::from_be_bytes([buf[5], buf[6]]));
+        let first = len.min(HID_PACKET - 7);
+        break (buf[4], len, buf[7..7 + first].to_vec());
+    };
+    while data.len() < len {
+        hid_read(dev, &mut buf, deadline)?;
+        if buf[..4] != cid {
+            continue;
+        }
+        let take = (len - data.len()).min(HID_PACKET - 5);
+        data.extend_from_slice(&buf[5..5 + take]);
+    }
+    if cmd == CTAPHID_ERROR {
+        bail!("Security key returned CTAPHID error {:#04x}", data.first().copied().unwrap_or(0));
+    }
+    Ok((cmd, data))
+}
+
+impl PresenceVerifier for Fido2 {
+    fn name(&self) -> &'static str {
+        "fido2"
+    }
+
+    fn available(&self) -> bool {
+        cfg!(target_os = "linux") && self.find_device().is_some()
+    }
+
+    fn verify(&self, reason: &str) -> anyhow::Result<()> {
+        use rand::RngCore;
+
+        let path = self.find_device().ok_or_else(|| anyhow::anyhow!("No FIDO security key found"))?;
+        let mut opts = OpenOptions::new();
+        opts.read(true).write(true);
+        // Reads are polled against a deadline, so a hung key can't block forever.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.custom_flags(libc::O_NONBLOCK);
+        }
+        let mut dev = opts
+            .open(&path)
+            .with_context(|| format!("Cannot open {} (check udev permissions)", path.display()))?;
+
+        // Allocate a channel.
+        let mut nonce = [0u8; 8];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        hid_send(&mut dev, BROADCAST_CID, CTAPHID_INIT, &nonce)?;
+        let cid = loop {
+            let (cmd, data) = hid_recv(&mut dev, BROADCAST_CID)?;
+            if cmd == CTAPHID_INIT && data.len() >= 12 && data[..8] == nonce {
+                break [data[8], data[9], data[10], data[11]];
+            }
+        };
+
+        // U2F REGISTER with a throwaway challenge: the key only answers 0x9000
+        // after a physical touch (0x6985 = "conditions not satisfied" until then).
+        let mut apdu = vec![0x00, 0x01, 0x03, 0x00, 0x00, 0x00, 0x40];
+        let mut params = [0u8; 64];
+        rand::rngs::OsRng.fill_bytes(&mut params);
+        apdu.extend_from_slice(&params);
+        apdu.extend_from_slice(&[0x00, 0x00]);
+
+        eprintln!("{reason}\nTouch your security key...");
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            hid_send(&mut dev, cid, CTAPHID_MSG, &apdu)?;
+            let (_, resp) = hid_recv(&mut dev, cid)?;
+            let sw = match resp.len() {
+                n if n >= 2 => u16::from_be_bytes([resp[n - 2], resp[n - 1]]),
+                _ => 0,
+            };
+            match sw {
+                0x9000 => return Ok(()),
+                0x6985 if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(250)),
+                0x6985 => bail!("Timed out waiting for security key touch."),
+                other => bail!("Security key rejected the request (status {other:#06x})."),
+            }
+        }
+    }
+}
+
+// ─── Commands ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Subcommand)]
+pub enum PresenceCommands {
+    /// Show the configured backend and which backends are available
+    Status,
+    /// Run the configured presence check once
+    Test,
+    /// Enroll an authenticator app (TOTP) as a presence backend
+    TotpEnroll,
+}
+
+pub fn cmd_presence(command: &PresenceCommands, json: bool) -> anyhow::Result<()> {
+    match command {
+        PresenceCommands::Status => {
+            let cfg = load_config()?;
+            let backends: Vec<serde_json::Value> = AUTO_ORDER
+                .iter()
+                .chain([PresenceBackend::Confirm].iter())
+                .map(|k| {
+                    let v = verifier_for(*k, &cfg);
+                    serde_json::json!({"backend": v.name(), "available": v.available()})
+                })
+                .collect();
+            let mut text = format!(
+                "Configured: {:?}\nHeadless policy: {:?}\nHeadless now: {}\n",
+                cfg.backend, cfg.headless, is_headless()
+            );
+            for b in &backends {
+                let mark = if b["available"].as_bool() == Some(true) { "✓" } else { "✗" };
+                let _ = writeln!(text, "  {mark} {}", b["backend"].as_str().unwrap_or("?"));
+            }
+            crate::pout(json, serde_json::json!({
+                "backend": cfg.backend,
+                "headless_policy": cfg.headless,
+                "headless": is_headless(),
+                "backends": backends,
+            }), text.trim_end())
+        }
+        PresenceCommands::Test => {
+            let backend = require_presence("Logline CLI — presence test")?;
+            crate::pout(json, serde_json::json!({"ok": true, "backend": backend}), &format!("✓ Presence confirmed via {backend}"))
+        }
+        PresenceCommands::TotpEnroll => cmd_totp_enroll(json),
+    }
+}
+
+fn cmd_totp_enroll(json: bool) -> anyhow::Result<()> {
+    use rand::RngCore;
+
+    if is_headless() {
+        bail!("TOTP enrollment needs an interactive terminal.");
+    }
+    if secrets::load_credential(TOTP_SECRET_KEY).is_some() {
+        // Replacing a factor must itself be gated by the current one.
+        Totp.verify("Confirm with your current authenticator code to replace it.")?;
+    }
+
+    let mut secret = [0u8; 20];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &secret);
+    let account = std::env::var("USER").unwrap_or_else(|_| "user".into());
+    let host = crate::get_hostname();
+    let uri = format!("otpauth://totp/Logline:{account}@{host}?secret={encoded}&issuer=Logline&digits=6&period={TOTP_STEP_SECS}");
+
+    eprintln!("Add this to your authenticator app:\n\n  {uri}\n\nSecret: {encoded}\n");
+    let code = rpassword::prompt_password("Enter the current code to confirm: ")?;
+    let Some(step) = totp_match(&secret, &code, current_step()) else {
+        bail!("Code did not match. Nothing was stored.");
+    };
+
+    secrets::store_credential(TOTP_SECRET_KEY, &encoded)?;
+    secrets::store_credential(TOTP_LAST_STEP_KEY, &step.to_string())?;
+
+    crate::pout(json, serde_json::json!({"ok": true, "backend": "totp"}), "✓ TOTP enrolled. Set `backend = \"totp\"` under [auth.presence] to require it.")
+}
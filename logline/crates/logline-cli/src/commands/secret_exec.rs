@@ -0,0 +1,266 @@
+//! `logline secrets exec` — run a command with vault secrets in its environment.
+//!
+//! Mappings (`ENV=key`) come from `--map` flags and from a `logline.secrets.json`
+//! manifest found in the current directory or a parent (up to the repo root):
+//!
+//! ```json
+//! {
+//!   "DATABASE_URL": "database_url",
+//!   "SENTRY_DSN": { "from_secret": "sentry_dsn", "optional": true }
+//! }
+//! ```
+//!
+//! Values go into the child's environment only. Unless `--no-mask` is given,
+//! the child's stdout/stderr are piped through a filter that replaces any
+//! injected value with `****`.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::commands::secrets;
+
+pub const MANIFEST_FILE: &str = "logline.secrets.json";
+const MASK: &[u8] = b"****";
+/// Shorter values are injected but not masked; they would garble ordinary output.
+const MIN_MASK_LEN: usize = 4;
+
+#[derive(Debug, Clone)]
+struct Mapping {
+    key: String,
+    optional: bool,
+}
+
+/// `logline.secrets.json` in `start` or the nearest parent, stopping at the repo root.
+fn find_manifest(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(MANIFEST_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+fn validate_env_name(name: &str) -> anyhow::Result<()> {
+    ensure!(
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        "Invalid environment variable name '{name}'"
+    );
+    Ok(())
+}
+
+fn load_manifest(path: &Path) -> anyhow::Result<BTreeMap<String, Mapping>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: serde_json::Value =
+        serde_json::from_str(&content).with_context(|| format!("Invalid JSON in {}", path.display()))?;
+    let entries = manifest
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("{} must be a JSON object of ENV -> secret key", path.display()))?;
+
+    let mut out = BTreeMap::new();
+    for (env, spec) in entries {
+        validate_env_name(env)?;
+        let mapping = match spec {
+            serde_json::Value::String(key) => Mapping { key: key.clone(), optional: false },
+            serde_json::Value::Object(_) => Mapping {
+                key: spec["from_secret"]
+                    .as_str()
+                    .ok_or_else(|| anyhow::anyhow!("Missing 'from_secret' for '{env}' in {}", path.display()))?
+                    .to_string(),
+                optional: spec["optional"].as_bool().unwrap_or(false),
+            },
+            _ => bail!("'{env}' in {} must be a key name or {{\"from_secret\": ...}}", path.display()),
+        };
+        out.insert(env.clone(), mapping);
+    }
+    Ok(out)
+}
+
+fn parse_map_flag(flag: &str) -> anyhow::Result<(String, Mapping)> {
+    let (env, key) = flag
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid --map '{flag}'. Use ENV=key (e.g. DATABASE_URL=database_url)"))?;
+    validate_env_name(env)?;
+    ensure!(!key.is_empty(), "Invalid --map '{flag}': missing secret key");
+    Ok((env.to_string(), Mapping { key: key.to_string(), optional: false }))
+}
+
+/// The vault key a mapping reads: `ns/key` when a namespace is given, else `key`.
+/// There is no fallback, so `--namespace prod` never silently injects a shared value.
+fn resolve(key: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(ns) => format!("{}/{key}", ns.trim_matches('/')),
+        None => key.to_string(),
+    }
+}
+
+pub struct ExecOptions<'a> {
+    pub maps: &'a [String],
+    pub manifest: Option<&'a Path>,
+    pub no_manifest: bool,
+    pub namespace: Option<&'a str>,
+    pub no_mask: bool,
+    pub command: &'a [String],
+}
+
+/// `secrets exec`: run the command and exit with its exit code.
+pub fn cmd_exec(opts: &ExecOptions) -> anyhow::Result<()> {
+    crate::require_unlocked("secrets:exec")?;
+    let code = run(opts)?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Run the command and return its exit code.
+fn run(opts: &ExecOptions) -> anyhow::Result<i32> {
+    let (program, args) = opts
+        .command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No command given. Usage: logline secrets exec --map ENV=key -- <cmd> [args...]"))?;
+
+    let mut mappings = BTreeMap::new();
+    if !opts.no_manifest {
+        let manifest = match opts.manifest {
+            Some(path) => Some(path.to_path_buf()),
+            None => find_manifest(&std::env::current_dir()?),
+        };
+        if let Some(path) = manifest {
+            mappings.extend(load_manifest(&path)?);
+        }
+    }
+    for flag in opts.maps {
+        let (env, mapping) = parse_map_flag(flag)?;
+        mappings.insert(env, mapping);
+    }
+    ensure!(
+        !mappings.is_empty(),
+        "No secrets to inject. Pass --map ENV=key or add a {MANIFEST_FILE} manifest."
+    );
+
+    let mut env = Vec::new();
+    let mut missing = Vec::new();
+    for (var, mapping) in &mappings {
+        let key = resolve(&mapping.key, opts.namespace);
+        match secrets::load_credential(&key) {
+            Some(value) => env.push((var.clone(), value)),
+            None if mapping.optional => {}
+            None => missing.push(format!("{var} <- {key}")),
+        }
+    }
+    if !missing.is_empty() {
+        bail!(
+            "Secrets not found in the vault:\n  {}\nStore them with: logline secrets set <key>",
+            missing.join("\n  ")
+        );
+    }
+
+    let names: Vec<&str> = env.iter().map(|(k, _)| k.as_str()).collect();
+    eprintln!("Injecting {} from vault: {}", names.len(), names.join(", "));
+
+    let mut cmd = Command::new(program);
+    cmd.args(args).envs(env.iter().map(|(k, v)| (k, v))).stdin(Stdio::inherit());
+
+    let spawn_err = |e: std::io::Error| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            anyhow::anyhow!("'{program}' not found. Is it installed?")
+        } else {
+            anyhow::anyhow!("Failed to run '{program}': {e}")
+        }
+    };
+
+    if opts.no_mask {
+        let status = cmd.status().map_err(spawn_err)?;
+        return Ok(status.code().unwrap_or(1));
+    }
+
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(spawn_err)?;
+    let patterns: Vec<Vec<u8>> = {
+        let mut p: Vec<Vec<u8>> = env
+            .iter()
+            .map(|(_, v)| v.as_bytes().to_vec())
+            .filter(|v| v.len() >= MIN_MASK_LEN)
+            .collect();
+        // Longest first, so a secret containing another is masked whole.
+        p.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        p.dedup();
+        p
+    };
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let out_patterns = patterns.clone();
+    let out = std::thread::spawn(move || mask_stream(stdout, std::io::stdout(), &out_patterns));
+    let err = std::thread::spawn(move || mask_stream(stderr, std::io::stderr(), &patterns));
+
+    let status = child.wait().context("Failed to wait for child process")?;
+    for handle in [out, err] {
+        handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("output masking thread panicked"))?
+            .context("Failed to relay child output")?;
+    }
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Copy `input` to `output`, replacing every occurrence of a pattern with `****`.
+///
+/// Output is forwarded as soon as it arrives, except for a trailing fragment
+/// that could still be the start of a secret split across reads.
+fn mask_stream(mut input: impl Read, mut output: impl Write, patterns: &[Vec<u8>]) -> std::io::Result<()> {
+    let mut pending: Vec<u8> = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            output.write_all(&mask(&pending, patterns))?;
+            return output.flush();
+        }
+        pending.extend_from_slice(&buf[..n]);
+        let masked = mask(&pending, patterns);
+        let hold = partial_suffix_len(&masked, patterns);
+        output.write_all(&masked[..masked.len() - hold])?;
+        output.flush()?;
+        pending = masked[masked.len() - hold..].to_vec();
+    }
+}
+
+fn mask(data: &[u8], patterns: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    'outer: while i < data.len() {
+        for p in patterns {
+            if data[i..].starts_with(p) {
+                out.extend_from_slice(MASK);
+                i += p.len();
+                continue 'outer;
+            }
+        }
+        out.push(data[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Length of the longest suffix of `data` that is a proper prefix of a pattern.
+fn partial_suffix_len(data: &[u8], patterns: &[Vec<u8>]) -> usize {
+    patterns
+        .iter()
+        .filter_map(|p| {
+            (1..p.len().min(data.len() + 1))
+                .rev()
+                .find(|&len| data.ends_with(&p[..len]))
+        })
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,217 @@
+//! Index of the secrets stored with `logline secrets`.
+//!
+//! Neither vault backend can enumerate its entries, so every write through
+//! [`secrets::store_secret`] also records the key here along with who stored it,
+//! when, and its expiry/rotation policy. Keys may be namespaced by environment
+//! or app (`prod/database_url`). The index is itself a vault entry, so it moves
+//! with `secrets vault migrate`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::secrets;
+
+/// Vault user holding the index (under the `logline-cli` service).
+pub const INDEX_KEY: &str = "secrets_index";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretMeta {
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Rotation is due this many days after `updated_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_every_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Metadata supplied with a write; `None`/empty keeps what is already indexed.
+#[derive(Debug, Default)]
+pub struct SecretOptions {
+    pub expires_at: Option<u64>,
+    pub rotate_every_days: Option<u32>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    Ok,
+    /// Rotation was due at this time.
+    RotationDue(u64),
+    /// Expired at this time.
+    Expired(u64),
+}
+
+impl SecretMeta {
+    pub fn rotation_due_at(&self) -> Option<u64> {
+        self.rotate_every_days
+            .map(|d| self.updated_at + u64::from(d) * 86400)
+    }
+
+    pub fn freshness(&self, now: u64) -> Freshness {
+        if let Some(at) = self.expires_at.filter(|&at| at <= now) {
+            return Freshness::Expired(at);
+        }
+        match self.rotation_due_at() {
+            Some(at) if at <= now => Freshness::RotationDue(at),
+            _ => Freshness::Ok,
+        }
+    }
+}
+
+pub type SecretIndex = BTreeMap<String, SecretMeta>;
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub fn parse(json: &str) -> anyhow::Result<SecretIndex> {
+    serde_json::from_str(json).with_context(|| {
+        format!(
+            "The secrets index (vault entry '{INDEX_KEY}') is unreadable. \
+             Refusing to use or overwrite it; restore it from a vault backup or export."
+        )
+    })
+}
+
+/// The index; empty if none was stored yet. Fails if it exists but can't be
+/// parsed, so writers never save over an index they couldn't read.
+pub fn load() -> anyhow::Result<SecretIndex> {
+    secrets::try_load_credential(INDEX_KEY)?
+        .map_or_else(|| Ok(SecretIndex::new()), |json| parse(&json))
+}
+
+fn save(index: &SecretIndex) -> anyhow::Result<()> {
+    secrets::store_unindexed(INDEX_KEY, &serde_json::to_string(index)?)
+}
+
+/// Who is storing a secret: the logged-in email, else `user@host`.
+fn stored_by() -> String {
+    crate::supabase::load_auth()
+        .and_then(|a| a.email)
+        .unwrap_or_else(|| {
+            let user = std::env::var("USER").unwrap_or_else(|_| "unknown".into());
+            format!("{user}@{}", crate::get_hostname())
+        })
+}
+
+fn fresh(now: u64) -> SecretMeta {
+    SecretMeta {
+        created_at: now,
+        updated_at: now,
+        stored_by: Some(stored_by()),
+        expires_at: None,
+        rotate_every_days: None,
+        tags: Vec::new(),
+    }
+}
+
+/// Record a write of `key`, keeping its creation time and any policy not overridden.
+pub fn record(key: &str, opts: &SecretOptions) -> anyhow::Result<()> {
+    let mut index = load().context("Secret stored, but the secrets index was not updated")?;
+    let now = now_secs();
+    let meta = index.entry(key.to_string()).or_insert_with(|| fresh(now));
+    meta.updated_at = now;
+    meta.stored_by = Some(stored_by());
+    if opts.expires_at.is_some() {
+        meta.expires_at = opts.expires_at;
+    }
+    if opts.rotate_every_days.is_some() {
+        meta.rotate_every_days = opts.rotate_every_days;
+    }
+    if !opts.tags.is_empty() {
+        meta.tags.clone_from(&opts.tags);
+    }
+    save(&index).context("Secret stored, but updating the secrets index failed")
+}
+
+/// Index imported keys, keeping the metadata they were exported with.
+pub fn import(entries: BTreeMap<String, Option<SecretMeta>>) -> anyhow::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut index = load().context("Secrets imported, but the secrets index was not updated")?;
+    let now = now_secs();
+    for (key, meta) in entries {
+        index.insert(key, meta.unwrap_or_else(|| fresh(now)));
+    }
+    save(&index).context("Secrets imported, but updating the secrets index failed")
+}
+
+/// Drop `key` from the index. Returns whether it was indexed.
+pub fn forget(key: &str) -> anyhow::Result<bool> {
+    let mut index = load()?;
+    if index.remove(key).is_none() {
+        return Ok(false);
+    }
+    save(&index)?;
+    Ok(true)
+}
+
+/// `prod` for `prod/database_url`; `None` for un-namespaced keys.
+pub fn namespace_of(key: &str) -> Option<&str> {
+    key.rsplit_once('/').map(|(ns, _)| ns)
+}
+
+/// True when `key` lives in `namespace` or one of its children.
+pub fn in_namespace(key: &str, namespace: &str) -> bool {
+    let namespace = namespace.trim_matches('/');
+    namespace_of(key).is_some_and(|ns| ns == namespace || ns.starts_with(&format!("{namespace}/")))
+}
+
+fn days_from_ymd(y: u64, m: u64, d: u64) -> Option<u64> {
+    let m = usize::try_from(m).ok().filter(|m| (1..=12).contains(m))?;
+    if y < 1970 {
+        return None;
+    }
+    let leap = |y: u64| y % 4 == 0 && (y % 100 != 0 || y % 400 == 0);
+    let month_days = [31, if leap(y) { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if d == 0 || d > month_days[m - 1] {
+        return None;
+    }
+    let years: u64 = (1970..y).map(|y| if leap(y) { 366 } else { 365 }).sum();
+    let months: u64 = month_days[..m - 1].iter().sum();
+    Some(years + months + d - 1)
+}
+
+/// Parse an expiry: `YYYY-MM-DD` (midnight UTC) or a relative `<N>d`.
+pub fn parse_expiry(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    if let Some(days) = s.strip_suffix('d') {
+        let days: u64 = days.parse().with_context(|| format!("Invalid expiry '{s}'"))?;
+        return Ok(now_secs() + days * 86400);
+    }
+    let parts: Vec<u64> = s.split('-').filter_map(|p| p.parse().ok()).collect();
+    let days = match parts[..] {
+        [y, m, d] => days_from_ymd(y, m, d),
+        _ => None,
+    };
+    let days = days.ok_or_else(|| anyhow::anyhow!("Invalid expiry '{s}'. Use YYYY-MM-DD or <N>d (e.g. 90d)."))?;
+    Ok(days * 86400)
+}
+
+/// Parse a rotation interval in days: `90` or `90d`.
+pub fn parse_rotation(s: &str) -> anyhow::Result<u32> {
+    let days: u32 = s
+        .trim()
+        .trim_end_matches('d')
+        .parse()
+        .with_context(|| format!("Invalid rotation interval '{s}'. Use a number of days (e.g. 90d)."))?;
+    ensure!(days > 0, "Rotation interval must be at least one day");
+    Ok(days)
+}
+
+/// `YYYY-MM-DD` for a unix timestamp.
+pub fn format_date(secs: u64) -> String {
+    let (y, m, d) = crate::days_to_ymd(secs / 86400);
+    format!("{y:04}-{m:02}-{d:02}")
+}
@@ -0,0 +1,469 @@
+//! `logline secrets rotate` — replace a credential and keep the old one until confirmed.
+//!
+//! A [`Rotator`] is chosen by the key's name (the part after any namespace). It
+//! issues a new value, through the provider API where one exists or by prompting
+//! otherwise, and probes it before anything is stored. The previous value is
+//! kept in the vault as a pending rotation until `--confirm`; `--rollback`
+//! restores it, re-pushes it to Vercel if the new one was pushed, and discards
+//! anything the rotator created.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::secret_index::{self, SecretMeta, SecretOptions};
+use crate::commands::{audit, secrets};
+use crate::integrations::{pg, vercel};
+
+/// Vault user holding pending rotations (under the `logline-cli` service).
+pub const PENDING_KEY: &str = "secrets_rotation_pending";
+
+const DEFAULT_VERCEL_TARGETS: &[&str] = &["production", "preview", "development"];
+
+/// A new credential from [`Rotator::issue`].
+pub struct Issued {
+    pub value: String,
+    /// Provider id of what was created (e.g. a Vercel token id), for cleanup.
+    pub handle: Option<String>,
+}
+
+/// Provider-specific way to replace one kind of credential.
+pub trait Rotator {
+    /// Rotator name, recorded in the pending rotation and audit log.
+    fn name(&self) -> &'static str;
+    /// Produce a replacement for `current`.
+    fn issue(&self, key: &str, current: &str) -> anyhow::Result<Issued>;
+    /// Check `value` works. Returns a short description of what it reached.
+    fn probe(&self, value: &str) -> anyhow::Result<String>;
+    /// Undo `issue` after a failed probe or a rollback. `active` is the credential in effect.
+    fn discard(&self, _issued: &Issued, _active: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+    /// What to do with the old credential once the new one is confirmed.
+    fn retire_hint(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+fn http() -> anyhow::Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?)
+}
+
+fn prompt_new_value(key: &str) -> anyhow::Result<String> {
+    let value = rpassword::prompt_password(format!("New value for '{key}' (hidden): "))?;
+    ensure!(!value.trim().is_empty(), "Value cannot be empty");
+    Ok(value.trim().to_string())
+}
+
+fn json_or_bail(resp: reqwest::blocking::Response, what: &str) -> anyhow::Result<serde_json::Value> {
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().unwrap_or_default();
+        bail!("{what} failed ({status}): {text}");
+    }
+    Ok(resp.json()?)
+}
+
+struct GitHub;
+
+impl Rotator for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn issue(&self, key: &str, _current: &str) -> anyhow::Result<Issued> {
+        eprintln!("GitHub has no API to mint personal access tokens.");
+        eprintln!("Create one at https://github.com/settings/tokens with the same scopes, then paste it.");
+        Ok(Issued { value: prompt_new_value(key)?, handle: None })
+    }
+
+    fn probe(&self, value: &str) -> anyhow::Result<String> {
+        let resp = http()?
+            .get("https://api.github.com/user")
+            .bearer_auth(value)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "logline-cli")
+            .send()?;
+        let user = json_or_bail(resp, "GitHub /user")?;
+        Ok(format!("GitHub user {}", user["login"].as_str().unwrap_or("?")))
+    }
+
+    fn retire_hint(&self) -> Option<&'static str> {
+        Some("Delete the old token at https://github.com/settings/tokens")
+    }
+}
+
+struct Vercel;
+
+impl Rotator for Vercel {
+    fn name(&self) -> &'static str {
+        "vercel"
+    }
+
+    fn issue(&self, _key: &str, current: &str) -> anyhow::Result<Issued> {
+        let name = format!("logline-cli {} {}", crate::get_hostname(), secret_index::format_date(now_secs()));
+        let resp = http()?
+            .post("https://api.vercel.com/v3/user/tokens")
+            .bearer_auth(current)
+            .header("User-Agent", "logline-cli")
+            .json(&serde_json::json!({ "name": name }))
+            .send()?;
+        let body = json_or_bail(resp, "Vercel token creation")?;
+        let value = body["bearerToken"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Vercel did not return a token"))?
+            .to_string();
+        Ok(Issued { value, handle: body["token"]["id"].as_str().map(str::to_string) })
+    }
+
+    fn probe(&self, value: &str) -> anyhow::Result<String> {
+        let resp = http()?
+            .get("https://api.vercel.com/v2/user")
+            .bearer_auth(value)
+            .header("User-Agent", "logline-cli")
+            .send()?;
+        let body = json_or_bail(resp, "Vercel /v2/user")?;
+        Ok(format!("Vercel user {}", body["user"]["username"].as_str().unwrap_or("?")))
+    }
+
+    fn discard(&self, issued: &Issued, active: &str) -> anyhow::Result<()> {
+        let Some(id) = &issued.handle else { return Ok(()) };
+        let resp = http()?
+            .delete(format!("https://api.vercel.com/v3/user/tokens/{id}"))
+            .bearer_auth(active)
+            .header("User-Agent", "logline-cli")
+            .send()?;
+        json_or_bail(resp, "Vercel token deletion").map(|_| ())
+    }
+
+    fn retire_hint(&self) -> Option<&'static str> {
+        Some("Delete the old token at https://vercel.com/account/tokens")
+    }
+}
+
+struct SupabaseServiceRole;
+
+impl Rotator for SupabaseServiceRole {
+    fn name(&self) -> &'static str {
+        "supabase-service-role"
+    }
+
+    fn issue(&self, key: &str, _current: &str) -> anyhow::Result<Issued> {
+        eprintln!("Service role keys are rotated from the Supabase dashboard (Project Settings → API).");
+        Ok(Issued { value: prompt_new_value(key)?, handle: None })
+    }
+
+    fn probe(&self, value: &str) -> anyhow::Result<String> {
+        let url = secrets::load_credential("supabase_url")
+            .map_or_else(|| crate::supabase::SupabaseConfig::from_env_or_file().map(|c| c.url), Ok)?;
+        let resp = http()?
+            .get(format!("{}/rest/v1/", url.trim_end_matches('/')))
+            .header("apikey", value)
+            .bearer_auth(value)
+            .send()?;
+        json_or_bail(resp, "Supabase REST probe")?;
+        Ok(format!("Supabase REST at {url}"))
+    }
+}
+
+struct SupabaseAccessToken;
+
+impl Rotator for SupabaseAccessToken {
+    fn name(&self) -> &'static str {
+        "supabase-management"
+    }
+
+    fn issue(&self, key: &str, _current: &str) -> anyhow::Result<Issued> {
+        eprintln!("Create a new access token at https://supabase.com/dashboard/account/tokens, then paste it.");
+        Ok(Issued { value: prompt_new_value(key)?, handle: None })
+    }
+
+    fn probe(&self, value: &str) -> anyhow::Result<String> {
+        let resp = http()?
+            .get("https://api.supabase.com/v1/projects")
+            .bearer_auth(value)
+            .send()?;
+        let projects = json_or_bail(resp, "Supabase Management API")?;
+        Ok(format!(
+            "Supabase Management API ({} project(s))",
+            projects.as_array().map_or(0, Vec::len)
+        ))
+    }
+
+    fn retire_hint(&self) -> Option<&'static str> {
+        Some("Revoke the old token at https://supabase.com/dashboard/account/tokens")
+    }
+}
+
+struct Postgres;
+
+impl Rotator for Postgres {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn issue(&self, key: &str, _current: &str) -> anyhow::Result<Issued> {
+        eprintln!("Paste the new connection string (e.g. after resetting the database password).");
+        Ok(Issued { value: prompt_new_value(key)?, handle: None })
+    }
+
+    fn probe(&self, value: &str) -> anyhow::Result<String> {
+        pg::ping(value).context("Connection test failed")?;
+        Ok("Postgres connection (select 1)".to_string())
+    }
+}
+
+/// Any other key: prompt, no probe.
+struct Manual;
+
+impl Rotator for Manual {
+    fn name(&self) -> &'static str {
+        "manual"
+    }
+
+    fn issue(&self, key: &str, _current: &str) -> anyhow::Result<Issued> {
+        Ok(Issued { value: prompt_new_value(key)?, handle: None })
+    }
+
+    fn probe(&self, _value: &str) -> anyhow::Result<String> {
+        Ok("no probe for this key".to_string())
+    }
+}
+
+/// Rotator for `key`, by its name without namespace.
+pub fn rotator_for(key: &str) -> Box<dyn Rotator> {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    match name {
+        "github_token" => Box::new(GitHub),
+        "vercel_token" => Box::new(Vercel),
+        "supabase_service_role_key" => Box::new(SupabaseServiceRole),
+        "supabase_access_token" => Box::new(SupabaseAccessToken),
+        "database_url" | "database_url_unpooled" => Box::new(Postgres),
+        _ => Box::new(Manual),
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VercelPush {
+    name: String,
+    targets: Vec<String>,
+}
+
+/// A rotation whose new value is live but not yet confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRotation {
+    pub rotated_at: u64,
+    pub rotator: String,
+    previous: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_meta: Option<SecretMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    handle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vercel: Option<VercelPush>,
+}
+
+/// In-flight rotations; empty if none. Fails if the store exists but can't be
+/// parsed: it holds the only copy of each previous value, so it must never be
+/// saved over with a map that lost them.
+pub fn load_pending() -> anyhow::Result<BTreeMap<String, PendingRotation>> {
+    secrets::try_load_credential(PENDING_KEY)?.map_or_else(
+        || Ok(BTreeMap::new()),
+        |json| {
+            serde_json::from_str(&json).with_context(|| {
+                format!(
+                    "The pending rotations (vault entry '{PENDING_KEY}') are unreadable. \
+                     Refusing to rotate, confirm or roll back until it is restored from a vault backup."
+                )
+            })
+        },
+    )
+}
+
+fn save_pending(pending: &BTreeMap<String, PendingRotation>) -> anyhow::Result<()> {
+    if pending.is_empty() {
+        secrets::delete_unindexed(PENDING_KEY)?;
+        return Ok(());
+    }
+    secrets::store_unindexed(PENDING_KEY, &serde_json::to_string(pending)?)
+}
+
+/// `DATABASE_URL` for `prod/database_url`.
+fn default_env_name(key: &str) -> String {
+    key.rsplit('/').next().unwrap_or(key).to_ascii_uppercase()
+}
+
+pub struct RotateOptions<'a> {
+    pub key: &'a str,
+    pub push_vercel: bool,
+    /// Vercel env var name; defaults to the key in upper case.
+    pub vercel_env: Option<&'a str>,
+    pub vercel_targets: &'a [String],
+}
+
+/// `secrets rotate`: start a rotation, or `--confirm` / `--rollback` a pending one.
+pub fn cmd_rotate(opts: &RotateOptions, confirm_pending: bool, rollback_pending: bool, json: bool) -> anyhow::Result<()> {
+    crate::require_unlocked("secrets:rotate")?;
+    if confirm_pending {
+        confirm(opts.key, json)
+    } else if rollback_pending {
+        rollback(opts.key, json)
+    } else {
+        rotate(opts, json)
+    }
+}
+
+fn rotate(opts: &RotateOptions, json: bool) -> anyhow::Result<()> {
+    let key = opts.key;
+    secrets::validate_key(key)?;
+    let mut pending = load_pending()?;
+    ensure!(
+        !pending.contains_key(key),
+        "'{key}' has an unconfirmed rotation. Run `logline secrets rotate {key} --confirm` or `--rollback` first."
+    );
+    let current = secrets::require_credential(key)?;
+    let rotator = rotator_for(key);
+
+    eprintln!("Rotating {key} ({})...", rotator.name());
+    let issued = rotator.issue(key, &current)?;
+    ensure!(issued.value != current, "The new value is identical to the current one.");
+
+    let probe = match rotator.probe(&issued.value) {
+        Ok(probe) => probe,
+        Err(e) => {
+            if let Err(cleanup) = rotator.discard(&issued, &current) {
+                eprintln!("⚠ Could not discard the new credential: {cleanup:#}");
+            }
+            bail!("New value for '{key}' failed its probe; nothing was changed.\n{e:#}");
+        }
+    };
+    eprintln!("✓ Probe: {probe}");
+
+    let vercel = opts.push_vercel.then(|| VercelPush {
+        name: opts.vercel_env.map_or_else(|| default_env_name(key), str::to_string),
+        targets: if opts.vercel_targets.is_empty() {
+            DEFAULT_VERCEL_TARGETS.iter().map(|t| (*t).to_string()).collect()
+        } else {
+            opts.vercel_targets.to_vec()
+        },
+    });
+
+    // Record the previous value before replacing it, so an interrupted rotation can still roll back.
+    pending.insert(
+        key.to_string(),
+        PendingRotation {
+            rotated_at: now_secs(),
+            rotator: rotator.name().to_string(),
+            previous: current,
+            previous_meta: secret_index::load()?.remove(key),
+            handle: issued.handle.clone(),
+            vercel: vercel.clone(),
+        },
+    );
+    save_pending(&pending)?;
+    secrets::store_secret(key, &issued.value, &SecretOptions::default())?;
+
+    let mut pushed = None;
+    if let Some(push) = &vercel {
+        let targets: Vec<&str> = push.targets.iter().map(String::as_str).collect();
+        match vercel::upsert_env_var(&push.name, &issued.value, &targets) {
+            Ok(()) => pushed = Some(push.name.clone()),
+            Err(e) => eprintln!("⚠ Vercel push failed: {e:#}\n  The vault has the new value; roll back with --rollback if needed."),
+        }
+    }
+
+    audit::record(
+        "secrets.rotate",
+        &serde_json::json!({
+            "key": key,
+            "rotator": rotator.name(),
+            "probe": probe,
+            "vercel_env": pushed,
+        }),
+    )?;
+
+    crate::pout(
+        json,
+        serde_json::json!({
+            "ok": true,
+            "key": key,
+            "rotator": rotator.name(),
+            "probe": probe,
+            "vercel_env": pushed,
+            "pending_confirmation": true,
+        }),
+        &format!(
+            "✓ Rotated {key}{}\n\
+             The previous value is kept until you confirm.\n  \
+             Confirm:   logline secrets rotate {key} --confirm\n  \
+             Roll back: logline secrets rotate {key} --rollback",
+            pushed.map(|n| format!(" (pushed to Vercel as {n})")).unwrap_or_default()
+        ),
+    )
+}
+
+fn confirm(key: &str, json: bool) -> anyhow::Result<()> {
+    let mut pending = load_pending()?;
+    let Some(rotation) = pending.remove(key) else {
+        bail!("No pending rotation for '{key}'.");
+    };
+    save_pending(&pending)?;
+    audit::record("secrets.rotate.confirm", &serde_json::json!({"key": key, "rotator": rotation.rotator}))?;
+
+    let hint = rotator_for(key).retire_hint();
+    crate::pout(
+        json,
+        serde_json::json!({"ok": true, "key": key, "confirmed": true, "retire_hint": hint}),
+        &format!(
+            "✓ Confirmed rotation of {key}; the previous value was discarded.{}",
+            hint.map(|h| format!("\n  {h}")).unwrap_or_default()
+        ),
+    )
+}
+
+fn rollback(key: &str, json: bool) -> anyhow::Result<()> {
+    let mut pending = load_pending()?;
+    let Some(rotation) = pending.get(key).cloned() else {
+        bail!("No pending rotation for '{key}'.");
+    };
+    let rotated = secrets::load_credential(key);
+
+    secrets::store_unindexed(key, &rotation.previous)?;
+    secret_index::import(BTreeMap::from([(key.to_string(), rotation.previous_meta.clone())]))?;
+
+    if let Some(push) = &rotation.vercel {
+        let targets: Vec<&str> = push.targets.iter().map(String::as_str).collect();
+        vercel::upsert_env_var(&push.name, &rotation.previous, &targets)
+            .with_context(|| format!("Restored {key} in the vault, but re-pushing {} to Vercel failed", push.name))?;
+    }
+    if let Some(value) = rotated {
+        let issued = Issued { value, handle: rotation.handle.clone() };
+        if let Err(e) = rotator_for(key).discard(&issued, &rotation.previous) {
+            eprintln!("⚠ Could not discard the rotated credential: {e:#}");
+        }
+    }
+
+    pending.remove(key);
+    save_pending(&pending)?;
+    audit::record(
+        "secrets.rotate.rollback",
+        &serde_json::json!({"key": key, "rotator": rotation.rotator, "vercel_env": rotation.vercel.map(|v| v.name)}),
+    )?;
+
+    crate::pout(
+        json,
+        serde_json::json!({"ok": true, "key": key, "rolled_back": true}),
+        &format!("✓ Rolled back {key} to its previous value"),
+    )
+}
@@ -1,9 +1,14 @@
-use anyhow::{bail, ensure};
+use anyhow::ensure;
 use clap::Subcommand;
 
+use crate::commands::{secret_bundle, secret_exec, secret_rotate, secret_scan};
+use crate::commands::secret_index::{self, Freshness, SecretOptions};
+use crate::commands::vault::{self, VaultEntry};
+use crate::commands::{auth_context, db, presence};
+
 const KEYRING_SERVICE: &str = "logline-cli";
 
-const ALL_KEYS: &[&str] = &[
+pub const ALL_KEYS: &[&str] = &[
     "supabase_url",
     "supabase_anon_key",
     "supabase_service_role_key",
@@ -20,39 +25,185 @@ const ALL_KEYS: &[&str] = &[
 pub enum SecretsCommands {
     /// Store a credential in macOS Keychain (prompted, never echoed)
     Set {
-        /// Credential key (e.g. github_token, database_url)
+        /// Credential key, optionally namespaced (e.g. github_token, prod/database_url)
         key: String,
+        /// Expiry date: YYYY-MM-DD or relative, e.g. 90d
+        #[arg(long)]
+        expires: Option<String>,
+        /// Rotation interval in days, e.g. 90d
+        #[arg(long)]
+        rotate_every: Option<String>,
+        /// Tags (repeatable or comma-separated)
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
     },
     /// Retrieve a credential from Keychain (requires unlocked session)
     Get {
         /// Credential key
         key: String,
     },
-    /// List all stored credential keys (names only, never values)
-    Ls,
+    /// List all stored credential keys with metadata (names only, never values)
+    Ls {
+        /// Only keys in this namespace (e.g. prod)
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Only keys with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
     /// Remove a credential from Keychain
     Rm {
         /// Credential key to remove
         key: String,
     },
-    /// Remove ALL stored credentials
-    Clear,
-    /// Check vault completeness against pipeline requirements
+    /// Remove ALL stored credentials (or all in one namespace)
+    Clear {
+        /// Only clear keys in this namespace
+        #[arg(long)]
+        namespace: Option<String>,
+    },
+    /// Check vault completeness, expiry and rotation against pipeline requirements
     Doctor,
+    /// Run a command with vault secrets injected into its environment
+    ///
+    /// Mappings come from --map and from a `logline.secrets.json` manifest in
+    /// the current directory or a parent. Injected values are masked in the
+    /// command's output.
+    Exec {
+        /// ENV=key mapping (repeatable), e.g. `DATABASE_URL=database_url`
+        #[arg(long = "map", value_name = "ENV=KEY")]
+        maps: Vec<String>,
+        /// Manifest path (default: nearest `logline.secrets.json`)
+        #[arg(long)]
+        manifest: Option<std::path::PathBuf>,
+        /// Ignore any manifest; use only --map
+        #[arg(long, conflicts_with = "manifest")]
+        no_manifest: bool,
+        /// Read keys from this namespace (prod -> prod/<key>); a key missing there is an error
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Don't pipe output through the masking filter (for interactive tools)
+        #[arg(long)]
+        no_mask: bool,
+        /// Command and arguments, after --
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Export secrets to an encrypted bundle for another machine
+    Export {
+        /// Bundle file to write (e.g. bundle.age)
+        #[arg(long)]
+        to: std::path::PathBuf,
+        /// Recipient: passkey public key (hex), ssh-ed25519 key, or age1... key
+        #[arg(long)]
+        recipient: String,
+        /// Only export keys in this namespace
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Overwrite an existing bundle file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import secrets from an encrypted bundle
+    Import {
+        /// Bundle file written by `logline secrets export`
+        bundle: std::path::PathBuf,
+        /// age identity file or OpenSSH key (default: this machine's passkey)
+        #[arg(long)]
+        identity: Option<std::path::PathBuf>,
+        /// Replace secrets that are already stored (default: keep them)
+        #[arg(long)]
+        overwrite: bool,
+        /// Show what would be imported without storing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rotate a credential: issue/prompt a new value, probe it, keep the old one until confirmed
+    Rotate {
+        /// Credential key (e.g. `github_token`, `prod/database_url`)
+        key: String,
+        /// Confirm a pending rotation and discard the previous value
+        #[arg(long, conflicts_with = "rollback")]
+        confirm: bool,
+        /// Restore the previous value of a pending rotation
+        #[arg(long)]
+        rollback: bool,
+        /// Also push the new value to Vercel env
+        #[arg(long, conflicts_with_all = ["confirm", "rollback"])]
+        push_vercel: bool,
+        /// Vercel env var name for --push-vercel (default: the key in upper case)
+        #[arg(long, value_name = "ENV_NAME", requires = "push_vercel")]
+        vercel_env: Option<String>,
+        /// Vercel targets for --push-vercel (default: production, preview, development)
+        #[arg(long = "vercel-target", value_delimiter = ',', requires = "push_vercel")]
+        vercel_targets: Vec<String>,
+    },
+    /// Scan the working tree, .env files and optionally git history for leaked secrets
+    ///
+    /// Matches stored vault values (reported by key and fingerprint, never by
+    /// value) and common token shapes. Exits non-zero when anything is found.
+    Scan {
+        /// Directory to scan (default: current directory)
+        #[arg(long)]
+        path: Option<std::path::PathBuf>,
+        /// Also scan lines added in git history (all branches)
+        #[arg(long)]
+        history: bool,
+        /// Limit --history to the most recent N commits
+        #[arg(long, requires = "history")]
+        max_commits: Option<usize>,
+        /// Only look for token patterns; don't load vault values
+        #[arg(long)]
+        no_vault: bool,
+        /// Also write the JSON report to this file
+        #[arg(long)]
+        report: Option<std::path::PathBuf>,
+    },
+    /// Print this machine's bundle recipient (its passkey as an ssh-ed25519 key)
+    Recipient,
+    /// Choose and migrate the storage backend (OS keyring or encrypted file)
+    Vault {
+        #[command(subcommand)]
+        command: vault::VaultCommands,
+    },
 }
 
+/// Keys the CLI stores for itself. They are not user secrets and stay out of the index.
+pub const INTERNAL_KEYS: &[&str] = &[
+    presence::TOTP_SECRET_KEY,
+    presence::TOTP_LAST_STEP_KEY,
+    db::REVIEW_RECEIPT_KEY,
+    secret_index::INDEX_KEY,
+    secret_rotate::PENDING_KEY,
+];
+
 pub fn store_credential(key: &str, value: &str) -> anyhow::Result<()> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, key)
-        .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-    entry
-        .set_password(value)
-        .map_err(|e| anyhow::anyhow!("Failed to store '{key}' in keychain: {e}"))?;
-    Ok(())
+    store_secret(key, value, &SecretOptions::default())
+}
+
+/// Store `key` and record it in the secrets index with `opts`.
+pub fn store_secret(key: &str, value: &str, opts: &SecretOptions) -> anyhow::Result<()> {
+    if INTERNAL_KEYS.contains(&key) {
+        return store_unindexed(key, value);
+    }
+    // Don't store a value the index then can't record.
+    secret_index::load()?;
+    store_unindexed(key, value)?;
+    secret_index::record(key, opts)
+}
+
+/// Store `key` without touching the index (internal state, the index itself).
+pub fn store_unindexed(key: &str, value: &str) -> anyhow::Result<()> {
+    VaultEntry::new(KEYRING_SERVICE, key).set(value)
 }
 
 pub fn load_credential(key: &str) -> Option<String> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, key).ok()?;
-    entry.get_password().ok()
+    VaultEntry::new(KEYRING_SERVICE, key).get()
+}
+
+/// Like [`load_credential`], but a vault that can't be read is an error rather than `None`.
+pub fn try_load_credential(key: &str) -> anyhow::Result<Option<String>> {
+    VaultEntry::new(KEYRING_SERVICE, key).try_get()
 }
 
 pub fn load_credential_or_env(keychain_key: &str, env_var: &str) -> Option<String> {
@@ -81,31 +232,62 @@ pub fn require_credential_or_env(keychain_key: &str, env_var: &str) -> anyhow::R
     })
 }
 
+/// Delete `key` from the vault without touching the index.
+pub fn delete_unindexed(key: &str) -> anyhow::Result<bool> {
+    VaultEntry::new(KEYRING_SERVICE, key).delete()
+}
+
+/// Delete `key` from the vault and the index. True if either held it.
 fn delete_credential(key: &str) -> anyhow::Result<bool> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, key)
-        .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-    match entry.delete_credential() {
-        Ok(()) => Ok(true),
-        Err(keyring::Error::NoEntry) => Ok(false),
-        Err(e) => bail!("Failed to delete '{key}': {e}"),
-    }
+    let deleted = delete_unindexed(key)?;
+    let indexed = !INTERNAL_KEYS.contains(&key) && secret_index::forget(key)?;
+    Ok(deleted || indexed)
 }
 
-fn validate_key(key: &str) -> anyhow::Result<()> {
+pub fn validate_key(key: &str) -> anyhow::Result<()> {
+    let (namespace, name) = key.rsplit_once('/').unwrap_or(("", key));
     ensure!(
-        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
         "Invalid key '{key}'. Use lowercase alphanumeric + underscores (e.g. github_token)"
     );
+    ensure!(
+        namespace.is_empty()
+            || namespace.split('/').all(|seg| {
+                !seg.is_empty() && seg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            }),
+        "Invalid namespace in '{key}'. Use segments like prod/ or web-app/prod/"
+    );
+    ensure!(
+        !INTERNAL_KEYS.contains(&key) && !auth_context::CONTEXT_KEYRING_USERS.contains(&key),
+        "'{key}' is reserved for the CLI's own state"
+    );
     Ok(())
 }
 
+/// Every user secret: the index plus well-known keys stored before the index existed.
+pub fn stored_keys() -> anyhow::Result<Vec<String>> {
+    let mut keys: Vec<String> = secret_index::load()?.into_keys().collect();
+    for &key in ALL_KEYS {
+        if !keys.iter().any(|k| k == key) && load_credential(key).is_some() {
+            keys.push(key.to_string());
+        }
+    }
+    keys.sort();
+    Ok(keys)
+}
+
 pub fn cmd_secrets(command: SecretsCommands, json: bool) -> anyhow::Result<()> {
     match command {
-        SecretsCommands::Set { key } => {
+        SecretsCommands::Set { key, expires, rotate_every, tags } => {
             validate_key(&key)?;
+            let opts = SecretOptions {
+                expires_at: expires.as_deref().map(secret_index::parse_expiry).transpose()?,
+                rotate_every_days: rotate_every.as_deref().map(secret_index::parse_rotation).transpose()?,
+                tags: tags.into_iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+            };
             let value = rpassword::prompt_password(format!("Enter value for '{key}' (hidden): "))?;
             ensure!(!value.trim().is_empty(), "Value cannot be empty");
-            store_credential(&key, value.trim())?;
+            store_secret(&key, value.trim(), &opts)?;
             crate::pout(
                 json,
                 serde_json::json!({"ok": true, "key": key}),
@@ -113,7 +295,7 @@ pub fn cmd_secrets(command: SecretsCommands, json: bool) -> anyhow::Result<()> {
             )
         }
         SecretsCommands::Get { key } => {
-            crate::require_unlocked()?;
+            crate::require_unlocked("secrets:read")?;
             let value = require_credential(&key)?;
             if json {
                 println!(
@@ -125,24 +307,7 @@ pub fn cmd_secrets(command: SecretsCommands, json: bool) -> anyhow::Result<()> {
             }
             Ok(())
         }
-        SecretsCommands::Ls => {
-            let mut entries = Vec::new();
-            for &key in ALL_KEYS {
-                let present = load_credential(key).is_some();
-                entries.push(serde_json::json!({"key": key, "stored": present}));
-            }
-            if json {
-                println!("{}", serde_json::to_string_pretty(&entries)?);
-            } else {
-                for entry in &entries {
-                    let k = entry["key"].as_str().unwrap_or("?");
-                    let stored = entry["stored"].as_bool().unwrap_or(false);
-                    let mark = if stored { "✓" } else { "✗" };
-                    println!("  {k:<30} {mark}");
-                }
-            }
-            Ok(())
-        }
+        SecretsCommands::Ls { namespace, tag } => cmd_secrets_ls(namespace.as_deref(), tag.as_deref(), json),
         SecretsCommands::Rm { key } => {
             validate_key(&key)?;
             let deleted = delete_credential(&key)?;
@@ -160,25 +325,139 @@ pub fn cmd_secrets(command: SecretsCommands, json: bool) -> anyhow::Result<()> {
                 )
             }
         }
-        SecretsCommands::Clear => {
+        SecretsCommands::Clear { namespace } => {
             let mut removed = 0u32;
-            for &key in ALL_KEYS {
-                if delete_credential(key)? {
+            for key in stored_keys()? {
+                if namespace.as_deref().is_some_and(|ns| !secret_index::in_namespace(&key, ns)) {
+                    continue;
+                }
+                if delete_credential(&key)? {
                     removed += 1;
                 }
             }
+            let scope = namespace.map(|ns| format!(" in {ns}/")).unwrap_or_default();
             crate::pout(
                 json,
                 serde_json::json!({"ok": true, "removed": removed}),
-                &format!("Cleared {removed} credentials from keychain"),
+                &format!("Cleared {removed} credentials{scope} from keychain"),
             )
         }
-        SecretsCommands::Doctor => {
-            cmd_secrets_doctor(json)
+        SecretsCommands::Exec { maps, manifest, no_manifest, namespace, no_mask, command } => {
+            let opts = secret_exec::ExecOptions {
+                maps: &maps,
+                manifest: manifest.as_deref(),
+                no_manifest,
+                namespace: namespace.as_deref(),
+                no_mask,
+                command: &command,
+            };
+            secret_exec::cmd_exec(&opts)
+        }
+        SecretsCommands::Export { to, recipient, namespace, force } => {
+            let opts = secret_bundle::ExportOptions { to: &to, recipient: &recipient, namespace: namespace.as_deref(), force };
+            secret_bundle::export(&opts, json)
         }
+        SecretsCommands::Import { bundle, identity, overwrite, dry_run } => {
+            let opts = secret_bundle::ImportOptions { bundle: &bundle, identity: identity.as_deref(), overwrite, dry_run };
+            secret_bundle::import(&opts, json)
+        }
+        SecretsCommands::Rotate { key, confirm, rollback, push_vercel, vercel_env, vercel_targets } => {
+            let opts = secret_rotate::RotateOptions { key: &key, push_vercel, vercel_env: vercel_env.as_deref(), vercel_targets: &vercel_targets };
+            secret_rotate::cmd_rotate(&opts, confirm, rollback, json)
+        }
+        SecretsCommands::Scan { path, history, max_commits, no_vault, report } => {
+            let opts = secret_scan::ScanCommandOptions { path: path.as_deref(), history, max_commits, no_vault, report: report.as_deref() };
+            secret_scan::cmd_scan(&opts, json)
+        }
+        SecretsCommands::Recipient => {
+            let recipient = secret_bundle::passkey_recipient()?;
+            crate::pout(json, serde_json::json!({"recipient": recipient}), &recipient)
+        }
+        SecretsCommands::Vault { command } => vault::cmd_vault(command, json),
+        SecretsCommands::Doctor => cmd_secrets_doctor(json),
     }
 }
 
+fn cmd_secrets_ls(namespace: Option<&str>, tag: Option<&str>, json: bool) -> anyhow::Result<()> {
+    let index = secret_index::load()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut entries = Vec::new();
+    for key in stored_keys()? {
+        if namespace.is_some_and(|ns| !secret_index::in_namespace(&key, ns)) {
+            continue;
+        }
+        let meta = index.get(&key);
+        if tag.is_some_and(|t| !meta.is_some_and(|m| m.tags.iter().any(|x| x == t))) {
+            continue;
+        }
+        let status = match meta.map(|m| m.freshness(now)) {
+            Some(Freshness::Expired(_)) => "expired",
+            Some(Freshness::RotationDue(_)) => "rotation_due",
+            _ => "ok",
+        };
+        entries.push(serde_json::json!({
+            "key": key,
+            "stored": true,
+            "namespace": secret_index::namespace_of(&key),
+            "indexed": meta.is_some(),
+            "meta": meta,
+            "status": status,
+        }));
+    }
+    // Well-known keys that are missing, so `ls` still shows what setup is left.
+    if namespace.is_none() && tag.is_none() {
+        for &key in ALL_KEYS {
+            if !entries.iter().any(|e| e["key"] == key) {
+                entries.push(serde_json::json!({"key": key, "stored": false}));
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let k = entry["key"].as_str().unwrap_or("?");
+        if entry["stored"] != true {
+            println!("  {k:<30} ✗");
+            continue;
+        }
+        let Some(meta) = index.get(k) else {
+            println!("  {k:<30} ✓  (not indexed; re-set it to record metadata)");
+            continue;
+        };
+        let mut notes = vec![format!(
+            "updated {} by {}",
+            secret_index::format_date(meta.updated_at),
+            meta.stored_by.as_deref().unwrap_or("?")
+        )];
+        match meta.freshness(now) {
+            Freshness::Expired(at) => notes.push(format!("EXPIRED {}", secret_index::format_date(at))),
+            Freshness::RotationDue(at) => notes.push(format!("ROTATION DUE since {}", secret_index::format_date(at))),
+            Freshness::Ok => {
+                if let Some(at) = meta.expires_at {
+                    notes.push(format!("expires {}", secret_index::format_date(at)));
+                }
+                if let Some(at) = meta.rotation_due_at() {
+                    notes.push(format!("rotate by {}", secret_index::format_date(at)));
+                }
+            }
+        }
+        if !meta.tags.is_empty() {
+            notes.push(format!("[{}]", meta.tags.join(", ")));
+        }
+        let mark = if entry["status"] == "ok" { "✓" } else { "✗" };
+        println!("  {k:<30} {mark}  {}", notes.join(", "));
+    }
+    Ok(())
+}
+
 const REQUIRED_FOR_DEPLOY: &[(&str, &str)] = &[
     ("database_url", "Supabase DB migrations"),
     ("github_token", "GitHub push / PR / release"),
@@ -243,6 +522,33 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
         .unwrap_or_default()
         .as_secs();
 
+    // Expiry and rotation policy from the secrets index. Expired secrets fail
+    // the vault check; overdue rotations are flagged but don't block.
+    let mut expired: Vec<serde_json::Value> = Vec::new();
+    let mut rotation_due: Vec<serde_json::Value> = Vec::new();
+    let mut stale_index: Vec<String> = Vec::new();
+    for (key, meta) in secret_index::load()? {
+        if load_credential(&key).is_none() {
+            stale_index.push(key);
+            continue;
+        }
+        match meta.freshness(now) {
+            Freshness::Expired(at) => {
+                expired.push(serde_json::json!({"key": key, "expired_at": at}));
+                vault_ok = false;
+            }
+            Freshness::RotationDue(at) => {
+                rotation_due.push(serde_json::json!({"key": key, "due_at": at, "updated_at": meta.updated_at}));
+            }
+            Freshness::Ok => {}
+        }
+    }
+
+    let pending_rotations: Vec<serde_json::Value> = secret_rotate::load_pending()?
+        .into_iter()
+        .map(|(key, p)| serde_json::json!({"key": key, "rotated_at": p.rotated_at, "rotator": p.rotator}))
+        .collect();
+
     let session = auth_session::load_session();
     let session_ok = session.as_ref().is_some_and(|s| s.expires_at > now);
     let session_remaining = session.as_ref()
@@ -254,7 +560,11 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
     let logged_in = identity.is_some();
     let auth_method = identity.as_ref().map(|i| i.auth_method.as_str()).unwrap_or("none");
     let passkey_ok = auth_method == "passkey";
-    let is_founder = identity.as_ref().is_some_and(|i| i.is_founder);
+    let is_founder = identity.as_ref().is_some_and(auth_session::AuthIdentity::is_founder);
+    let founder_unknown = identity.as_ref().and_then(|i| match &i.founder {
+        auth_session::FounderStatus::Unknown(why) => Some(why.clone()),
+        _ => None,
+    });
     let profile = identity.as_ref().map(|i| i.profile.as_str()).unwrap_or("none");
     let subject_email = identity.as_ref().and_then(|i| i.email.as_deref()).unwrap_or("?");
     let subject_id = identity.as_ref().map(|i| i.user_id.as_str()).unwrap_or("?");
@@ -268,7 +578,8 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
     }
     let no_leaks = env_leaks.is_empty();
 
-    let ready_for_infra = vault_ok && session_ok && logged_in && passkey_ok && !is_founder && no_leaks;
+    let ready_for_infra = vault_ok && session_ok && logged_in && passkey_ok && !is_founder
+        && founder_unknown.is_none() && no_leaks;
 
     let auth_report = serde_json::json!({
         "logged_in": logged_in,
@@ -279,6 +590,7 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
         "is_founder": is_founder,
         "passkey_ok": passkey_ok,
         "founder_blocked": is_founder,
+        "founder_status": identity.as_ref().map(|i| &i.founder),
     });
 
     let report = serde_json::json!({
@@ -287,6 +599,10 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
         "session_active": session_ok,
         "auth": auth_report,
         "groups": report_groups,
+        "expired": expired,
+        "rotation_due": rotation_due,
+        "stale_index": stale_index,
+        "pending_rotations": pending_rotations,
         "env_leaks": env_leaks,
         "no_leaks": no_leaks,
     });
@@ -320,6 +636,32 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
         }
     }
 
+    // Expiry / rotation section
+    println!();
+    if expired.is_empty() && rotation_due.is_empty() && stale_index.is_empty() && pending_rotations.is_empty() {
+        println!("✓ rotation: no expired or overdue secrets");
+    } else {
+        println!("✗ rotation:");
+        for e in &expired {
+            let key = e["key"].as_str().unwrap_or("?");
+            let at = secret_index::format_date(e["expired_at"].as_u64().unwrap_or(0));
+            println!("    ✗ {key} expired {at}  <-- logline secrets set {key} --expires <date>");
+        }
+        for r in &rotation_due {
+            let key = r["key"].as_str().unwrap_or("?");
+            let at = secret_index::format_date(r["due_at"].as_u64().unwrap_or(0));
+            println!("    ✗ {key} rotation overdue since {at}  <-- rotate it, then logline secrets set {key}");
+        }
+        for key in &stale_index {
+            println!("    ✗ {key} is indexed but missing from the vault  <-- logline secrets rm {key}");
+        }
+        for p in &pending_rotations {
+            let key = p["key"].as_str().unwrap_or("?");
+            let at = secret_index::format_date(p["rotated_at"].as_u64().unwrap_or(0));
+            println!("    ✗ {key} rotated {at}, not yet confirmed  <-- logline secrets rotate {key} --confirm (or --rollback)");
+        }
+    }
+
     // Session section
     println!();
     if session_ok {
@@ -338,7 +680,7 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
         println!("    logged_in: false");
         println!("    Fix: logline auth login --passkey");
     } else {
-        let auth_mark = if passkey_ok && !is_founder { "✓" } else { "✗" };
+        let auth_mark = if passkey_ok && !is_founder && founder_unknown.is_none() { "✓" } else { "✗" };
         println!("{auth_mark} auth:");
         println!("    logged_in: true");
 
@@ -349,10 +691,13 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
 
         println!("    subject: {subject_email} ({subject_id})");
 
-        let profile_mark = if !is_founder { "✓" } else { "✗" };
+        let profile_mark = if !is_founder && founder_unknown.is_none() { "✓" } else { "✗" };
         println!("    {profile_mark} profile: {profile}{}",
             if is_founder { "  <-- FAIL: founder cannot run infra. Use operator/service account." } else { "" }
         );
+        if let Some(why) = &founder_unknown {
+            println!("      founder status unknown: {why}");
+        }
     }
 
     // Env leak section
@@ -374,11 +719,12 @@ fn cmd_secrets_doctor(json: bool) -> anyhow::Result<()> {
     } else {
         println!("ready_for_infra: false");
         println!();
-        if !vault_ok { println!("  Fix: logline secrets set <key> (see vault above)"); }
+        if !vault_ok { println!("  Fix: logline secrets set <key> (see vault and rotation above)"); }
         if !session_ok { println!("  Fix: logline auth unlock"); }
         if !logged_in { println!("  Fix: logline auth login --passkey"); }
         else if !passkey_ok { println!("  Fix: logline auth login --passkey"); }
         if is_founder { println!("  Fix: log in as operator/service user, not founder"); }
+        if founder_unknown.is_some() { println!("  Fix: reconnect, then logline auth login --passkey to refresh verified claims"); }
         if !no_leaks { println!("  Fix: remove secrets from environment variables (see env section above)"); }
     }
 
@@ -0,0 +1,578 @@
+//! Credential vault backends.
+//!
+//! Everything the CLI keeps secret goes through [`VaultEntry`], addressed like
+//! a keyring item by (service, user). The backend is chosen per machine with
+//! `[auth.vault]` in runtime.toml, or `LOGLINE_VAULT_BACKEND`:
+//!
+//! - `keyring`: the OS keyring (default).
+//! - `file`: one encrypted file in the config dir, for headless hosts and
+//!   containers without a Secret Service daemon. All entries are stored as a
+//!   single JSON document, sealed with ChaCha20-Poly1305 under an Argon2id
+//!   passphrase key, or encrypted to an age X25519 identity. Writes hold an
+//!   exclusive lock on a sibling `.lock` file and re-read the vault under it,
+//!   so concurrent `logline` processes don't overwrite each other's entries.
+//!
+//! `logline secrets vault migrate` copies entries between the two.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{bail, ensure, Context};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use clap::Subcommand;
+use fs2::FileExt;
+use logline_core::{load_vault_config, VaultBackend, VaultConfig, VaultKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{auth_context, presence, secret_index, secrets};
+
+const VAULT_FILE: &str = "vault.enc";
+const VAULT_AAD: &[u8] = b"logline-vault/v1";
+// OWASP minimum for Argon2id: 19 MiB, 2 passes, 1 lane.
+const ARGON2_M_COST: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
+/// service -> user -> secret
+type Entries = BTreeMap<String, BTreeMap<String, String>>;
+
+#[derive(Debug, Subcommand)]
+pub enum VaultCommands {
+    /// Show the vault backend in use on this machine
+    Status,
+    /// Copy all known entries to another backend
+    Migrate {
+        /// Destination backend: keyring | file
+        #[arg(long)]
+        to: String,
+        /// Delete entries from the source once all were copied
+        #[arg(long)]
+        remove_source: bool,
+    },
+}
+
+/// One secret, addressed like a keyring item.
+#[derive(Debug, Clone)]
+pub struct VaultEntry {
+    service: String,
+    user: String,
+}
+
+impl VaultEntry {
+    pub fn new(service: &str, user: &str) -> Self {
+        Self { service: service.to_string(), user: user.to_string() }
+    }
+
+    pub fn get(&self) -> Option<String> {
+        backend_get(active_backend(), &self.service, &self.user)
+    }
+
+    /// Like [`get`](Self::get), but only a missing entry is `None`; a locked
+    /// keychain or an unreadable vault is an error.
+    pub fn try_get(&self) -> anyhow::Result<Option<String>> {
+        backend_try_get(active_backend(), &self.service, &self.user)
+    }
+
+    pub fn set(&self, value: &str) -> anyhow::Result<()> {
+        backend_set(active_backend(), &self.service, &self.user, value)
+    }
+
+    /// Remove the entry. Returns whether it existed.
+    pub fn delete(&self) -> anyhow::Result<bool> {
+        backend_delete(active_backend(), &self.service, &self.user)
+    }
+}
+
+fn vault_config() -> VaultConfig {
+    load_vault_config(&presence::config_dir()).unwrap_or_default()
+}
+
+fn parse_backend(s: &str) -> anyhow::Result<VaultBackend> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "keyring" => Ok(VaultBackend::Keyring),
+        "file" => Ok(VaultBackend::File),
+        other => bail!("Unknown vault backend '{other}'. Use keyring or file."),
+    }
+}
+
+fn key_name(k: VaultKey) -> &'static str {
+    match k {
+        VaultKey::Passphrase => "passphrase",
+        VaultKey::Age => "age",
+    }
+}
+
+fn backend_name(b: VaultBackend) -> &'static str {
+    match b {
+        VaultBackend::Keyring => "keyring",
+        VaultBackend::File => "file",
+    }
+}
+
+/// Backend for this machine: `LOGLINE_VAULT_BACKEND`, else `[auth.vault]`.
+pub fn active_backend() -> VaultBackend {
+    static ACTIVE: OnceLock<VaultBackend> = OnceLock::new();
+    *ACTIVE.get_or_init(|| {
+        std::env::var("LOGLINE_VAULT_BACKEND")
+            .ok()
+            .and_then(|v| parse_backend(&v).ok())
+            .unwrap_or_else(|| vault_config().backend)
+    })
+}
+
+fn backend_get(b: VaultBackend, service: &str, user: &str) -> Option<String> {
+    match b {
+        VaultBackend::Keyring => keyring::Entry::new(service, user).ok()?.get_password().ok(),
+        VaultBackend::File => with_file_vault(|v| Ok(v.entries.get(service).and_then(|m| m.get(user)).cloned()))
+            .unwrap_or_else(|e| {
+                static REPORTED: std::sync::Once = std::sync::Once::new();
+                REPORTED.call_once(|| eprintln!("Vault error: {e:#}"));
+                None
+            }),
+    }
+}
+
+fn backend_try_get(b: VaultBackend, service: &str, user: &str) -> anyhow::Result<Option<String>> {
+    match b {
+        VaultBackend::Keyring => {
+            let entry = keyring::Entry::new(service, user).map_err(|e| anyhow::anyhow!("Keyring error: {e}"))?;
+            match entry.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => bail!("Cannot read '{user}' from the keyring: {e}"),
+            }
+        }
+        VaultBackend::File => with_file_vault(|v| Ok(v.entries.get(service).and_then(|m| m.get(user)).cloned())),
+    }
+}
+
+fn backend_set(b: VaultBackend, service: &str, user: &str, value: &str) -> anyhow::Result<()> {
+    match b {
+        VaultBackend::Keyring => {
+            let entry = keyring::Entry::new(service, user).map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
+            entry
+                .set_password(value)
+                .map_err(|e| anyhow::anyhow!("Failed to store '{user}' in keychain: {e}"))
+        }
+        VaultBackend::File => with_file_vault(|v| {
+            v.update(|entries| {
+                entries
+                    .entry(service.to_string())
+                    .or_default()
+                    .insert(user.to_string(), value.to_string());
+                ((), true)
+            })
+        }),
+    }
+}
+
+fn backend_delete(b: VaultBackend, service: &str, user: &str) -> anyhow::Result<bool> {
+    match b {
+        VaultBackend::Keyring => {
+            let entry = keyring::Entry::new(service, user).map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
+            match entry.delete_credential() {
+                Ok(()) => Ok(true),
+                Err(keyring::Error::NoEntry) => Ok(false),
+                Err(e) => bail!("Failed to delete '{user}': {e}"),
+            }
+        }
+        VaultBackend::File => with_file_vault(|v| {
+            v.update(|entries| {
+                let removed = entries.get_mut(service).and_then(|m| m.remove(user)).is_some();
+                entries.retain(|_, m| !m.is_empty());
+                (removed, removed)
+            })
+        }),
+    }
+}
+
+// ─── File vault ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    v: u8,
+    /// `argon2id` or `age`.
+    kdf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<[u32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    ct: String,
+}
+
+enum SealKey {
+    Passphrase { key: [u8; 32], salt: [u8; 16] },
+    Age(age::x25519::Identity),
+}
+
+struct FileVault {
+    path: PathBuf,
+    /// `None` until the first write when the vault file does not exist yet.
+    key: Option<SealKey>,
+    entries: Entries,
+}
+
+/// Open the file vault once per process (one passphrase prompt) and run `f`.
+/// A failed unlock is remembered so later lookups don't prompt again.
+fn with_file_vault<R>(f: impl FnOnce(&mut FileVault) -> anyhow::Result<R>) -> anyhow::Result<R> {
+    static VAULT: Mutex<Option<Result<FileVault, String>>> = Mutex::new(None);
+    let mut guard = VAULT.lock().map_err(|_| anyhow::anyhow!("vault lock poisoned"))?;
+    let vault = guard.get_or_insert_with(|| FileVault::open(&vault_config()).map_err(|e| format!("{e:#}")));
+    match vault {
+        Ok(v) => f(v),
+        Err(e) => bail!("{e}"),
+    }
+}
+
+fn expand_home(p: &str) -> PathBuf {
+    match (p.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(p),
+    }
+}
+
+fn vault_path(cfg: &VaultConfig) -> PathBuf {
+    cfg.path
+        .as_deref()
+        .map_or_else(|| presence::config_dir().join(VAULT_FILE), expand_home)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: [u32; 3]) -> anyhow::Result<[u8; 32]> {
+    let [m, t, p] = params;
+    let params = argon2::Params::new(m, t, p, Some(32)).map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {e}"))?;
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0u8; 32];
+    argon
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {e}"))?;
+    Ok(key)
+}
+
+fn read_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(p) = std::env::var("LOGLINE_VAULT_PASSPHRASE") {
+        if !p.is_empty() {
+            return Ok(p);
+        }
+    }
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "File vault is locked and there is no terminal to prompt on.\n\
+         Set LOGLINE_VAULT_PASSPHRASE, or use an age identity ([auth.vault] key = \"age\")."
+    );
+    let pass = rpassword::prompt_password("Vault passphrase: ")?;
+    ensure!(!pass.is_empty(), "Passphrase cannot be empty");
+    if confirm {
+        let again = rpassword::prompt_password("Repeat passphrase: ")?;
+        ensure!(pass == again, "Passphrases do not match");
+    }
+    Ok(pass)
+}
+
+fn load_age_identity(cfg: &VaultConfig) -> anyhow::Result<age::x25519::Identity> {
+    let path = std::env::var("LOGLINE_VAULT_AGE_IDENTITY")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .or_else(|| cfg.age_identity.clone())
+        .map(|p| expand_home(&p))
+        .ok_or_else(|| anyhow::anyhow!(
+            "[auth.vault] key = \"age\" needs age_identity (or LOGLINE_VAULT_AGE_IDENTITY).\n\
+             Create one with: age-keygen -o ~/.config/logline/vault.age"
+        ))?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read age identity {}", path.display()))?;
+    content
+        .lines()
+        .map(str::trim)
+        .find(|l| l.starts_with("AGE-SECRET-KEY-"))
+        .ok_or_else(|| anyhow::anyhow!("No AGE-SECRET-KEY found in {}", path.display()))?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid age identity in {}: {e}", path.display()))
+}
+
+fn read_envelope(path: &Path) -> anyhow::Result<Option<Envelope>> {
+    match fs::read_to_string(path) {
+        Ok(c) => Ok(Some(serde_json::from_str(&c).with_context(|| format!("Corrupt vault file {}", path.display()))?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => bail!("Failed to read {}: {e}", path.display()),
+    }
+}
+
+/// Decrypt `env`, reusing `cached` while it still fits (same salt, or an age
+/// identity), so re-reading under the write lock doesn't prompt again.
+fn unseal(env: &Envelope, cached: Option<&SealKey>, cfg: &VaultConfig) -> anyhow::Result<(SealKey, Entries)> {
+    ensure!(env.v == 1, "Unsupported vault version {}", env.v);
+    let ct = B64.decode(&env.ct).context("Corrupt vault ciphertext")?;
+    let (key, plaintext) = match env.kdf.as_str() {
+        "argon2id" => {
+            let salt: [u8; 16] = env
+                .salt
+                .as_deref()
+                .and_then(|s| B64.decode(s).ok())
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| anyhow::anyhow!("Corrupt vault salt"))?;
+            let nonce: [u8; 12] = env
+                .nonce
+                .as_deref()
+                .and_then(|s| B64.decode(s).ok())
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| anyhow::anyhow!("Corrupt vault nonce"))?;
+            let params = env.params.ok_or_else(|| anyhow::anyhow!("Corrupt vault parameters"))?;
+            let key = match cached {
+                Some(SealKey::Passphrase { key, salt: cached_salt }) if *cached_salt == salt => *key,
+                _ => derive_key(&read_passphrase(false)?, &salt, params)?,
+            };
+            let plaintext = ChaCha20Poly1305::new(&Key::from(key))
+                .decrypt(&Nonce::from(nonce), Payload { msg: &ct, aad: VAULT_AAD })
+                .map_err(|_| anyhow::anyhow!("Wrong vault passphrase, or the vault file was modified"))?;
+            (SealKey::Passphrase { key, salt }, plaintext)
+        }
+        "age" => {
+            let identity = match cached {
+                Some(SealKey::Age(identity)) => identity.clone(),
+                _ => load_age_identity(cfg)?,
+            };
+            let plaintext = age::decrypt(&identity, &ct)
+                .map_err(|e| anyhow::anyhow!("Cannot decrypt vault with this age identity: {e}"))?;
+            (SealKey::Age(identity), plaintext)
+        }
+        other => bail!("Unsupported vault kdf '{other}'"),
+    };
+    let entries = serde_json::from_slice(&plaintext).context("Corrupt vault contents")?;
+    Ok((key, entries))
+}
+
+impl FileVault {
+    fn open(cfg: &VaultConfig) -> anyhow::Result<Self> {
+        let path = vault_path(cfg);
+        let Some(env) = read_envelope(&path)? else {
+            return Ok(Self { path, key: None, entries: Entries::new() });
+        };
+        let (key, entries) = unseal(&env, None, cfg)?;
+        Ok(Self { path, key: Some(key), entries })
+    }
+
+    /// Apply `f` to the entries as they are on disk now, holding an exclusive
+    /// lock until the result is written. `f` returns whether anything changed.
+    fn update<R>(&mut self, f: impl FnOnce(&mut Entries) -> (R, bool)) -> anyhow::Result<R> {
+        let lock_path = self.path.with_extension("lock");
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        lock.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        if let Some(env) = read_envelope(&self.path)? {
+            let (key, entries) = unseal(&env, self.key.as_ref(), &vault_config())?;
+            self.key = Some(key);
+            self.entries = entries;
+        }
+        let (result, changed) = f(&mut self.entries);
+        if changed {
+            self.save()?;
+        }
+        // Closing the lock file releases the lock.
+        drop(lock);
+        Ok(result)
+    }
+
+    /// Key for a vault that is being written for the first time.
+    fn new_key(&self) -> anyhow::Result<SealKey> {
+        let cfg = vault_config();
+        Ok(match cfg.key {
+            VaultKey::Passphrase => {
+                eprintln!("Creating file vault at {}", self.path.display());
+                let mut salt = [0u8; 16];
+                rand::rngs::OsRng.fill_bytes(&mut salt);
+                let pass = read_passphrase(true)?;
+                let key = derive_key(&pass, &salt, [ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST])?;
+                SealKey::Passphrase { key, salt }
+            }
+            VaultKey::Age => SealKey::Age(load_age_identity(&cfg)?),
+        })
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        if self.key.is_none() {
+            self.key = Some(self.new_key()?);
+        }
+        let plaintext = serde_json::to_vec(&self.entries)?;
+        let env = match self.key.as_ref().expect("key set above") {
+            SealKey::Passphrase { key, salt } => {
+                let mut nonce = [0u8; 12];
+                rand::rngs::OsRng.fill_bytes(&mut nonce);
+                let ct = ChaCha20Poly1305::new(&Key::from(*key))
+                    .encrypt(&Nonce::from(nonce), Payload { msg: &plaintext, aad: VAULT_AAD })
+                    .map_err(|_| anyhow::anyhow!("Vault encryption failed"))?;
+                Envelope {
+                    v: 1,
+                    kdf: "argon2id".into(),
+                    salt: Some(B64.encode(salt)),
+                    params: Some([ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST]),
+                    nonce: Some(B64.encode(nonce)),
+                    ct: B64.encode(ct),
+                }
+            }
+            SealKey::Age(identity) => {
+                let ct = age::encrypt(&identity.to_public(), &plaintext)
+                    .map_err(|e| anyhow::anyhow!("Vault encryption failed: {e}"))?;
+                Envelope { v: 1, kdf: "age".into(), salt: None, params: None, nonce: None, ct: B64.encode(ct) }
+            }
+        };
+        write_atomic(&self.path, serde_json::to_string_pretty(&env)?.as_bytes())
+    }
+}
+
+/// Replace `path` with `body` (owner-only), so a crash never leaves half a vault.
+fn write_atomic(path: &Path, body: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Unique per writer, so concurrent writers never share a temp file.
+    let tmp = path.with_extension(format!("tmp.{}.{:08x}", std::process::id(), rand::rngs::OsRng.next_u32()));
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts
+        .open(&tmp)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    file.write_all(body)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+// ─── Migration ──────────────────────────────────────────────────────────────
+
+/// Entries that may exist. The keyring cannot be enumerated, so this is the
+/// known credential keys plus the per-context auth entries of every context.
+fn known_entries() -> anyhow::Result<Vec<(String, String)>> {
+    let mut out = Vec::new();
+    for service in auth_context::all_services() {
+        for user in auth_context::CONTEXT_KEYRING_USERS {
+            out.push((service.clone(), (*user).to_string()));
+        }
+    }
+    let default = auth_context::all_services().remove(0);
+    for user in secrets::ALL_KEYS.iter().chain(secrets::INTERNAL_KEYS) {
+        out.push((default.clone(), (*user).to_string()));
+    }
+    let index = backend_get(VaultBackend::Keyring, &default, secret_index::INDEX_KEY)
+        .map(|json| secret_index::parse(&json))
+        .transpose()?
+        .unwrap_or_default();
+    for user in index.into_keys() {
+        if !out.iter().any(|(s, u)| *s == default && *u == user) {
+            out.push((default.clone(), user));
+        }
+    }
+    Ok(out)
+}
+
+fn source_entries(from: VaultBackend) -> anyhow::Result<Vec<(String, String, String)>> {
+    match from {
+        VaultBackend::File => with_file_vault(|v| {
+            Ok(v.entries
+                .iter()
+                .flat_map(|(s, m)| m.iter().map(move |(u, val)| (s.clone(), u.clone(), val.clone())))
+                .collect())
+        }),
+        VaultBackend::Keyring => Ok(known_entries()?
+            .into_iter()
+            .filter_map(|(s, u)| backend_get(VaultBackend::Keyring, &s, &u).map(|v| (s, u, v)))
+            .collect()),
+    }
+}
+
+pub fn cmd_vault(command: VaultCommands, json: bool) -> anyhow::Result<()> {
+    match command {
+        VaultCommands::Status => {
+            let cfg = vault_config();
+            let backend = active_backend();
+            let path = vault_path(&cfg);
+            crate::pout(
+                json,
+                serde_json::json!({
+                    "backend": backend_name(backend),
+                    "key": key_name(cfg.key),
+                    "path": path,
+                    "file_exists": path.exists(),
+                }),
+                &format!(
+                    "Vault backend: {}{}",
+                    backend_name(backend),
+                    if backend == VaultBackend::File {
+                        format!(" ({}, key: {})", path.display(), key_name(cfg.key))
+                    } else {
+                        String::new()
+                    }
+                ),
+            )
+        }
+        VaultCommands::Migrate { to, remove_source } => {
+            let to = parse_backend(&to)?;
+            let from = match to {
+                VaultBackend::File => VaultBackend::Keyring,
+                VaultBackend::Keyring => VaultBackend::File,
+            };
+
+            let entries = source_entries(from)?;
+            let mut moved = Vec::new();
+            for (service, user, value) in &entries {
+                backend_set(to, service, user, value)?;
+                ensure!(
+                    backend_get(to, service, user).as_deref() == Some(value.as_str()),
+                    "Read-back of {service}/{user} from {} failed; source left untouched.",
+                    backend_name(to)
+                );
+                moved.push(format!("{service}/{user}"));
+            }
+            if remove_source {
+                for (service, user, _) in &entries {
+                    backend_delete(from, service, user)?;
+                }
+            }
+
+            let hint = format!(
+                "Use it on this machine with `[auth.vault] backend = \"{}\"` in runtime.toml (or LOGLINE_VAULT_BACKEND={}).",
+                backend_name(to),
+                backend_name(to)
+            );
+            crate::pout(
+                json,
+                serde_json::json!({
+                    "ok": true,
+                    "from": backend_name(from),
+                    "to": backend_name(to),
+                    "migrated": moved,
+                    "source_removed": remove_source,
+                }),
+                &format!(
+                    "✓ Migrated {} entr{} {} → {}{}\n{hint}",
+                    moved.len(),
+                    if moved.len() == 1 { "y" } else { "ies" },
+                    backend_name(from),
+                    backend_name(to),
+                    if remove_source { " (source removed)" } else { "" }
+                ),
+            )
+        }
+    }
+}
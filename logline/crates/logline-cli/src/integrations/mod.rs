@@ -1,3 +1,4 @@
 pub mod github;
+pub mod pg;
 pub mod vercel;
 pub mod supabase_migrate;
@@ -0,0 +1,481 @@
+//! Native Postgres client for `logline db`.
+//!
+//! Connections are made in-process, so the database URL (and its password)
+//! never appears on a command line. Results come back as typed rows and
+//! queries take bound parameters.
+//!
+//! TLS follows libpq's `sslmode`: `disable` connects in plain text; `prefer`
+//! (the default) and `require` encrypt without checking the certificate, as
+//! psql does; `verify-ca` checks the chain and `verify-full` also the host
+//! name, against the Mozilla roots or the file named by `sslrootcert`.
+
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use postgres::error::SqlState;
+use postgres::types::{FromSql, Kind, Type};
+use postgres::{Client, GenericClient};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Seconds from the Unix epoch to the Postgres epoch (2000-01-01).
+const PG_EPOCH_OFFSET: i64 = 946_684_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CertCheck {
+    None,
+    Chain,
+    ChainAndName,
+}
+
+/// Split libpq TLS options out of the URL; tokio-postgres only understands
+/// `disable`/`prefer`/`require` and rejects `sslrootcert`.
+fn split_tls_options(url: &str) -> anyhow::Result<(String, CertCheck, Option<String>)> {
+    let Some((base, query)) = url.split_once('?') else {
+        return Ok((url.to_string(), CertCheck::None, None));
+    };
+    let mut check = CertCheck::None;
+    let mut root_cert = None;
+    let mut rest = Vec::new();
+    for param in query.split('&').filter(|p| !p.is_empty()) {
+        match param.split_once('=') {
+            Some(("sslmode", mode)) => {
+                let (mode, c) = match mode {
+                    "disable" | "prefer" | "require" => (mode, CertCheck::None),
+                    "allow" => ("prefer", CertCheck::None),
+                    "verify-ca" => ("require", CertCheck::Chain),
+                    "verify-full" => ("require", CertCheck::ChainAndName),
+                    other => bail!("Unsupported sslmode '{other}' in the database URL"),
+                };
+                check = c;
+                rest.push(format!("sslmode={mode}"));
+            }
+            Some(("sslrootcert", path)) => root_cert = Some(path.to_string()),
+            _ => rest.push(param.to_string()),
+        }
+    }
+    // libpq treats a root certificate under `require` as `verify-ca`.
+    if root_cert.is_some() && check == CertCheck::None {
+        check = CertCheck::Chain;
+    }
+    let url = if rest.is_empty() { base.to_string() } else { format!("{base}?{}", rest.join("&")) };
+    Ok((url, check, root_cert))
+}
+
+/// Certificate policy for one `sslmode`. Handshake signatures are always verified.
+#[derive(Debug)]
+struct CertPolicy {
+    webpki: Option<Arc<dyn ServerCertVerifier>>,
+    check_name: bool,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for CertPolicy {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let Some(webpki) = &self.webpki else {
+            return Ok(ServerCertVerified::assertion());
+        };
+        match webpki.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now) {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) if !self.check_name => Ok(ServerCertVerified::assertion()),
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+fn tls_config(check: CertCheck, root_cert: Option<&str>) -> anyhow::Result<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let webpki: Option<Arc<dyn ServerCertVerifier>> = if check == CertCheck::None {
+        None
+    } else {
+        let mut roots = RootCertStore::empty();
+        match root_cert {
+            Some(path) => {
+                for cert in CertificateDer::pem_file_iter(path).with_context(|| format!("Failed to read sslrootcert {path}"))? {
+                    roots
+                        .add(cert.with_context(|| format!("Invalid certificate in {path}"))?)
+                        .with_context(|| format!("Invalid certificate in {path}"))?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        Some(
+            rustls::client::WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .context("Failed to set up TLS certificate verification")?,
+        )
+    };
+    let policy = CertPolicy {
+        webpki,
+        check_name: check == CertCheck::ChainAndName,
+        algorithms: provider.signature_verification_algorithms,
+    };
+    Ok(rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(policy))
+        .with_no_client_auth())
+}
+
+/// Connect to `url` (a `postgres://` URL).
+pub fn connect(url: &str) -> anyhow::Result<Client> {
+    let (url, check, root_cert) = split_tls_options(url)?;
+    // Parse errors can echo the URL back; keep the password out of them.
+    let mut config: postgres::Config = url
+        .parse()
+        .map_err(|e: postgres::Error| anyhow::anyhow!("Invalid database URL: {}", describe_error(&e).replace(&url, "<url>")))?;
+    config.connect_timeout(CONNECT_TIMEOUT);
+    if config.get_application_name().is_none() {
+        config.application_name("logline");
+    }
+    let tls = tokio_postgres_rustls::MakeRustlsConnect::new(tls_config(check, root_cert.as_deref())?);
+    config
+        .connect(tls)
+        .map_err(|e| anyhow::anyhow!("Failed to connect to the database: {}", describe_error(&e)))
+}
+
+/// The server and database `url` points at, as `host:port/dbname`, without
+/// credentials or options. Multiple hosts are comma-separated.
+pub fn target(url: &str) -> anyhow::Result<String> {
+    let (url, _, _) = split_tls_options(url)?;
+    let config: postgres::Config = url
+        .parse()
+        .map_err(|e: postgres::Error| anyhow::anyhow!("Invalid database URL: {}", describe_error(&e).replace(&url, "<url>")))?;
+    let ports = config.get_ports();
+    let hosts: Vec<String> = config
+        .get_hosts()
+        .iter()
+        .enumerate()
+        .map(|(i, host)| {
+            let host = match host {
+                postgres::config::Host::Tcp(name) => name.to_ascii_lowercase(),
+                postgres::config::Host::Unix(path) => path.display().to_string(),
+            };
+            // One port applies to every host; otherwise they pair up.
+            let port = ports.get(i).or_else(|| ports.first()).copied().unwrap_or(5432);
+            format!("{host}:{port}")
+        })
+        .collect();
+    let dbname = config.get_dbname().or_else(|| config.get_user()).unwrap_or_default();
+    Ok(format!("{}/{dbname}", hosts.join(",")))
+}
+
+/// Connect and run `select 1`.
+pub fn ping(url: &str) -> anyhow::Result<()> {
+    connect(url)?
+        .simple_query("select 1")
+        .map_err(|e| anyhow::anyhow!("{}", describe_error(&e)))?;
+    Ok(())
+}
+
+/// Server message with its detail and hint, or the client-side error and its causes.
+pub fn describe_error(e: &postgres::Error) -> String {
+    let Some(db) = e.as_db_error() else {
+        let mut parts = vec![e.to_string()];
+        let mut source = std::error::Error::source(e);
+        while let Some(cause) = source {
+            parts.push(cause.to_string());
+            source = cause.source();
+        }
+        return parts.join(": ");
+    };
+    let mut lines = vec![format!("{}: {}", db.severity(), db.message())];
+    lines.extend(db.detail().map(|d| format!("DETAIL: {d}")));
+    lines.extend(db.hint().map(|h| format!("HINT: {h}")));
+    lines.join("\n")
+}
+
+pub fn is_read_only_violation(e: &postgres::Error) -> bool {
+    e.code() == Some(&SqlState::READ_ONLY_SQL_TRANSACTION)
+}
+
+/// Rows with their column names; values are already JSON. Statements that
+/// return no columns report the number of rows they affected instead.
+#[derive(Debug, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    pub affected: Option<u64>,
+}
+
+impl QueryResult {
+    /// One JSON object per row, keyed by column name.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .cloned()
+                    .zip(row.iter().cloned())
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect()
+    }
+
+    /// An aligned table in psql's style, with a row count footer.
+    pub fn to_table(&self) -> String {
+        if let Some(n) = self.affected {
+            return format!("({n} row{} affected)", if n == 1 { "" } else { "s" });
+        }
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(display_value).collect())
+            .collect();
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.chars().count()).collect();
+        for row in &cells {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let line = |values: &[String]| {
+            values
+                .iter()
+                .zip(&widths)
+                .map(|(v, &w)| format!(" {v:<w$} "))
+                .collect::<Vec<_>>()
+                .join("|")
+                .trim_end()
+                .to_string()
+        };
+        let mut out = vec![
+            line(&self.columns),
+            widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+"),
+        ];
+        out.extend(cells.iter().map(|row| line(row)));
+        out.push(format!("({} row{})", self.rows.len(), if self.rows.len() == 1 { "" } else { "s" }));
+        out.join("\n")
+    }
+}
+
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Run one statement with bound parameters and collect typed rows.
+pub fn query(
+    client: &mut impl GenericClient,
+    sql: &str,
+    params: &[&(dyn postgres::types::ToSql + Sync)],
+) -> Result<QueryResult, postgres::Error> {
+    let statement = client.prepare(sql)?;
+    if statement.columns().is_empty() {
+        let affected = client.execute(&statement, params)?;
+        return Ok(QueryResult { affected: Some(affected), ..QueryResult::default() });
+    }
+    let columns = statement.columns().iter().map(|c| c.name().to_string()).collect();
+    let rows = client
+        .query(&statement, params)?
+        .iter()
+        .map(|row| (0..row.len()).map(|i| row.try_get::<_, Cell>(i).map(|c| c.0)).collect())
+        .collect::<Result<_, _>>()?;
+    Ok(QueryResult { columns, rows, affected: None })
+}
+
+/// Any column value as JSON. Types without a natural JSON form come back as
+/// strings; those with no decoder here show as `<type>` (cast them to text).
+struct Cell(serde_json::Value);
+
+type DecodeResult<T> = Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+impl<'a> FromSql<'a> for Cell {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> DecodeResult<Self> {
+        use serde_json::Value;
+        let value = match *ty {
+            Type::BOOL => Value::Bool(bool::from_sql(ty, raw)?),
+            Type::INT2 => i16::from_sql(ty, raw)?.into(),
+            Type::INT4 => i32::from_sql(ty, raw)?.into(),
+            Type::INT8 => i64::from_sql(ty, raw)?.into(),
+            Type::OID => u32::from_sql(ty, raw)?.into(),
+            Type::FLOAT4 => float(f64::from(f32::from_sql(ty, raw)?)),
+            Type::FLOAT8 => float(f64::from_sql(ty, raw)?),
+            Type::NUMERIC => {
+                let text = numeric_to_string(raw)?;
+                match text.parse::<serde_json::Number>() {
+                    Ok(n) if n.to_string() == text => Value::Number(n),
+                    _ => Value::String(text),
+                }
+            }
+            Type::JSON | Type::JSONB => Value::from_sql(ty, raw)?,
+            Type::UUID => {
+                let hex = hex::encode(raw);
+                ensure_len(raw, 16, "uuid")?;
+                Value::String(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+            }
+            Type::BYTEA => Value::String(format!("\\x{}", hex::encode(raw))),
+            Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+                let micros = i64::from_sql(ty, raw)?;
+                Value::String(format_timestamp(micros, *ty == Type::TIMESTAMPTZ))
+            }
+            Type::DATE => Value::String(format_date(i32::from_sql(ty, raw)?)),
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::XML => {
+                Value::String(String::from_utf8_lossy(raw).into_owned())
+            }
+            _ => match ty.kind() {
+                Kind::Array(_) => Value::Array(Vec::<Cell>::from_sql(ty, raw)?.into_iter().map(|c| c.0).collect()),
+                Kind::Domain(base) => Cell::from_sql(base, raw)?.0,
+                // Enums and citext travel as text.
+                Kind::Enum(_) => Value::String(String::from_utf8_lossy(raw).into_owned()),
+                _ if ty.name() == "citext" => Value::String(String::from_utf8_lossy(raw).into_owned()),
+                _ => Value::String(format!("<{}>", ty.name())),
+            },
+        };
+        Ok(Cell(value))
+    }
+
+    fn from_sql_null(_: &Type) -> DecodeResult<Self> {
+        Ok(Cell(serde_json::Value::Null))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+fn ensure_len(raw: &[u8], len: usize, what: &str) -> DecodeResult<()> {
+    if raw.len() == len {
+        Ok(())
+    } else {
+        Err(format!("invalid {what} value").into())
+    }
+}
+
+fn float(f: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(f).map_or_else(|| serde_json::Value::String(f.to_string()), serde_json::Value::Number)
+}
+
+/// Decode the binary `numeric` format: base-10000 digits with a weight and scale.
+fn numeric_to_string(raw: &[u8]) -> DecodeResult<String> {
+    let word = |i: usize| -> DecodeResult<u16> {
+        raw.get(i * 2..i * 2 + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| "invalid numeric value".into())
+    };
+    let ndigits = usize::from(word(0)?);
+    let weight = i32::from(i16::from_be_bytes(word(1)?.to_be_bytes()));
+    let sign = word(2)?;
+    let scale = usize::from(word(3)?);
+    match sign {
+        0xC000 => return Ok("NaN".into()),
+        0xD000 => return Ok("Infinity".into()),
+        0xF000 => return Ok("-Infinity".into()),
+        _ => {}
+    }
+    let digits = (0..ndigits).map(|i| word(4 + i)).collect::<DecodeResult<Vec<u16>>>()?;
+    let digit = |pos: i32| usize::try_from(pos).ok().and_then(|p| digits.get(p)).copied().unwrap_or(0);
+
+    let mut out = String::new();
+    if sign == 0x4000 {
+        out.push('-');
+    }
+    if weight < 0 {
+        out.push('0');
+    } else {
+        out.push_str(&digit(0).to_string());
+        for pos in 1..=weight {
+            write!(out, "{:04}", digit(pos))?;
+        }
+    }
+    if scale > 0 {
+        let groups = i32::try_from(scale.div_ceil(4))?;
+        let mut fraction = String::new();
+        for pos in weight + 1..weight + 1 + groups {
+            write!(fraction, "{:04}", digit(pos))?;
+        }
+        fraction.truncate(scale);
+        out.push('.');
+        out.push_str(&fraction);
+    }
+    Ok(out)
+}
+
+/// Proleptic Gregorian (year, month, day) for days since 1970-01-01, either
+/// side of the epoch. Year 0 is 1 BC, as in ISO 8601.
+fn civil_from_days(days_since_unix: i64) -> (i64, i64, i64) {
+    let z = days_since_unix + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// `YYYY-MM-DD` and the era suffix Postgres prints: ` BC` before year 1.
+fn ymd(days_since_unix: i64) -> (String, &'static str) {
+    let (year, month, day) = civil_from_days(days_since_unix);
+    let (year, era) = if year <= 0 { (1 - year, " BC") } else { (year, "") };
+    (format!("{year:04}-{month:02}-{day:02}"), era)
+}
+
+/// ISO 8601 for microseconds since 2000-01-01.
+fn format_timestamp(micros: i64, utc: bool) -> String {
+    match micros {
+        i64::MAX => return "infinity".into(),
+        i64::MIN => return "-infinity".into(),
+        _ => {}
+    }
+    let secs = micros.div_euclid(1_000_000) + PG_EPOCH_OFFSET;
+    let frac = micros.rem_euclid(1_000_000);
+    let (date, era) = ymd(secs.div_euclid(86400));
+    let rem = secs.rem_euclid(86400);
+    let (hour, min, sec) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+    let frac = if frac == 0 { String::new() } else { format!(".{frac:06}").trim_end_matches('0').to_string() };
+    let zone = if utc { "Z" } else { "" };
+    format!("{date}T{hour:02}:{min:02}:{sec:02}{frac}{zone}{era}")
+}
+
+/// `YYYY-MM-DD` for days since 2000-01-01.
+fn format_date(days: i32) -> String {
+    match days {
+        i32::MAX => return "infinity".into(),
+        i32::MIN => return "-infinity".into(),
+        _ => {}
+    }
+    let (date, era) = ymd(i64::from(days) + PG_EPOCH_OFFSET / 86400);
+    format!("{date}{era}")
+}
@@ -6,6 +6,7 @@ pub fn migrate_up(env: &str, json: bool) -> anyhow::Result<()> {
         DbCommands::Migrate {
             command: MigrateCommands::Up {
                 env: env.to_string(),
+                accept_drift: false,
             },
         },
         json,
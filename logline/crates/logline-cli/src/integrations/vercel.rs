@@ -104,6 +104,33 @@ pub fn set_env_var(key: &str, value: &str, target: &[&str]) -> anyhow::Result<()
     }
 }
 
+/// Create or replace an environment variable on Vercel.
+pub fn upsert_env_var(key: &str, value: &str, target: &[&str]) -> anyhow::Result<()> {
+    let (client, token, _org_id, project_id) = vercel_client()?;
+
+    let url = format!("https://api.vercel.com/v10/projects/{project_id}/env?upsert=true");
+
+    let resp = client
+        .post(&url)
+        .bearer_auth(&token)
+        .header("User-Agent", "logline-cli")
+        .json(&serde_json::json!({
+            "key": key,
+            "value": value,
+            "target": target,
+            "type": "encrypted",
+        }))
+        .send()?;
+
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        let status = resp.status();
+        let text = resp.text().unwrap_or_default();
+        bail!("Vercel env upsert failed ({status}): {text}")
+    }
+}
+
 /// Sync env vars from a manifest file (vercel.env.json) to Vercel.
 pub fn sync_env() -> anyhow::Result<serde_json::Value> {
     let manifest_path = std::env::current_dir()
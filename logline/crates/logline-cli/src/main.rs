@@ -14,16 +14,20 @@ use logline_core::{
 };
 use logline_runtime::LoglineRuntime;
 
+use crate::commands::auth_context;
+use crate::commands::auth_device;
 use crate::commands::auth_session;
 use crate::commands::cicd;
 use crate::commands::db;
 use crate::commands::deploy;
 use crate::commands::dev;
+use crate::commands::passkey;
+use crate::commands::presence;
 use crate::commands::secrets;
 use crate::supabase::{
     SupabaseClient, SupabaseConfig, StoredAuth,
     get_valid_token, load_auth, save_auth, delete_auth,
-    load_passkey, save_passkey,
+    load_passkey,
 };
 
 #[derive(Debug, Parser)]
@@ -35,6 +39,10 @@ struct Cli {
     #[arg(long, global = true)]
     config_dir: Option<PathBuf>,
 
+    /// Auth context to use (default: the one set with `auth context use`, or `LOGLINE_CONTEXT`)
+    #[arg(long, global = true)]
+    context: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -95,7 +103,7 @@ enum Commands {
         #[command(subcommand)]
         command: SupabaseCommands,
     },
-    /// Credential vault — store/retrieve secrets in macOS Keychain
+    /// Credential vault — store/retrieve secrets in the OS keyring or an encrypted file vault
     Secrets {
         #[command(subcommand)]
         command: secrets::SecretsCommands,
@@ -142,35 +150,63 @@ enum BackendCommands {
 
 #[derive(Debug, Subcommand)]
 enum AuthCommands {
-    /// Unlock session with Touch ID (required before any privileged command)
+    /// Unlock session after a user-presence check (required before any privileged command)
     Unlock {
         /// Session TTL (e.g. "5m", "30m", "2h"). Default: 30m
         #[arg(long, default_value = "30m")]
         ttl: String,
+        /// Scopes to unlock, comma-separated (e.g. "secrets:read,db:read"). Default: all
+        #[arg(long, value_delimiter = ',')]
+        scope: Vec<String>,
     },
     /// Lock session immediately (revoke access)
     Lock,
-    /// Show session status and remaining TTL
+    /// Show session status and the time left on each scope
     Status,
-    /// Login with email/password (Supabase Auth direct)
+    /// Login with email/password, passkey, or browser approval
     Login {
         /// Email address
         #[arg(long)]
         email: Option<String>,
-        /// Use passkey (Touch ID) to unlock stored refresh token
+        /// Sign in with the registered passkey (challenge-response, presence-gated)
         #[arg(long)]
         passkey: bool,
+        /// Approve this login from a browser (works for SSO and headless boxes)
+        #[arg(long, conflicts_with_all = ["email", "passkey"])]
+        device: bool,
+        /// Device name shown on the approval page (default: hostname)
+        #[arg(long, requires = "device")]
+        device_name: Option<String>,
     },
-    /// Register a passkey (Ed25519 keypair + Touch ID gate)
+    /// Register a passkey (Ed25519 keypair)
     PasskeyRegister {
         /// Device name for this passkey
         #[arg(long)]
         device_name: Option<String>,
     },
+    /// Manage passkeys across devices (list, rotate, revoke)
+    Passkey {
+        #[command(subcommand)]
+        command: passkey::PasskeyCommands,
+    },
+    /// Configure and test the user-presence gate (biometrics, polkit, PAM, FIDO2, TOTP)
+    Presence {
+        #[command(subcommand)]
+        command: presence::PresenceCommands,
+    },
+    /// Manage named auth contexts (project + identity): list, use, add, rm
+    Context {
+        #[command(subcommand)]
+        command: auth_context::ContextCommands,
+    },
     /// Show current identity
     Whoami,
     /// Remove stored tokens and logout
-    Logout,
+    Logout {
+        /// Also revoke this machine's passkey (server row and local key)
+        #[arg(long)]
+        forget_passkey: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -292,6 +328,8 @@ enum SupabaseCommands {
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     let cfg_dir = cli.config_dir.clone().unwrap_or_else(default_config_dir);
+    presence::set_config_dir(cfg_dir.clone());
+    auth_context::select(cli.context.clone())?;
 
     let catalog = match load_catalog_from_dir(&cfg_dir) {
         Ok(c) => c,
@@ -353,9 +391,9 @@ fn main() -> anyhow::Result<()> {
         // ─── Auth ───────────────────────────────────────────────────────
         Commands::Auth { command } => {
             match &command {
-                AuthCommands::Unlock { ttl } => {
+                AuthCommands::Unlock { ttl, scope } => {
                     return auth_session::cmd_auth_session(
-                        auth_session::SessionCommands::Unlock { ttl: ttl.clone() },
+                        auth_session::SessionCommands::Unlock { ttl: ttl.clone(), scope: scope.clone() },
                         cli.json,
                     );
                 }
@@ -371,6 +409,12 @@ fn main() -> anyhow::Result<()> {
                         cli.json,
                     );
                 }
+                AuthCommands::Presence { command } => {
+                    return presence::cmd_presence(command, cli.json);
+                }
+                AuthCommands::Context { command } => {
+                    return auth_context::cmd_context(command, cli.json);
+                }
                 _ => {}
             }
 
@@ -378,26 +422,47 @@ fn main() -> anyhow::Result<()> {
             let client = SupabaseClient::new(config)?;
 
             match command {
-                AuthCommands::Unlock { .. } | AuthCommands::Lock | AuthCommands::Status => unreachable!(),
-                AuthCommands::Login { email, passkey } => {
-                    if passkey {
+                AuthCommands::Unlock { .. }
+                | AuthCommands::Lock
+                | AuthCommands::Status
+                | AuthCommands::Presence { .. }
+                | AuthCommands::Context { .. } => unreachable!(),
+                AuthCommands::Login { email, passkey, device, device_name } => {
+                    if device {
+                        auth_device::cmd_login_device(&client, device_name, cli.json)?;
+                    } else if passkey {
                         cmd_login_passkey(&client, cli.json)?;
                     } else {
                         let email = email.ok_or_else(|| {
-                            anyhow::anyhow!("--email <address> is required.\nUsage: logline auth login --email you@example.com")
+                            anyhow::anyhow!("--email <address> or --device is required.\nUsage: logline auth login --email you@example.com\n       logline auth login --device")
                         })?;
                         cmd_login_email(&client, &email, cli.json)?;
                     }
                 }
                 AuthCommands::PasskeyRegister { device_name } => {
-                    cmd_passkey_register(&client, device_name, cli.json)?;
+                    passkey::cmd_register(&client, device_name, cli.json)?;
+                }
+                AuthCommands::Passkey { command } => {
+                    passkey::cmd_passkey(&client, command, cli.json)?;
                 }
                 AuthCommands::Whoami => {
                     cmd_whoami(&client, cli.json)?;
                 }
-                AuthCommands::Logout => {
+                AuthCommands::Logout { forget_passkey } => {
+                    let local_device = passkey::local_device_name();
+                    if forget_passkey {
+                        if let Some(device) = &local_device {
+                            // Needs a valid session, so revoke before dropping tokens.
+                            passkey::cmd_passkey(&client, passkey::PasskeyCommands::Revoke { device: device.clone() }, cli.json)?;
+                        }
+                    }
                     delete_auth()?;
-                    pout(cli.json, serde_json::json!({"ok":true}), "Logged out. All local tokens removed.")?;
+                    let kept = if forget_passkey { None } else { local_device };
+                    let text = match &kept {
+                        Some(device) => format!("Logged out. Session tokens removed.\nPasskey for '{device}' kept (use --forget-passkey or `logline auth passkey revoke --device {device}` to remove it)."),
+                        None => "Logged out. Session tokens removed.".to_string(),
+                    };
+                    pout(cli.json, serde_json::json!({"ok":true, "passkey_kept": kept}), &text)?;
                 }
             }
         }
@@ -492,11 +557,8 @@ fn main() -> anyhow::Result<()> {
                 if token.trim().is_empty() {
                     anyhow::bail!("Token cannot be empty");
                 }
-                let entry = keyring::Entry::new("logline-cli", "supabase_access_token")
-                    .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-                entry.set_password(token.trim())
-                    .map_err(|e| anyhow::anyhow!("Failed to store in keychain: {e}"))?;
-                pout(cli.json, serde_json::json!({"ok": true}), "Supabase access token stored in OS keychain.")?;
+                secrets::store_credential("supabase_access_token", token.trim())?;
+                pout(cli.json, serde_json::json!({"ok": true}), "Supabase access token stored in the credential vault.")?;
             }
             SupabaseCommands::Check { workdir } => {
                 println!("supabase version:");
@@ -557,118 +619,28 @@ fn cmd_login_email(client: &SupabaseClient, email: &str, json: bool) -> anyhow::
 }
 
 fn cmd_login_passkey(client: &SupabaseClient, json: bool) -> anyhow::Result<()> {
-    let auth = load_auth().ok_or_else(|| {
-        anyhow::anyhow!("No stored session. Run `logline auth login --email` first, then register a passkey.")
+    let passkey = load_passkey().ok_or_else(|| {
+        anyhow::anyhow!("No passkey registered. Run `logline auth login --email` first, then `logline auth passkey-register`.")
     })?;
 
-    if load_passkey().is_none() {
-        anyhow::bail!("No passkey registered. Run `logline auth passkey-register` first.");
-    }
+    // Passkeys registered before user_id was recorded fall back to the stored session.
+    let user_id = passkey["user_id"]
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| load_auth().and_then(|a| a.user_id))
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine user for this passkey. Run `logline auth passkey-register` again."))?;
 
-    // Touch ID gate (macOS)
-    if cfg!(target_os = "macos") {
-        eprintln!("Touch ID required to unlock session...");
-        let result = std::process::Command::new("swift")
-            .arg("-e")
-            .arg(r#"
-import LocalAuthentication
-import Foundation
-let ctx = LAContext()
-var err: NSError?
-guard ctx.canEvaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, error: &err) else {
-    fputs("biometrics unavailable: \(err?.localizedDescription ?? "unknown")\n", stderr)
-    exit(1)
-}
-let sema = DispatchSemaphore(value: 0)
-var ok = false
-ctx.evaluatePolicy(.deviceOwnerAuthenticationWithBiometrics, localizedReason: "Logline CLI authentication") { success, _ in
-    ok = success
-    sema.signal()
-}
-sema.wait()
-exit(ok ? 0 : 1)
-"#)
-            .output();
-
-        match result {
-            Ok(out) if out.status.success() => {}
-            Ok(_) => anyhow::bail!("Touch ID authentication failed or was cancelled."),
-            Err(e) => {
-                eprintln!("Touch ID unavailable ({e}), falling back to Enter confirmation.");
-                eprint!("Press Enter to confirm identity: ");
-                let mut buf = String::new();
-                std::io::stdin().read_line(&mut buf)?;
-            }
-        }
-    } else {
-        eprint!("Press Enter to confirm identity: ");
-        let mut buf = String::new();
-        std::io::stdin().read_line(&mut buf)?;
-    }
+    presence::require_presence("Logline CLI — sign in with passkey")?;
 
-    let resp = client.refresh_token(&auth.refresh_token)?;
-    let now = now_secs();
-
-    let stored = StoredAuth {
-        access_token: resp.access_token.clone(),
-        refresh_token: resp.refresh_token,
-        user_id: Some(resp.user.id.clone()),
-        email: resp.user.email.clone(),
-        expires_at: Some(now + resp.expires_in),
-        auth_method: Some("passkey".into()),
-    };
-    save_auth(&stored)?;
+    // The presence check only gates use of the key; the daemon checks the signature.
+    let (user_id, email) = auth_device::login_with_passkey(client, &passkey, &user_id)?;
 
     pout(json, serde_json::json!({
         "ok": true,
-        "user_id": resp.user.id,
-        "email": resp.user.email,
-        "auth_method": "passkey",
-    }), &format!("Authenticated via passkey as {}", resp.user.email.as_deref().unwrap_or(&resp.user.id)))?;
-
-    Ok(())
-}
-
-fn cmd_passkey_register(client: &SupabaseClient, device_name: Option<String>, json: bool) -> anyhow::Result<()> {
-    let token = get_valid_token(client)?;
-    let user = client.get_user(&token)?;
-    let user_id = user["id"].as_str().ok_or_else(|| anyhow::anyhow!("Cannot determine user_id"))?;
-
-    use ed25519_dalek::SigningKey;
-    use rand::rngs::OsRng;
-
-    let signing_key = SigningKey::generate(&mut OsRng);
-    let public_key = signing_key.verifying_key();
-    let public_key_hex = hex::encode(public_key.as_bytes());
-    let private_key_hex = hex::encode(signing_key.to_bytes());
-
-    let device = device_name.unwrap_or_else(get_hostname);
-
-    let passkey_data = serde_json::json!({
-        "device_name": device,
-        "private_key": private_key_hex,
-        "public_key": public_key_hex,
-        "algorithm": "ed25519",
-    });
-
-    save_passkey(&passkey_data)?;
-
-    // Register public key in cli_passkey_credentials via PostgREST
-    let cred = serde_json::json!({
         "user_id": user_id,
-        "device_name": device,
-        "public_key": public_key_hex,
-        "algorithm": "ed25519",
-        "status": "active",
-    });
-
-    client.postgrest_upsert("cli_passkey_credentials", &cred, "user_id,device_name", &token)?;
-
-    pout(json, serde_json::json!({
-        "ok": true,
-        "device_name": device,
-        "public_key": public_key_hex,
-    }), &format!("Passkey registered for device '{}'\nPublic key: {}", device, public_key_hex))?;
+        "email": email,
+        "auth_method": "passkey",
+    }), &format!("Authenticated via passkey as {}", email.as_deref().unwrap_or(&user_id)))?;
 
     Ok(())
 }
@@ -682,6 +654,7 @@ fn cmd_whoami(client: &SupabaseClient, json: bool) -> anyhow::Result<()> {
     } else {
         let id = user["id"].as_str().unwrap_or("?");
         let email = user["email"].as_str().unwrap_or("?");
+        println!("Context: {}", auth_context::active_name());
         println!("User ID: {id}");
         println!("Email:   {email}");
     }
@@ -1062,7 +1035,7 @@ fn cmd_ready(pipeline: &str, json: bool) -> anyhow::Result<()> {
     let identity = auth_session::load_identity();
     let logged_in = identity.is_some();
     let passkey_ok = identity.as_ref().is_some_and(|i| i.auth_method == "passkey");
-    let founder_blocked = identity.as_ref().is_some_and(|i| i.is_founder);
+    let founder_blocked = identity.as_ref().is_some_and(auth_session::AuthIdentity::is_founder);
 
     if !logged_in {
         issues.push("Not logged in. Fix: logline auth login --passkey".into());
@@ -1075,6 +1048,9 @@ fn cmd_ready(pipeline: &str, json: bool) -> anyhow::Result<()> {
     if founder_blocked {
         issues.push("Founder/god mode blocked for infra. Fix: use operator/service account.".into());
     }
+    if let Some(auth_session::FounderStatus::Unknown(why)) = identity.as_ref().map(|i| &i.founder) {
+        issues.push(format!("Founder status unknown ({why}). Infra gates may refuse to run."));
+    }
 
     // 3. Pipeline exists
     let pipeline_file = std::env::current_dir()
@@ -1227,14 +1203,18 @@ fn parse_key_val(s: &str) -> Result<(String, String), String> {
     Ok((s[..pos].to_string(), s[pos + 1..].to_string()))
 }
 
-/// Gate: require an active unlocked session. Used by command modules.
-pub fn require_unlocked() -> anyhow::Result<commands::auth_session::SessionToken> {
-    commands::auth_session::require_unlocked()
+/// Gate: require an unlocked session granting `scope`. Used by command modules.
+pub fn require_unlocked(scope: &str) -> anyhow::Result<commands::auth_session::SessionToken> {
+    commands::auth_session::require_unlocked(scope)
 }
 
-/// Uber-gate: session + passkey + non-founder. Used by deploy/cicd/db commands.
-pub fn require_infra_identity() -> anyhow::Result<(commands::auth_session::SessionToken, commands::auth_session::AuthIdentity)> {
-    commands::auth_session::require_infra_identity()
+/// Uber-gate: scoped session + passkey + non-founder. Used by deploy/cicd/db commands.
+/// `on_unknown` is the gate's choice when founder status cannot be verified.
+pub fn require_infra_identity(
+    scope: &str,
+    on_unknown: commands::auth_session::OnUnknownFounder,
+) -> anyhow::Result<(commands::auth_session::SessionToken, commands::auth_session::AuthIdentity)> {
+    commands::auth_session::require_infra_identity(scope, on_unknown)
 }
 
 pub fn pout(json_mode: bool, value: serde_json::Value, text: &str) -> anyhow::Result<()> {
@@ -1282,11 +1262,9 @@ fn apply_supabase_env(cmd: &mut Command, _workdir: Option<&PathBuf>) {
         return;
     }
 
-    if let Ok(entry) = keyring::Entry::new("logline-cli", "supabase_access_token") {
-        if let Ok(token) = entry.get_password() {
-            cmd.env("SUPABASE_ACCESS_TOKEN", token);
-            return;
-        }
+    if let Some(token) = secrets::load_credential("supabase_access_token") {
+        cmd.env("SUPABASE_ACCESS_TOKEN", token);
+        return;
     }
 
     eprintln!("Warning: No SUPABASE_ACCESS_TOKEN found in keychain or env.");
@@ -1,3 +1,4 @@
+use std::fmt::Write as _;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -6,6 +7,8 @@ use anyhow::{bail, Context};
 use reqwest::blocking::{Client, Response};
 use serde::{Deserialize, Serialize};
 
+use crate::commands::auth_context;
+
 // ─── Config ─────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,7 +18,39 @@ pub struct SupabaseConfig {
 }
 
 impl SupabaseConfig {
+    /// Project of the active auth context. `--context` beats the env vars;
+    /// a context made current with `auth context use` only beats the files.
     pub fn from_env_or_file() -> anyhow::Result<Self> {
+        let ctx = auth_context::active_context();
+        if auth_context::explicitly_selected() {
+            if let Some(ctx) = ctx {
+                return Ok(Self { url: ctx.url, anon_key: ctx.anon_key });
+            }
+            return Self::legacy();
+        }
+        if let Some(from_env) = Self::from_env() {
+            return Ok(from_env);
+        }
+        if let Some(ctx) = ctx {
+            return Ok(Self { url: ctx.url, anon_key: ctx.anon_key });
+        }
+        Self::legacy()
+    }
+
+    fn from_env() -> Option<Self> {
+        if let (Ok(url), Ok(key)) = (
+            std::env::var("NEXT_PUBLIC_SUPABASE_URL"),
+            std::env::var("NEXT_PUBLIC_SUPABASE_ANON_KEY"),
+        ) {
+            if !url.is_empty() && !key.is_empty() {
+                return Some(Self { url, anon_key: key });
+            }
+        }
+        None
+    }
+
+    /// Project of the implicit `default` context: env vars, config.json, then .env files.
+    pub fn legacy() -> anyhow::Result<Self> {
         if let (Ok(url), Ok(key)) = (
             std::env::var("NEXT_PUBLIC_SUPABASE_URL"),
             std::env::var("NEXT_PUBLIC_SUPABASE_ANON_KEY"),
@@ -77,23 +112,20 @@ pub fn auth_path() -> PathBuf {
     config_dir().join("auth.json")
 }
 
-const KEYRING_SERVICE: &str = "logline-cli";
+// Both live in the active auth context's keyring namespace.
 const KEYRING_AUTH_USER: &str = "auth_tokens";
 const KEYRING_PASSKEY_USER: &str = "passkey_ed25519";
 
 pub fn load_auth() -> Option<StoredAuth> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_AUTH_USER).ok()?;
-    let json = entry.get_password().ok()?;
+    let json = auth_context::entry(KEYRING_AUTH_USER).get()?;
     serde_json::from_str(&json).ok()
 }
 
 pub fn save_auth(auth: &StoredAuth) -> anyhow::Result<()> {
     let json = serde_json::to_string(auth)?;
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_AUTH_USER)
-        .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-    entry
-        .set_password(&json)
-        .map_err(|e| anyhow::anyhow!("Failed to store auth in keychain: {e}"))?;
+    auth_context::entry(KEYRING_AUTH_USER)
+        .set(&json)
+        .map_err(|e| anyhow::anyhow!("Failed to store auth: {e}"))?;
 
     // Clean up any legacy file-based auth
     let path = auth_path();
@@ -104,17 +136,18 @@ pub fn save_auth(auth: &StoredAuth) -> anyhow::Result<()> {
 }
 
 pub fn delete_auth() -> anyhow::Result<()> {
-    if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, KEYRING_AUTH_USER) {
-        let _ = entry.delete_credential();
-    }
+    let _ = auth_context::entry(KEYRING_AUTH_USER).delete();
     let path = auth_path();
     if path.exists() {
         fs::remove_file(path)?;
     }
+    Ok(())
+}
 
-    if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, KEYRING_PASSKEY_USER) {
-        let _ = entry.delete_credential();
-    }
+/// Remove the local passkey. Callers should revoke the server row as well
+/// (see `commands::passkey`) so the two stay consistent.
+pub fn delete_passkey() -> anyhow::Result<()> {
+    let _ = auth_context::entry(KEYRING_PASSKEY_USER).delete();
     let passkey_path = config_dir().join("passkey.json");
     if passkey_path.exists() {
         fs::remove_file(passkey_path)?;
@@ -123,18 +156,15 @@ pub fn delete_auth() -> anyhow::Result<()> {
 }
 
 pub fn load_passkey() -> Option<serde_json::Value> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_PASSKEY_USER).ok()?;
-    let json = entry.get_password().ok()?;
+    let json = auth_context::entry(KEYRING_PASSKEY_USER).get()?;
     serde_json::from_str(&json).ok()
 }
 
 pub fn save_passkey(data: &serde_json::Value) -> anyhow::Result<()> {
     let json = serde_json::to_string(data)?;
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_PASSKEY_USER)
-        .map_err(|e| anyhow::anyhow!("Keychain error: {e}"))?;
-    entry
-        .set_password(&json)
-        .map_err(|e| anyhow::anyhow!("Failed to store passkey in keychain: {e}"))?;
+    auth_context::entry(KEYRING_PASSKEY_USER)
+        .set(&json)
+        .map_err(|e| anyhow::anyhow!("Failed to store passkey: {e}"))?;
 
     // Clean up any legacy file-based passkey
     let path = config_dir().join("passkey.json");
@@ -247,6 +277,18 @@ impl SupabaseClient {
         Ok(resp.json().unwrap_or(serde_json::json!({"ok": true})))
     }
 
+    pub fn postgrest_update(
+        &self,
+        table: &str,
+        query: &str,
+        body: &serde_json::Value,
+        access_token: &str,
+    ) -> anyhow::Result<serde_json::Value> {
+        let url = format!("{}/rest/v1/{}?{}", self.config.url, table, query);
+        let resp = self.postgrest_request("PATCH", &url, access_token, Some(body))?;
+        Ok(resp.json().unwrap_or(serde_json::json!([])))
+    }
+
     pub fn postgrest_upsert(
         &self,
         table: &str,
@@ -403,6 +445,19 @@ pub fn get_valid_token(client: &SupabaseClient) -> anyhow::Result<String> {
 
 // ─── Helpers ────────────────────────────────────────────────────────────────
 
+/// Percent-encode a value for a `PostgREST` query string (`col=eq.<value>`).
+pub fn encode_query_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{b:02X}");
+        }
+    }
+    out
+}
+
 fn parse_env_value(content: &str, key: &str) -> Option<String> {
     for raw_line in content.lines() {
         let line = raw_line.trim();
@@ -22,6 +22,143 @@ impl Default for RuntimePolicy {
     }
 }
 
+/// User-presence backend used to gate `auth unlock` and passkey use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresenceBackend {
+    /// First available backend for the platform.
+    #[default]
+    Auto,
+    MacosBiometrics,
+    Polkit,
+    Pam,
+    Fido2,
+    Totp,
+    /// Press Enter. Proves nothing; only when explicitly configured.
+    Confirm,
+}
+
+/// What to do when no human can answer a prompt (CI, no TTY).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeadlessPolicy {
+    /// Refuse to run privileged commands.
+    #[default]
+    Refuse,
+    /// Skip the presence check (trusted runners only).
+    Allow,
+}
+
+/// `[auth.presence]` section of runtime.toml.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresenceConfig {
+    pub backend: PresenceBackend,
+    pub headless: HeadlessPolicy,
+    /// hidraw node of the security key; scanned when unset.
+    pub fido2_device: Option<String>,
+    pub pam_service: String,
+    pub polkit_action: String,
+    pub timeout_seconds: u64,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            backend: PresenceBackend::Auto,
+            headless: HeadlessPolicy::Refuse,
+            fido2_device: None,
+            pam_service: "login".to_string(),
+            polkit_action: "org.freedesktop.policykit.exec".to_string(),
+            timeout_seconds: 30,
+        }
+    }
+}
+
+/// Where the CLI keeps credentials.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VaultBackend {
+    /// OS keyring (macOS Keychain, Secret Service, Windows Credential Manager).
+    #[default]
+    Keyring,
+    /// One encrypted file in the config dir, for hosts without a keyring daemon.
+    File,
+}
+
+/// How the file vault key is obtained.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VaultKey {
+    /// Argon2id over a passphrase (prompted, or `LOGLINE_VAULT_PASSPHRASE`).
+    #[default]
+    Passphrase,
+    /// An age X25519 identity file.
+    Age,
+}
+
+/// `[auth.vault]` section of runtime.toml.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultConfig {
+    pub backend: VaultBackend,
+    pub key: VaultKey,
+    /// Vault file; `vault.enc` in the config dir when unset.
+    pub path: Option<String>,
+    /// Identity file for `key = "age"`.
+    pub age_identity: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawRuntimeAuth {
+    #[serde(default)]
+    auth: RawAuthSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawAuthSection {
+    #[serde(default)]
+    presence: PresenceConfig,
+    #[serde(default)]
+    vault: VaultConfig,
+}
+
+fn load_auth_section(dir: &Path) -> Result<RawAuthSection, LoglineError> {
+    let path = dir.join("runtime.toml");
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(RawAuthSection::default()),
+        Err(e) => {
+            return Err(LoglineError::Internal(format!(
+                "failed to read {}: {e}",
+                path.display()
+            )));
+        }
+    };
+    let raw: RawRuntimeAuth = toml::from_str(&content).map_err(|e| {
+        LoglineError::Validation(format!("invalid TOML in {}: {e}", path.display()))
+    })?;
+    Ok(raw.auth)
+}
+
+/// Read `[auth.presence]` from `runtime.toml` in `dir`; defaults if the file is missing.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or is invalid TOML.
+pub fn load_presence_config(dir: &Path) -> Result<PresenceConfig, LoglineError> {
+    Ok(load_auth_section(dir)?.presence)
+}
+
+/// Read `[auth.vault]` from `runtime.toml` in `dir`; defaults if the file is missing.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or is invalid TOML.
+pub fn load_vault_config(dir: &Path) -> Result<VaultConfig, LoglineError> {
+    Ok(load_auth_section(dir)?.vault)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     pub id: String,
@@ -0,0 +1,84 @@
+-- Migration 005: Richer custom_access_token claims
+-- - tenant_id: primary tenant (workspace_id kept for existing consumers)
+-- - app_memberships: every app membership in the primary tenant, with role
+-- - capabilities: user_capabilities rows (e.g. founder)
+-- Lets services and the CLI authorize from verified token claims instead of
+-- a PostgREST round trip per check.
+-- Depends on: 003_auth_hooks
+
+begin;
+
+create or replace function app.custom_access_token(event jsonb)
+returns jsonb
+language plpgsql
+stable
+set search_path = public
+as $$
+declare
+  v_user_id      text;
+  v_tenant_id    text;
+  v_app_id       text;
+  v_memberships  jsonb;
+  v_capabilities jsonb;
+  v_claims       jsonb;
+begin
+  v_user_id := event->>'user_id';
+  v_claims  := event->'claims';
+
+  -- Capabilities are global (not tenant-scoped).
+  select coalesce(jsonb_agg(capability order by capability), '[]'::jsonb)
+    into v_capabilities
+  from user_capabilities
+  where user_id = v_user_id;
+
+  v_claims := v_claims || jsonb_build_object('capabilities', v_capabilities);
+
+  -- Resolve first tenant membership (alphabetical for determinism).
+  select tenant_id into v_tenant_id
+  from tenant_memberships
+  where user_id = v_user_id
+  order by tenant_id asc
+  limit 1;
+
+  if v_tenant_id is not null then
+    v_claims := v_claims || jsonb_build_object(
+      'tenant_id', v_tenant_id,
+      'workspace_id', v_tenant_id
+    );
+
+    select coalesce(
+             jsonb_agg(
+               jsonb_build_object('tenant_id', tenant_id, 'app_id', app_id, 'role', role)
+               order by app_id
+             ),
+             '[]'::jsonb
+           )
+      into v_memberships
+    from app_memberships
+    where user_id = v_user_id
+      and tenant_id = v_tenant_id;
+
+    v_claims := v_claims || jsonb_build_object('app_memberships', v_memberships);
+
+    -- Resolve first app membership for this tenant.
+    select app_id into v_app_id
+    from app_memberships
+    where user_id = v_user_id
+      and tenant_id = v_tenant_id
+    order by app_id asc
+    limit 1;
+
+    if v_app_id is not null then
+      v_claims := v_claims || jsonb_build_object('app_id', v_app_id);
+    end if;
+  end if;
+
+  return jsonb_build_object('claims', v_claims);
+end;
+$$;
+
+grant select on user_capabilities to supabase_auth_admin;
+
+revoke execute on function app.custom_access_token(jsonb) from authenticated, anon, public;
+
+commit;
@@ -0,0 +1,57 @@
+-- Migration 006: CLI passkey credentials lifecycle
+-- - Creates cli_passkey_credentials (previously only defined in db/schema.ts)
+-- - One *active* key per (user, device); revoked keys are kept for audit,
+--   so rotation can insert a new key after marking the old one revoked
+-- - Owners can list, register and revoke their own keys (incl. lost devices)
+-- Depends on: 001_base_tables, 002_rbac_rls
+
+begin;
+
+create table if not exists cli_passkey_credentials (
+  credential_id text primary key default gen_random_uuid()::text,
+  user_id       text not null references users(user_id) on delete cascade,
+  device_name   text not null,
+  public_key    text not null,
+  algorithm     text not null default 'ed25519',
+  status        text not null default 'active' check (status in ('active', 'revoked')),
+  created_at    timestamptz not null default now(),
+  last_used_at  timestamptz
+);
+
+alter table cli_passkey_credentials
+  add column if not exists revoked_at timestamptz;
+
+-- Replace the full unique constraint with a partial one on active keys.
+alter table cli_passkey_credentials
+  drop constraint if exists cli_passkey_credentials_user_id_device_name_unique;
+
+create unique index if not exists uq_cli_passkey_active_device
+  on cli_passkey_credentials (user_id, device_name)
+  where status = 'active';
+
+create index if not exists idx_cli_passkey_user
+  on cli_passkey_credentials (user_id, status);
+
+alter table cli_passkey_credentials enable row level security;
+
+drop policy if exists cli_passkey_select_owner on cli_passkey_credentials;
+create policy cli_passkey_select_owner on cli_passkey_credentials
+  for select using (user_id = (select app.current_user_id()));
+
+drop policy if exists cli_passkey_insert_owner on cli_passkey_credentials;
+create policy cli_passkey_insert_owner on cli_passkey_credentials
+  for insert with check (
+    user_id = (select app.current_user_id())
+    and status = 'active'
+  );
+
+-- Owners may only revoke; keys are never re-activated or edited in place.
+drop policy if exists cli_passkey_revoke_owner on cli_passkey_credentials;
+create policy cli_passkey_revoke_owner on cli_passkey_credentials
+  for update using (user_id = (select app.current_user_id()))
+  with check (
+    user_id = (select app.current_user_id())
+    and status = 'revoked'
+  );
+
+commit;